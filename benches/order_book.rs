@@ -0,0 +1,61 @@
+use chrono::Utc;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use marketmakerlib::{
+    binance::data::{DepthSnapshot, DepthUpdate, OfferData},
+    order_book_state::OrderBookState,
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::hint::black_box;
+
+/// Levels per side, about what a 5000 level snapshot leaves after a while
+const DEPTH: i64 = 5_000;
+
+fn deep_book() -> OrderBookState {
+    let level = |price: Decimal| OfferData {
+        price,
+        size: dec!(1),
+    };
+    let mut book = OrderBookState::default();
+    book.apply_snapshot(DepthSnapshot {
+        last_update_id: 1,
+        bids: (0..DEPTH)
+            .map(|i| level(dec!(10000) - Decimal::new(i, 2)))
+            .collect(),
+        asks: (0..DEPTH)
+            .map(|i| level(dec!(10000.01) + Decimal::new(i, 2)))
+            .collect(),
+    });
+    book
+}
+
+fn bid_update(price: Decimal, size: Decimal) -> DepthUpdate {
+    DepthUpdate {
+        event_time: Utc::now(),
+        symbol: "BTCUSDT".to_string(),
+        first_update_id: 2,
+        final_update_id: 2,
+        bids: vec![OfferData { price, size }],
+        asks: Vec::new(),
+    }
+}
+
+/// Most updates land behind the touch and skip the metric refresh, compare against the
+/// ones that move the best bid
+fn apply_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_update_changes");
+    for (name, price) in [("behind_touch", dec!(9990)), ("at_touch", dec!(10000))] {
+        let update = bid_update(price, dec!(2));
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                deep_book,
+                |book| book.apply_update_changes(black_box(&update)).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, apply_update);
+criterion_main!(benches);
@@ -1,6 +1,7 @@
-use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use chrono::{DateTime, Utc, serde::ts_milliseconds};
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize, ser::SerializeTuple};
+use tracing::warn;
 
 #[derive(Debug, Deserialize)]
 pub struct OfferData {
@@ -10,7 +11,45 @@ pub struct OfferData {
     pub size: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+/// Serialized as Binance sends levels: a `["price", "size"]` pair of strings
+impl Serialize for OfferData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut level = serializer.serialize_tuple(2)?;
+        level.serialize_element(&self.price.to_string())?;
+        level.serialize_element(&self.size.to_string())?;
+        level.end()
+    }
+}
+
+/// A level as received, kept when it doesn't parse so the rest of the message survives
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawLevel {
+    Valid(OfferData),
+    Malformed(serde_json::Value),
+}
+
+/// Deserializes a list of levels, skipping (and logging) any that fail to parse.
+///
+/// One bad price or size would otherwise fail the whole message, losing its update ids and
+/// leaving a sequence gap that forces a resync.
+fn skip_malformed_levels<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<OfferData>, D::Error> {
+    let levels = Vec::<RawLevel>::deserialize(deserializer)?;
+    Ok(levels
+        .into_iter()
+        .filter_map(|level| match level {
+            RawLevel::Valid(level) => Some(level),
+            RawLevel::Malformed(value) => {
+                warn!("Skipping malformed level: {}", value);
+                None
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct DepthUpdate {
     #[serde(rename = "E", with = "ts_milliseconds")]
     pub event_time: DateTime<Utc>,
@@ -20,16 +59,18 @@ pub struct DepthUpdate {
     pub first_update_id: u64,
     #[serde(rename = "u")]
     pub final_update_id: u64,
-    #[serde(rename = "b")]
+    #[serde(rename = "b", deserialize_with = "skip_malformed_levels")]
     pub bids: Vec<OfferData>,
-    #[serde(rename = "a")]
+    #[serde(rename = "a", deserialize_with = "skip_malformed_levels")]
     pub asks: Vec<OfferData>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DepthSnapshot {
     pub last_update_id: u64,
+    #[serde(deserialize_with = "skip_malformed_levels")]
     pub bids: Vec<OfferData>,
+    #[serde(deserialize_with = "skip_malformed_levels")]
     pub asks: Vec<OfferData>,
 }
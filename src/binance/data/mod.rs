@@ -1,3 +1,8 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::StreamError;
+
 mod depth_update;
 mod historical_data;
 mod kline;
@@ -6,20 +11,50 @@ mod ticker;
 mod trade;
 
 pub use depth_update::{DepthSnapshot, DepthUpdate, OfferData};
-pub use kline::KlineEventData;
+pub use kline::{KlineData, KlineEventData};
 pub use price::AveragePrice;
 pub use ticker::{BookTickerEvent, MiniTickerData, TickerData, WindowTickerData};
 pub use trade::{AggregateTrade, TradeEventData};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum BinanceEvent {
     Trade(TradeEventData),
     AggTrade(AggregateTrade),
     Kline(KlineEventData),
     AvgPrice(AveragePrice),
     DepthUpdate(DepthUpdate),
+    /// Top levels of the book from a `@depth<N>` stream
+    PartialDepth(DepthSnapshot),
     BookTicker(BookTickerEvent),
     MiniTicker(MiniTickerData),
     Ticker(TickerData),
     WindowTicker(WindowTickerData),
+    /// Binance rejected a request on the stream
+    StreamError(StreamError),
+    /// Binance accepted the request with this id, e.g. a subscription
+    RequestAck(u64),
+}
+
+impl BinanceEvent {
+    /// When Binance generated the event, `None` for events without a timestamp
+    pub fn event_time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            BinanceEvent::Trade(trade) => Some(trade.event_time),
+            BinanceEvent::AggTrade(trade) => Some(trade.event_time),
+            BinanceEvent::Kline(kline) => Some(kline.event_time),
+            BinanceEvent::AvgPrice(price) => Some(price.event_time),
+            BinanceEvent::DepthUpdate(update) => Some(update.event_time),
+            BinanceEvent::MiniTicker(ticker) => millis(ticker.event_time),
+            BinanceEvent::Ticker(ticker) => millis(ticker.event_time),
+            BinanceEvent::WindowTicker(ticker) => millis(ticker.event_time),
+            BinanceEvent::PartialDepth(_)
+            | BinanceEvent::BookTicker(_)
+            | BinanceEvent::StreamError(_)
+            | BinanceEvent::RequestAck(_) => None,
+        }
+    }
+}
+
+fn millis(timestamp: u64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(i64::try_from(timestamp).ok()?)
 }
@@ -1,8 +1,8 @@
-use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use chrono::{DateTime, Utc, serde::ts_milliseconds};
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 //#[serde(deny_unknown_fields)]
 pub struct TradeEventData {
     // #[serde(rename = "e")]
@@ -23,7 +23,7 @@ pub struct TradeEventData {
     pub buyer_market_maker: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct AggregateTrade {
     #[serde(rename = "E", with = "ts_milliseconds")]
     pub event_time: DateTime<Utc>,
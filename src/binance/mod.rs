@@ -1,23 +1,33 @@
 use data::{
-    AggregateTrade, AveragePrice, BinanceEvent, BookTickerEvent, DepthUpdate, KlineEventData,
-    MiniTickerData, TickerData, TradeEventData, WindowTickerData,
+    AggregateTrade, AveragePrice, BinanceEvent, BookTickerEvent, DepthSnapshot, DepthUpdate,
+    KlineEventData, MiniTickerData, TickerData, TradeEventData, WindowTickerData,
 };
 use rust_decimal::Decimal;
-use serde::{Deserialize, ser::Error};
-use std::collections::BTreeMap;
-use tracing::debug;
+use serde::{Deserialize, Serialize, ser::Error};
+use std::{collections::BTreeMap, fmt};
+use tracing::{debug, warn};
+
+use crate::recent_trades::Trade;
 
 pub mod data;
+pub mod rest;
+pub mod subscription;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct VolumeProfile {
     // Price -> Volume data
     volume_by_price: BTreeMap<Decimal, VolumeData>,
     // Configurable price bucket size
     bucket_size: Decimal,
+    /// Half width of the tracked price band around the mid, in basis points
+    price_band_bps: Option<Decimal>,
+    /// Lowest and highest price tracked, set by [`VolumeProfile::recenter`]
+    band: Option<(Decimal, Decimal)>,
+    /// Highest exchange trade id counted, anything at or below it is a duplicate
+    last_trade_id: Option<u64>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct VolumeData {
     total_volume: Decimal,
     buy_volume: Decimal,
@@ -28,19 +38,163 @@ pub struct VolumeData {
     ask_volume_delta: Decimal,
 }
 
+impl VolumeData {
+    pub fn total_volume(&self) -> Decimal {
+        self.total_volume
+    }
+
+    pub fn buy_volume(&self) -> Decimal {
+        self.buy_volume
+    }
+
+    pub fn sell_volume(&self) -> Decimal {
+        self.sell_volume
+    }
+
+    pub fn trade_count(&self) -> u64 {
+        self.trade_count
+    }
+
+    pub fn bid_volume_delta(&self) -> Decimal {
+        self.bid_volume_delta
+    }
+
+    pub fn ask_volume_delta(&self) -> Decimal {
+        self.ask_volume_delta
+    }
+}
+
 impl VolumeProfile {
     pub fn new(bucket_size: Decimal) -> Self {
         Self {
             volume_by_price: BTreeMap::new(),
             bucket_size,
+            price_band_bps: None,
+            band: None,
+            last_trade_id: None,
         }
     }
 
+    /// Only tracks prices within `bps` basis points either side of the mid, keeping memory
+    /// bounded on long runs. Nothing is filtered until the first [`Self::recenter`].
+    pub fn with_price_band_bps(mut self, bps: Decimal) -> Self {
+        self.price_band_bps = Some(bps);
+        self
+    }
+
+    /// Centres the price band on `mid`, evicting buckets that fell entirely outside it
+    pub fn recenter(&mut self, mid: Decimal) {
+        let Some(bps) = self.price_band_bps else {
+            return;
+        };
+        let half_width = mid * bps / Decimal::from(10_000);
+        let (low, high) = (mid - half_width, mid + half_width);
+        self.band = Some((low, high));
+
+        self.volume_by_price = self.volume_by_price.split_off(&self.get_price_bucket(low));
+        let evicted = self
+            .volume_by_price
+            .split_off(&(self.get_price_bucket(high) + self.bucket_size));
+        if !evicted.is_empty() {
+            debug!(
+                "Evicted {} volume profile buckets above {}",
+                evicted.len(),
+                high
+            );
+        }
+    }
+
+    /// Price band currently tracked, `None` before the first recenter or without a band
+    pub fn band(&self) -> Option<(Decimal, Decimal)> {
+        self.band
+    }
+
+    fn in_band(&self, price: Decimal) -> bool {
+        self.band
+            .is_none_or(|(low, high)| (low..=high).contains(&price))
+    }
+
     pub fn get_price_bucket(&self, price: Decimal) -> Decimal {
         (price / self.bucket_size).floor() * self.bucket_size
     }
 
+    pub fn bucket_size(&self) -> Decimal {
+        self.bucket_size
+    }
+
+    /// Buckets in ascending price order, keyed by their lower edge
+    pub fn levels(&self) -> impl Iterator<Item = (Decimal, &VolumeData)> {
+        self.volume_by_price
+            .iter()
+            .map(|(&price, data)| (price, data))
+    }
+
+    pub fn total_buy_volume(&self) -> Decimal {
+        self.volume_by_price
+            .values()
+            .map(|data| data.buy_volume)
+            .sum()
+    }
+
+    pub fn total_sell_volume(&self) -> Decimal {
+        self.volume_by_price
+            .values()
+            .map(|data| data.sell_volume)
+            .sum()
+    }
+
+    /// Point of control: the bucket with the most traded volume, the lowest on a tie
+    pub fn point_of_control(&self) -> Option<Decimal> {
+        self.volume_by_price
+            .iter()
+            .filter(|(_, data)| data.total_volume > Decimal::ZERO)
+            .rev()
+            .max_by_key(|(_, data)| data.total_volume)
+            .map(|(&price, _)| price)
+    }
+
+    /// Lowest and highest bucket of the value area holding `fraction` of the traded volume.
+    ///
+    /// Grown outwards from the point of control, each step taking whichever neighbouring
+    /// bucket traded more. `None` when nothing has traded.
+    pub fn value_area(&self, fraction: Decimal) -> Option<(Decimal, Decimal)> {
+        let levels: Vec<(Decimal, Decimal)> = self
+            .volume_by_price
+            .iter()
+            .map(|(&price, data)| (price, data.total_volume))
+            .collect();
+        let poc = self.point_of_control()?;
+        let poc_index = levels.iter().position(|&(price, _)| price == poc)?;
+        let target = levels.iter().map(|(_, volume)| volume).sum::<Decimal>() * fraction;
+
+        let (mut low, mut high) = (poc_index, poc_index);
+        let mut covered = levels[poc_index].1;
+        while covered < target {
+            let below = low.checked_sub(1).map(|i| levels[i].1);
+            let above = levels.get(high + 1).map(|&(_, volume)| volume);
+            match (below, above) {
+                (Some(below), Some(above)) if below > above => {
+                    low -= 1;
+                    covered += below;
+                }
+                (_, Some(above)) => {
+                    high += 1;
+                    covered += above;
+                }
+                (Some(below), None) => {
+                    low -= 1;
+                    covered += below;
+                }
+                (None, None) => break,
+            }
+        }
+        Some((levels[low].0, levels[high].0))
+    }
+
     pub fn update_from_agg_trade(&mut self, trade: &data::AggregateTrade) {
+        if !self.is_new_trade(trade.last_trade_id) || !self.in_band(trade.price) {
+            return;
+        }
         let bucket_price = self.get_price_bucket(trade.price);
         let data = self.volume_by_price.entry(bucket_price).or_default();
 
@@ -54,6 +208,9 @@ impl VolumeProfile {
     }
 
     pub fn update_from_trade(&mut self, trade: &TradeEventData) {
+        if !self.is_new_trade(trade.trade_id) || !self.in_band(trade.price) {
+            return;
+        }
         let bucket_price = self.get_price_bucket(trade.price);
         let data = self.volume_by_price.entry(bucket_price).or_default();
 
@@ -67,17 +224,45 @@ impl VolumeProfile {
         data.trade_count += 1;
     }
 
+    /// Adds a trade from the merged trade feed
+    pub fn update_from(&mut self, trade: &Trade) {
+        if !self.is_new_trade(trade.last_trade_id()) || !self.in_band(trade.price) {
+            return;
+        }
+        let bucket_price = self.get_price_bucket(trade.price);
+        let data = self.volume_by_price.entry(bucket_price).or_default();
+
+        data.total_volume += trade.quantity;
+        if trade.buyer_market_maker {
+            data.sell_volume += trade.quantity;
+        } else {
+            data.buy_volume += trade.quantity;
+        }
+        data.trade_count += trade.num_trades();
+    }
+
+    /// Records `last_trade_id` as counted, `false` if it already was, so the redelivered
+    /// trades after a reconnect or from overlapping streams aren't counted twice
+    fn is_new_trade(&mut self, last_trade_id: u64) -> bool {
+        if self.last_trade_id.is_some_and(|last| last_trade_id <= last) {
+            debug!("Ignoring duplicate trade {}", last_trade_id);
+            return false;
+        }
+        self.last_trade_id = Some(last_trade_id);
+        true
+    }
+
     pub fn update_from_depth(&mut self, update: &DepthUpdate) {
         // Accumulate deltas per bucket: (bid_delta, ask_delta)
         let mut accum: BTreeMap<Decimal, (Decimal, Decimal)> = BTreeMap::new();
 
-        for bid in &update.bids {
+        for bid in update.bids.iter().filter(|bid| self.in_band(bid.price)) {
             let bucket_price = self.get_price_bucket(bid.price);
             let (bid_delta, _) = accum.entry(bucket_price).or_default();
             *bid_delta += bid.size;
         }
 
-        for ask in &update.asks {
+        for ask in update.asks.iter().filter(|ask| self.in_band(ask.price)) {
             let bucket_price = self.get_price_bucket(ask.price);
             let (_, ask_delta) = accum.entry(bucket_price).or_default();
             *ask_delta += ask.size;
@@ -102,12 +287,78 @@ pub enum BinanceMessage {
     Direct(serde_json::Value),
 }
 #[derive(Debug, Deserialize)]
-#[serde(untagged)]
+#[serde(from = "RawProtocolMessage")]
 pub enum ProtocolMessage {
     Heartbeat(u64),
-    Response { result: serde_json::Value, id: u64 },
+    Response {
+        result: serde_json::Value,
+        id: u64,
+    },
+    /// The server rejected a request, e.g. a subscription to an invalid symbol
+    Error {
+        code: i64,
+        msg: String,
+        id: Option<u64>,
+    },
+}
+
+/// Wire format of [`ProtocolMessage`], errors arrive as `{"error": {"code", "msg"}, "id"}`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawProtocolMessage {
+    Heartbeat(u64),
+    Response {
+        result: serde_json::Value,
+        id: u64,
+    },
+    Error {
+        error: ErrorBody,
+        #[serde(default)]
+        id: Option<u64>,
+    },
 }
 
+#[derive(Deserialize)]
+struct ErrorBody {
+    code: i64,
+    msg: String,
+}
+
+impl From<RawProtocolMessage> for ProtocolMessage {
+    fn from(raw: RawProtocolMessage) -> Self {
+        match raw {
+            RawProtocolMessage::Heartbeat(timestamp) => Self::Heartbeat(timestamp),
+            RawProtocolMessage::Response { result, id } => Self::Response { result, id },
+            RawProtocolMessage::Error { error, id } => Self::Error {
+                code: error.code,
+                msg: error.msg,
+                id,
+            },
+        }
+    }
+}
+
+/// An error reported by Binance on the stream, such as a failed subscription
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StreamError {
+    pub code: i64,
+    pub msg: String,
+    /// Id of the request that failed, if Binance could tell
+    pub id: Option<u64>,
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Binance error {}: {}", self.code, self.msg)?;
+        if let Some(id) = self.id {
+            write!(f, " (request id {id})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StreamError {}
+
 impl BinanceMessage {
     pub fn from_str_into_market_data(
         data: &str,
@@ -122,17 +373,21 @@ impl BinanceMessage {
                 // Fallback to parsing the data field directly
                 Self::fallback_on_data(data).map_err(Option::Some)
             }
-            BinanceMessage::Protocol(msg) => {
-                match msg {
-                    ProtocolMessage::Heartbeat(timestamp) => {
-                        debug!("Received heartbeat at {}", timestamp);
-                    }
-                    ProtocolMessage::Response { result, id } => {
-                        debug!("Received response message: id={}, result={:?}", id, result);
-                    }
+            BinanceMessage::Protocol(msg) => match msg {
+                ProtocolMessage::Heartbeat(timestamp) => {
+                    debug!("Received heartbeat at {}", timestamp);
+                    Err(None)
                 }
-                Err(None)
-            }
+                ProtocolMessage::Response { result, id } => {
+                    debug!("Received response message: id={}, result={:?}", id, result);
+                    Ok(BinanceEvent::RequestAck(id))
+                }
+                ProtocolMessage::Error { code, msg, id } => {
+                    let error = StreamError { code, msg, id };
+                    warn!("Received error message: {}", error);
+                    Ok(BinanceEvent::StreamError(error))
+                }
+            },
         }
     }
 
@@ -150,6 +405,12 @@ impl BinanceMessage {
             s if s.starts_with("aggTrade") => {
                 serde_json::from_value::<AggregateTrade>(data).map(BinanceEvent::AggTrade)
             }
+            // `depth5`, `depth10` and `depth20` are partial book snapshots, `depth` diffs
+            s if s.starts_with("depth")
+                && s["depth".len()..].starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                serde_json::from_value::<DepthSnapshot>(data).map(BinanceEvent::PartialDepth)
+            }
             s if s.starts_with("depth") => {
                 serde_json::from_value::<DepthUpdate>(data).map(BinanceEvent::DepthUpdate)
             }
@@ -231,6 +492,13 @@ impl BinanceMessage {
             }
         }
 
+        // Partial depth snapshots have no 'e' field either
+        if data.get("lastUpdateId").is_some()
+            && let Ok(snapshot) = serde_json::from_value::<DepthSnapshot>(data.clone())
+        {
+            return Ok(BinanceEvent::PartialDepth(snapshot));
+        }
+
         // Try BookTicker specifically (no 'e' field)
         if data.get("u").is_some()
             && data.get("s").is_some()
@@ -0,0 +1,84 @@
+use binance_spot_connector_rust::{
+    http::error::{ClientError, HttpError},
+    hyper::{Error as HyperError, Response},
+};
+use std::{collections::HashMap, fmt, time::Duration};
+
+/// Why a Binance REST call failed, with rate limiting told apart so callers can back off
+/// rather than hammer the API into a longer ban
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestError {
+    /// 429, the request weight or order rate limit was exceeded
+    RateLimited { retry_after: Option<Duration> },
+    /// 418, the IP is banned for carrying on after 429s
+    Banned { retry_after: Option<Duration> },
+    /// Any other failure, from sending the request to a non-success status
+    Request(String),
+}
+
+impl RestError {
+    /// The rate limit error for `status_code`, if it's one, reading the wait from the
+    /// `Retry-After` header in seconds
+    pub fn from_status(status_code: u16, headers: &HashMap<String, String>) -> Option<Self> {
+        let retry_after = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))
+            .and_then(|(_, value)| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        match status_code {
+            429 => Some(RestError::RateLimited { retry_after }),
+            418 => Some(RestError::Banned { retry_after }),
+            _ => None,
+        }
+    }
+
+    /// How long the exchange asked us to wait, `None` if it didn't say or for other errors
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            RestError::RateLimited { retry_after } | RestError::Banned { retry_after } => {
+                *retry_after
+            }
+            RestError::Request(_) => None,
+        }
+    }
+
+    fn from_http_error<T: fmt::Debug>(error: &HttpError<T>) -> Self {
+        Self::from_status(error.status_code, &error.headers).unwrap_or_else(|| {
+            RestError::Request(format!("HTTP {}: {:?}", error.status_code, error.data))
+        })
+    }
+}
+
+impl fmt::Display for RestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestError::RateLimited { retry_after } => {
+                write!(f, "Rate limited, retry after {retry_after:?}")
+            }
+            RestError::Banned { retry_after } => {
+                write!(f, "IP banned, retry after {retry_after:?}")
+            }
+            RestError::Request(message) => write!(f, "Request failed: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for RestError {}
+
+impl From<HyperError> for RestError {
+    fn from(error: HyperError) -> Self {
+        match error {
+            HyperError::Client(ClientError::Raw(error)) => Self::from_http_error(&error),
+            HyperError::Client(ClientError::Structured(error)) => Self::from_http_error(&error),
+            HyperError::Server(error) => Self::from_http_error(&error),
+            other => RestError::Request(format!("{other:?}")),
+        }
+    }
+}
+
+/// Reads the body of a sent request, classifying rate limit statuses.
+///
+/// Wrap every REST call in this, e.g. `read_body(client.send(request).await).await`.
+pub async fn read_body(response: Result<Response, HyperError>) -> Result<String, RestError> {
+    Ok(response?.into_body_str().await?)
+}
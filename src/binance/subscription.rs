@@ -0,0 +1,104 @@
+use std::{collections::BTreeMap, fmt};
+
+use super::{StreamError, data::BinanceEvent};
+
+/// Tracks subscribe requests on a websocket connection until Binance acknowledges or rejects
+/// each, so a failed subscription is noticed instead of the stream just staying silent.
+///
+/// Request ids come from the connector, which numbers every request it sends on the
+/// connection. Record each with [`Self::track`] and feed incoming events to
+/// [`Self::on_event`].
+#[derive(Debug, Default)]
+pub struct SubscriptionManager {
+    /// Streams of each request still waiting for a reply, by request id
+    pending: BTreeMap<u64, Vec<String>>,
+    confirmed: Vec<String>,
+}
+
+/// Why a subscription didn't go through
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionError {
+    /// Binance replied to the request with an error
+    Rejected {
+        streams: Vec<String>,
+        error: StreamError,
+    },
+    /// No reply came in time for these streams
+    TimedOut { streams: Vec<String> },
+}
+
+impl SubscriptionManager {
+    /// Waits for a reply to request `id`, which subscribed to `streams`
+    pub fn track(&mut self, id: u64, streams: impl IntoIterator<Item = impl Into<String>>) {
+        self.pending
+            .insert(id, streams.into_iter().map(Into::into).collect());
+    }
+
+    /// Resolves the request `event` replies to, if it's a reply to one being tracked. `Ok`
+    /// carries the streams the acknowledged request subscribed to.
+    ///
+    /// Errors without a request id can't be told apart and are left for the caller.
+    pub fn on_event(
+        &mut self,
+        event: &BinanceEvent,
+    ) -> Option<Result<Vec<String>, SubscriptionError>> {
+        match event {
+            BinanceEvent::RequestAck(id) => {
+                let streams = self.pending.remove(id)?;
+                self.confirmed.extend(streams.iter().cloned());
+                Some(Ok(streams))
+            }
+            BinanceEvent::StreamError(error) => {
+                let streams = self.pending.remove(&error.id?)?;
+                Some(Err(SubscriptionError::Rejected {
+                    streams,
+                    error: error.clone(),
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether every tracked request has been acknowledged
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Streams Binance confirmed so far
+    pub fn confirmed(&self) -> &[String] {
+        &self.confirmed
+    }
+
+    /// The error for giving up on the requests still pending, `None` if there are none
+    pub fn timed_out(&self) -> Option<SubscriptionError> {
+        if self.is_complete() {
+            return None;
+        }
+        Some(SubscriptionError::TimedOut {
+            streams: self.pending.values().flatten().cloned().collect(),
+        })
+    }
+}
+
+impl fmt::Display for SubscriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubscriptionError::Rejected { streams, error } => {
+                write!(
+                    f,
+                    "Subscription to {} rejected: {error}",
+                    streams.join(", ")
+                )
+            }
+            SubscriptionError::TimedOut { streams } => {
+                write!(
+                    f,
+                    "Subscription to {} not confirmed in time",
+                    streams.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SubscriptionError {}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+
+/// A mid price and spread observed at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookSample {
+    pub timestamp: DateTime<Utc>,
+    pub mid_price: Decimal,
+    pub spread: Decimal,
+}
+
+/// Fixed-size ring buffer of recent mid price and spread samples, oldest first.
+///
+/// Once `capacity` samples are held the oldest is dropped on each new one, so memory stays
+/// bounded however long the book runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookHistory {
+    samples: VecDeque<BookSample>,
+    capacity: usize,
+}
+
+impl Default for BookHistory {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl BookHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, sample: BookSample) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Changes the capacity, keeping the most recent samples when shrinking
+    pub fn set_capacity(&mut self, capacity: usize) {
+        while self.samples.len() > capacity {
+            self.samples.pop_front();
+        }
+        self.capacity = capacity;
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&BookSample> {
+        self.samples.back()
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &BookSample> {
+        self.samples.iter()
+    }
+
+    /// Rate of change of the mid price in price units per second over the last `n` samples.
+    ///
+    /// Positive when the mid is trending up. `None` with fewer than two samples or when they
+    /// all share a timestamp.
+    pub fn mid_velocity(&self, n: usize) -> Option<Decimal> {
+        let n = n.min(self.samples.len());
+        if n < 2 {
+            return None;
+        }
+        let first = self.samples[self.samples.len() - n];
+        let last = self.samples.back()?;
+        let elapsed_ms = (last.timestamp - first.timestamp).num_milliseconds();
+        (last.mid_price - first.mid_price)
+            .checked_div(Decimal::from(elapsed_ms))
+            .map(|per_ms| per_ms * Decimal::ONE_THOUSAND)
+    }
+
+    /// Mean spread over the last `n` samples
+    pub fn average_spread(&self, n: usize) -> Option<Decimal> {
+        let n = n.min(self.samples.len());
+        if n == 0 {
+            return None;
+        }
+        let sum = self
+            .samples
+            .iter()
+            .rev()
+            .take(n)
+            .map(|s| s.spread)
+            .sum::<Decimal>();
+        Some(sum / Decimal::from(n))
+    }
+
+    /// Latest spread relative to the average over the last `n` samples.
+    ///
+    /// Values well above 1 mean the spread has widened, a sign of liquidity stress.
+    pub fn spread_ratio(&self, n: usize) -> Option<Decimal> {
+        let latest = self.latest()?.spread;
+        latest.checked_div(self.average_spread(n)?)
+    }
+}
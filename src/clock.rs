@@ -0,0 +1,122 @@
+//! Time sources, and the skew between ours and the exchange's.
+//!
+//! Two clocks are in play:
+//! - Our [`Clock`] times what we do: order placement, fills and cancels, the audit log, order
+//!   TTLs, warmup and the order throttle.
+//! - The exchange's `event_time` and `trade_time` time what the market did: the order book's
+//!   [`last_update_time`](crate::order_book_state::OrderBookState::last_update_time), trade
+//!   windows, realized volatility buckets and feature snapshots.
+//!
+//! Anything comparing the two, like book staleness, converts our time to the exchange's with
+//! a [`ClockSkew`] first.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+/// Source of the current time, so time-dependent logic can be driven deterministically
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Wall clock time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to
+///
+/// Clones share the same time, so a test can keep a handle and advance the clock a
+/// [`MarketMaker`](crate::market_maker::MarketMaker) is using.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().expect("MockClock lock poisoned") = now;
+    }
+
+    pub fn advance(&self, by: TimeDelta) {
+        *self.now.lock().expect("MockClock lock poisoned") += by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().expect("MockClock lock poisoned")
+    }
+}
+
+/// How far our clock runs ahead of the exchange's, from the event times of messages as they
+/// arrive.
+///
+/// Each sample is `local_now - event_time`, the clock skew plus the delivery latency. The
+/// median of the recent samples shrugs off the odd delayed message, and keeping the typical
+/// latency in the offset means an exchange time converted from ours is when the exchange
+/// stamped the messages arriving now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockSkew {
+    samples: VecDeque<TimeDelta>,
+    capacity: usize,
+    offset: TimeDelta,
+}
+
+impl Default for ClockSkew {
+    fn default() -> Self {
+        Self::new(101)
+    }
+}
+
+impl ClockSkew {
+    /// Estimates the skew from the last `capacity` samples
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            offset: TimeDelta::zero(),
+        }
+    }
+
+    /// Records a message stamped `event_time` by the exchange arriving at `local_now`
+    pub fn record(&mut self, local_now: DateTime<Utc>, event_time: DateTime<Utc>) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(local_now - event_time);
+
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        self.offset = sorted[sorted.len() / 2];
+    }
+
+    /// Median of `local_now - event_time` over the recent samples, zero before any
+    pub fn offset(&self) -> TimeDelta {
+        self.offset
+    }
+
+    /// The exchange's time at our `local` time
+    pub fn to_exchange_time(&self, local: DateTime<Utc>) -> DateTime<Utc> {
+        local - self.offset
+    }
+
+    /// Our time at the exchange's `exchange` time
+    pub fn to_local_time(&self, exchange: DateTime<Utc>) -> DateTime<Utc> {
+        exchange + self.offset
+    }
+}
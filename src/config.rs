@@ -0,0 +1,263 @@
+use anyhow::{Context, Result, bail, ensure};
+use binance_spot_connector_rust::{
+    market::klines::KlineInterval,
+    market_stream::{
+        agg_trade::AggTradeStream, avg_price::AvgPriceStream, book_ticker::BookTickerStream,
+        diff_depth::DiffDepthStream, kline::KlineStream, mini_ticker::MiniTickerStream,
+        partial_depth::PartialDepthStream, rolling_window_ticker::RollingWindowTickerStream,
+        ticker::TickerStream, trade::TradeStream,
+    },
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    market_maker::MarketMakerConfig, replay::ReplaySpeed, symbol::Symbol, trade_feed::TradeSource,
+};
+
+/// Everything needed to run the binary, loadable from a JSON file
+///
+/// Missing fields fall back to their defaults, so a file only needs the overrides.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub symbol: Symbol,
+    pub streams: Vec<StreamConfig>,
+    /// How long to run before shutting down, in seconds
+    #[serde(with = "duration_secs")]
+    pub run_duration: Duration,
+    /// Which trade stream(s) feed the strategy, the matching stream must also be subscribed
+    pub trade_source: TradeSource,
+    /// Most order book levels kept per side, bounding memory over long runs
+    pub max_book_levels: usize,
+    /// How often time based order housekeeping runs, whether or not market data is arriving,
+    /// in milliseconds. See [`MarketMaker::maintenance`](crate::market_maker::MarketMaker::maintenance).
+    #[serde(with = "duration_ms")]
+    pub maintenance_interval: Duration,
+    /// Where to write the session's orders and PnL at shutdown, CSV for a `.csv` path and JSON
+    /// otherwise. Nothing is written when unset.
+    pub session_export_path: Option<PathBuf>,
+    /// Replay a recorded session instead of connecting to Binance
+    pub replay: Option<ReplayConfig>,
+    pub market_maker: MarketMakerConfig,
+}
+
+/// A recorded session to run the maker on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    /// Raw websocket frames, one per line, see
+    /// [`FileReplaySource`](crate::market_data::FileReplaySource)
+    pub events_path: PathBuf,
+    /// REST depth snapshot taken while the frames were recorded, the book is built from it
+    pub snapshot_path: PathBuf,
+    /// How fast the frames are delivered, the maker sees the recorded time whatever the speed
+    #[serde(default)]
+    pub speed: ReplaySpeed,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            symbol: Symbol::new("BTCUSDT").expect("BTCUSDT is a valid symbol"),
+            streams: vec![
+                StreamConfig::DiffDepth,
+                StreamConfig::AggTrade,
+                StreamConfig::BookTicker,
+                StreamConfig::MiniTicker,
+                StreamConfig::Ticker,
+                StreamConfig::AvgPrice,
+                StreamConfig::Kline {
+                    interval: "1m".to_string(),
+                },
+                StreamConfig::Kline {
+                    interval: "5m".to_string(),
+                },
+                StreamConfig::Kline {
+                    interval: "15m".to_string(),
+                },
+                StreamConfig::RollingWindowTicker {
+                    window: "1h".to_string(),
+                },
+            ],
+            run_duration: Duration::from_secs(500),
+            trade_source: TradeSource::default(),
+            max_book_levels: 1_000,
+            maintenance_interval: Duration::from_secs(1),
+            session_export_path: None,
+            replay: None,
+            market_maker: MarketMakerConfig::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let config: Self = read_json(path)?;
+        ensure!(
+            config.max_book_levels > 0,
+            "max_book_levels must be at least 1"
+        );
+        ensure!(
+            !config.maintenance_interval.is_zero(),
+            "maintenance_interval must be positive"
+        );
+        config.market_maker.validate()?;
+        Ok(config)
+    }
+
+    /// Websocket streams to subscribe to for the configured symbol
+    pub fn subscriptions<S: ConnectorStream>(&self) -> Result<Vec<S>> {
+        self.streams
+            .iter()
+            .map(|stream| stream.to_stream(&self.symbol))
+            .collect()
+    }
+}
+
+impl MarketMakerConfig {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let config: Self = read_json(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))
+}
+
+/// A market data stream to subscribe to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamConfig {
+    DiffDepth,
+    /// Top `levels` (5, 10 or 20) of the book every 100ms
+    PartialDepth {
+        levels: u16,
+    },
+    AggTrade,
+    Trade,
+    BookTicker,
+    MiniTicker,
+    Ticker,
+    AvgPrice,
+    Kline {
+        interval: String,
+    },
+    RollingWindowTicker {
+        window: String,
+    },
+}
+
+/// The connector's websocket `Stream`, which it doesn't export by name
+pub trait ConnectorStream:
+    From<DiffDepthStream>
+    + From<PartialDepthStream>
+    + From<AggTradeStream>
+    + From<TradeStream>
+    + From<BookTickerStream>
+    + From<MiniTickerStream>
+    + From<TickerStream>
+    + From<AvgPriceStream>
+    + From<KlineStream>
+    + From<RollingWindowTickerStream>
+{
+}
+
+impl<S> ConnectorStream for S where
+    S: From<DiffDepthStream>
+        + From<PartialDepthStream>
+        + From<AggTradeStream>
+        + From<TradeStream>
+        + From<BookTickerStream>
+        + From<MiniTickerStream>
+        + From<TickerStream>
+        + From<AvgPriceStream>
+        + From<KlineStream>
+        + From<RollingWindowTickerStream>
+{
+}
+
+impl StreamConfig {
+    pub fn to_stream<S: ConnectorStream>(&self, symbol: &Symbol) -> Result<S> {
+        let symbol = symbol.as_str();
+        let stream = match self {
+            StreamConfig::DiffDepth => DiffDepthStream::from_100ms(symbol).into(),
+            StreamConfig::PartialDepth { levels } => match levels {
+                5 | 10 | 20 => PartialDepthStream::from_100ms(symbol, *levels).into(),
+                _ => bail!("Unsupported partial depth levels: {levels}"),
+            },
+            StreamConfig::AggTrade => AggTradeStream::new(symbol).into(),
+            StreamConfig::Trade => TradeStream::new(symbol).into(),
+            StreamConfig::BookTicker => BookTickerStream::from_symbol(symbol).into(),
+            StreamConfig::MiniTicker => MiniTickerStream::from_symbol(symbol).into(),
+            StreamConfig::Ticker => TickerStream::from_symbol(symbol).into(),
+            StreamConfig::AvgPrice => AvgPriceStream::new(symbol).into(),
+            StreamConfig::Kline { interval } => {
+                KlineStream::new(symbol, parse_kline_interval(interval)?).into()
+            }
+            StreamConfig::RollingWindowTicker { window } => match window.as_str() {
+                "1h" | "4h" | "1d" => RollingWindowTickerStream::from_symbol(window, symbol).into(),
+                _ => bail!("Unsupported rolling window ticker window: {window}"),
+            },
+        };
+        Ok(stream)
+    }
+}
+
+pub fn parse_kline_interval(interval: &str) -> Result<KlineInterval> {
+    let interval = match interval {
+        "1m" => KlineInterval::Minutes1,
+        "3m" => KlineInterval::Minutes3,
+        "5m" => KlineInterval::Minutes5,
+        "15m" => KlineInterval::Minutes15,
+        "30m" => KlineInterval::Minutes30,
+        "1h" => KlineInterval::Hours1,
+        "2h" => KlineInterval::Hours2,
+        "4h" => KlineInterval::Hours4,
+        "6h" => KlineInterval::Hours6,
+        "8h" => KlineInterval::Hours8,
+        "12h" => KlineInterval::Hours12,
+        "1d" => KlineInterval::Days1,
+        "3d" => KlineInterval::Days3,
+        "1w" => KlineInterval::Weeks1,
+        "1M" => KlineInterval::Months1,
+        _ => bail!("Unsupported kline interval: {interval}"),
+    };
+    Ok(interval)
+}
+
+/// (De)serializes a [`Duration`] as whole seconds
+pub(crate) mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
+}
+
+/// (De)serializes a [`Duration`] as whole milliseconds
+pub(crate) mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
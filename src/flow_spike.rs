@@ -0,0 +1,167 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::VecDeque;
+use tracing::debug;
+
+use crate::recent_trades::Trade;
+
+/// Windows and thresholds of a [`FlowSpikeDetector`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowSpikeConfig {
+    /// Recent window the trade rate and flow imbalance are measured over
+    pub window: TimeDelta,
+    /// Longer window, ending where `window` starts, the usual trade rate is measured over
+    pub baseline: TimeDelta,
+    /// How many times the baseline trade rate the recent rate has to reach
+    pub rate_multiplier: Decimal,
+    /// Smallest magnitude of the recent trade flow imbalance, in [0, 1]
+    pub imbalance_threshold: Decimal,
+    /// Fewest trades within `window` for a spike, so a lone print after a lull isn't one
+    pub min_trades: u64,
+}
+
+impl Default for FlowSpikeConfig {
+    fn default() -> Self {
+        Self {
+            window: TimeDelta::seconds(5),
+            baseline: TimeDelta::minutes(1),
+            rate_multiplier: dec!(3),
+            imbalance_threshold: dec!(0.6),
+            min_trades: 10,
+        }
+    }
+}
+
+/// Flow turning fast and one-sided, as reported by [`FlowSpikeDetector::update`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlowSpike {
+    /// Time of the trade that set it off
+    pub time: DateTime<Utc>,
+    pub trades_per_second: Decimal,
+    pub baseline_trades_per_second: Decimal,
+    /// Trade flow imbalance over the window, negative when sellers are taking
+    pub imbalance: Decimal,
+}
+
+/// Early warning of a move: the trade rate jumps while the flow turns one-sided.
+///
+/// Both the rate and the imbalance are measured over [`FlowSpikeConfig::window`], the rate
+/// against the one over the baseline before it. Nothing is reported until the trades seen
+/// span the whole baseline.
+#[derive(Debug, Clone)]
+pub struct FlowSpikeDetector {
+    config: FlowSpikeConfig,
+    /// Trades within the baseline and recent windows, oldest first
+    trades: VecDeque<Trade>,
+    first_trade_time: Option<DateTime<Utc>>,
+    spiking: bool,
+}
+
+impl Default for FlowSpikeDetector {
+    fn default() -> Self {
+        Self::new(FlowSpikeConfig::default())
+    }
+}
+
+impl FlowSpikeDetector {
+    pub fn new(config: FlowSpikeConfig) -> Self {
+        Self {
+            config,
+            trades: VecDeque::new(),
+            first_trade_time: None,
+            spiking: false,
+        }
+    }
+
+    /// Takes in the next trade, returning a [`FlowSpike`] when it sets one off.
+    ///
+    /// A spike is reported once, on the trade that starts it, and again only after the flow
+    /// has calmed down in between. Out of order trades are ignored.
+    pub fn update(&mut self, trade: impl Into<Trade>) -> Option<FlowSpike> {
+        let trade = trade.into();
+        let now = trade.trade_time();
+        if self
+            .trades
+            .back()
+            .is_some_and(|latest| now < latest.trade_time())
+        {
+            return None;
+        }
+        self.first_trade_time.get_or_insert(now);
+        self.trades.push_back(trade);
+        let history_start = now - self.config.baseline - self.config.window;
+        while self
+            .trades
+            .front()
+            .is_some_and(|oldest| oldest.trade_time() <= history_start)
+        {
+            self.trades.pop_front();
+        }
+
+        let spike = self.measure(now);
+        let started = spike.is_some() && !self.spiking;
+        self.spiking = spike.is_some();
+        if started {
+            debug!("Trade flow spike: {:?}", spike);
+            spike
+        } else {
+            None
+        }
+    }
+
+    pub fn config(&self) -> &FlowSpikeConfig {
+        &self.config
+    }
+
+    /// Whether the flow is still fast and one-sided since the last reported spike
+    pub fn is_spiking(&self) -> bool {
+        self.spiking
+    }
+
+    fn measure(&self, now: DateTime<Utc>) -> Option<FlowSpike> {
+        let (window, baseline) = (self.config.window, self.config.baseline);
+        if self
+            .first_trade_time
+            .is_none_or(|first| now - first < baseline + window)
+        {
+            return None;
+        }
+
+        let window_start = now - window;
+        let (mut recent_trades, mut baseline_trades) = (0u64, 0u64);
+        let (mut buy_volume, mut sell_volume) = (Decimal::ZERO, Decimal::ZERO);
+        for trade in &self.trades {
+            if trade.trade_time() <= window_start {
+                baseline_trades += trade.num_trades();
+            } else {
+                recent_trades += trade.num_trades();
+                if trade.buyer_market_maker {
+                    sell_volume += trade.quantity;
+                } else {
+                    buy_volume += trade.quantity;
+                }
+            }
+        }
+        if recent_trades < self.config.min_trades {
+            return None;
+        }
+
+        let per_second = |trades: u64, span: TimeDelta| {
+            (Decimal::from(trades) * Decimal::from(1000))
+                .checked_div(Decimal::from(span.num_milliseconds()))
+        };
+        let trades_per_second = per_second(recent_trades, window)?;
+        let baseline_trades_per_second = per_second(baseline_trades, baseline)?;
+        let imbalance = (buy_volume - sell_volume).checked_div(buy_volume + sell_volume)?;
+
+        (trades_per_second >= baseline_trades_per_second * self.config.rate_multiplier
+            && imbalance.abs() >= self.config.imbalance_threshold)
+            .then_some(FlowSpike {
+                time: now,
+                trades_per_second,
+                baseline_trades_per_second,
+                imbalance,
+            })
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::{HashMap, VecDeque};
+use tracing::debug;
+
+use crate::binance::data::{KlineData, KlineEventData};
+
+/// A single OHLCV candle
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub open_time: DateTime<Utc>,
+    pub close_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+}
+
+impl From<&KlineData> for Candle {
+    fn from(kline: &KlineData) -> Self {
+        Self {
+            open_time: kline.start_time,
+            close_time: kline.close_time,
+            open: kline.open_price,
+            high: kline.high_price,
+            low: kline.low_price,
+            close: kline.close_price,
+            volume: kline.base_asset_volume,
+        }
+    }
+}
+
+/// Recent candles for each subscribed kline interval (e.g. `"1m"`, `"5m"`, `"15m"`)
+///
+/// Only closed candles count towards the history and derived values, the candle still
+/// forming is kept separately.
+#[derive(Debug, Clone)]
+pub struct KlineStore {
+    capacity: usize,
+    /// Closed candles per interval, oldest first
+    closed: HashMap<String, VecDeque<Candle>>,
+    forming: HashMap<String, Candle>,
+}
+
+impl Default for KlineStore {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl KlineStore {
+    /// Keeps the last `capacity` closed candles per interval
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            closed: HashMap::new(),
+            forming: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, event: &KlineEventData) {
+        let kline = &event.kline;
+        let candle = Candle::from(kline);
+        if !kline.is_kline_closed {
+            self.forming.insert(kline.interval.clone(), candle);
+            return;
+        }
+
+        self.forming.remove(&kline.interval);
+        let candles = self.closed.entry(kline.interval.clone()).or_default();
+        if candles
+            .back()
+            .is_some_and(|last| last.open_time >= candle.open_time)
+        {
+            debug!(
+                "Ignoring repeated {} candle opened at {}",
+                kline.interval, candle.open_time
+            );
+            return;
+        }
+        if candles.len() >= self.capacity {
+            candles.pop_front();
+        }
+        candles.push_back(candle);
+    }
+
+    /// Intervals with at least one closed candle
+    pub fn intervals(&self) -> impl Iterator<Item = &str> {
+        self.closed.keys().map(String::as_str)
+    }
+
+    /// Closed candles for `interval`, oldest first
+    pub fn candles(&self, interval: &str) -> impl DoubleEndedIterator<Item = &Candle> {
+        self.closed.get(interval).into_iter().flatten()
+    }
+
+    /// Most recent closed candle
+    pub fn latest(&self, interval: &str) -> Option<&Candle> {
+        self.closed.get(interval)?.back()
+    }
+
+    /// The candle currently forming, not yet closed
+    pub fn forming(&self, interval: &str) -> Option<&Candle> {
+        self.forming.get(interval)
+    }
+
+    /// Average True Range over the last `period` closed candles, in price units.
+    ///
+    /// The true range of a candle is the largest of `high - low`, `|high - prev_close|` and
+    /// `|low - prev_close|`, so `period + 1` candles are needed before this returns a value.
+    pub fn atr(&self, interval: &str, period: usize) -> Option<Decimal> {
+        let candles = self.closed.get(interval)?;
+        if period == 0 || candles.len() < period + 1 {
+            return None;
+        }
+        let recent = candles.range(candles.len() - period - 1..);
+        let sum = recent
+            .clone()
+            .zip(recent.skip(1))
+            .map(|(prev, candle)| {
+                (candle.high - candle.low)
+                    .max((candle.high - prev.close).abs())
+                    .max((candle.low - prev.close).abs())
+            })
+            .sum::<Decimal>();
+        Some(sum / Decimal::from(period))
+    }
+
+    /// Simple moving average of the close over the last `period` closed candles
+    pub fn sma(&self, interval: &str, period: usize) -> Option<Decimal> {
+        let candles = self.closed.get(interval)?;
+        if period == 0 || candles.len() < period {
+            return None;
+        }
+        let sum = candles
+            .iter()
+            .rev()
+            .take(period)
+            .map(|c| c.close)
+            .sum::<Decimal>();
+        Some(sum / Decimal::from(period))
+    }
+}
@@ -1,4 +1,17 @@
 pub mod binance;
+pub mod book_history;
+pub mod clock;
+pub mod config;
+pub mod flow_spike;
+pub mod kline_store;
+pub mod market_data;
 pub mod market_maker;
 pub mod order_book_state;
+pub mod realized_volatility;
 pub mod recent_trades;
+pub mod replay;
+pub mod strategy;
+pub mod symbol;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod trade_feed;
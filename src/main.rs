@@ -1,49 +1,71 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use binance_spot_connector_rust::{
-    hyper::BinanceHttpClient,
-    market::{self, klines::KlineInterval},
-    market_stream::{
-        agg_trade::AggTradeStream, avg_price::AvgPriceStream, book_ticker::BookTickerStream,
-        diff_depth::DiffDepthStream, kline::KlineStream, mini_ticker::MiniTickerStream,
-        rolling_window_ticker::RollingWindowTickerStream, ticker::TickerStream, trade::TradeStream,
-    },
-    tokio_tungstenite::BinanceWebSocketClient,
+    hyper::BinanceHttpClient, market, tokio_tungstenite::BinanceWebSocketClient,
+};
+use chrono::Utc;
+use futures_util::{StreamExt, future::ready};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    select,
+    sync::{mpsc, oneshot, watch},
+    time::Instant,
 };
-use futures_util::StreamExt;
-use rust_decimal::{Decimal, prelude::FromPrimitive};
-use std::{collections::VecDeque, time::Duration};
-use tokio::select;
 use tracing::{debug, error, info, warn};
-use tracing_subscriber::layer::SubscriberExt;
 
 use marketmakerlib::{
     binance::{
-        BinanceMessage, VolumeProfile,
-        data::{AveragePrice, BinanceEvent, DepthSnapshot},
+        data::{BinanceEvent, DepthSnapshot, DepthUpdate},
+        rest::{RestError, read_body},
+        subscription::{SubscriptionError, SubscriptionManager},
     },
-    market_maker::{MarketMaker, MarketMakerConfig},
+    clock::{Clock, MockClock, SystemClock},
+    config::AppConfig,
+    market_data::{BinanceStreamSource, FileReplaySource, MarketDataSource},
+    market_maker::MarketMaker,
     order_book_state::OrderBookState,
     recent_trades::RecentTrades,
+    replay::ReplayPacer,
+    trade_feed::TradeFeed,
 };
 
+/// Snapshot refetches before giving up on joining it to the depth stream
+const MAX_BOOTSTRAP_ATTEMPTS: u32 = 5;
+/// Wait after a rate limit response that didn't say how long to back off
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// Wait for Binance to confirm the stream subscriptions before giving up
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Reconnects to try in a row after the websocket drops before giving up
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+/// Wait before each reconnect
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Levels each side of the book needs before the strategy acts on it
+const MIN_BOOK_LEVELS: usize = 5;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
     info!("Running!");
 
-    let mut order_book_state = OrderBookState::default();
-
-    let client = BinanceHttpClient::default();
-    // Establish connection
-    let (mut conn, _) = BinanceWebSocketClient::connect_async_default()
-        .await
-        .expect("Failed to connect");
+    // Optional path to a JSON config file, otherwise run with the defaults
+    let config = match std::env::args().nth(1) {
+        Some(path) => {
+            info!("Loading config from {}", path);
+            AppConfig::from_file(path)?
+        }
+        None => AppConfig::default(),
+    };
 
-    let symbol = "BTCUSDT";
+    let mut order_book_state = OrderBookState::default()
+        .with_symbol(config.symbol.clone())
+        .with_min_levels(MIN_BOOK_LEVELS)
+        .with_max_levels_per_side(config.max_book_levels);
 
-    let (message_tx, mut message_rx) = tokio::sync::mpsc::channel(10_000);
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::channel(10_000);
+    // Tells the stream handler to stop reading and close the connection
+    let (shutdown_tx, mut shutdown_rx) = tokio::sync::watch::channel(false);
 
     let (depth_tx, mut depth_rx) = tokio::sync::mpsc::channel(2_000);
+    let (partial_depth_tx, mut partial_depth_rx) = tokio::sync::mpsc::channel(500);
     let (agg_tx, mut agg_rx) = tokio::sync::mpsc::channel(2_000);
     let (book_ticker_tx, mut book_ticker_rx) = tokio::sync::mpsc::channel(5_000);
     let (mini_ticker_tx, mut mini_ticker_rx) = tokio::sync::mpsc::channel(500);
@@ -53,31 +75,15 @@ async fn main() -> Result<()> {
     let (trade_tx, mut trade_rx) = tokio::sync::mpsc::channel(500);
     let (window_ticker_tx, mut window_ticker_rx) = tokio::sync::mpsc::channel(500);
 
-    // Subscribe to streams
-    conn.subscribe(vec![
-        &DiffDepthStream::from_100ms(symbol).into(),
-        &AggTradeStream::new(symbol).into(),
-        &BookTickerStream::from_symbol(symbol).into(),
-        &MiniTickerStream::from_symbol(symbol).into(),
-        &TickerStream::from_symbol(symbol).into(),
-        &AvgPriceStream::new(symbol).into(),
-        &KlineStream::new(symbol, KlineInterval::Minutes3).into(),
-        //&TradeStream::new(symbol).into(),
-        &RollingWindowTickerStream::from_symbol("1h", symbol).into(),
-    ])
-    .await;
-    //     //&AvgPriceStream::new(symbol).into(),
-    //     //&TradeStream::new(symbol).into(),
-    //     //&KlineStream::new(symbol, KlineInterval::Minutes1).into(),
-    //     &DiffDepthStream::from_100ms(symbol).into(),
-    //     &AggTradeStream::new(symbol).into(),
-    //     //&BookTickerStream::from_symbol(symbol).into(),
-    // ])
-    // .await;
-
-    // Start a timer for 10 seconds
+    // The names Binance acknowledges the subscription with
+    let streams = config.subscriptions()?;
+    let stream_names: Vec<String> = streams.iter().map(ToString::to_string).collect();
+    let mut subscriptions = SubscriptionManager::default();
+    let (subscribed_tx, subscribed_rx) = tokio::sync::oneshot::channel();
+
+    // Start a timer for the configured run duration
     let timer = tokio::time::Instant::now();
-    let duration = Duration::new(500, 0);
+    let duration = config.run_duration;
     // Initialize counters and timing
     let start_time = tokio::time::Instant::now();
     let mut last_check = start_time;
@@ -85,29 +91,119 @@ async fn main() -> Result<()> {
     let mut messages_since_last_check = 0;
     let check_interval = Duration::from_secs(1); // Check every second
 
-    let stream_handler = tokio::spawn(async move {
-        while let Some(message) = conn.as_mut().next().await {
-            match message {
-                Ok(message) => message_tx.send(message).await?,
-                Err(_) => break,
-            }
-            if timer.elapsed() >= duration {
-                info!("10 seconds elapsed, exiting loop.");
-                break; // Exit the loop after 10 seconds
-            }
+    // A replay runs the maker on the recorded time, moved along by the pacer
+    let (clock, pacer): (Arc<dyn Clock>, _) = match &config.replay {
+        Some(replay) => {
+            let pacer = ReplayPacer::new(replay.speed, MockClock::new(Utc::now()));
+            (Arc::new(pacer.clock().clone()), Some(pacer))
         }
-        conn.close().await.expect("Failed to close connection");
-        info!("Exiting stream handler, closed connection");
-        Ok::<_, anyhow::Error>(())
-    });
+        None => (Arc::new(SystemClock), None),
+    };
+
+    // The stream handler hands each reconnect to the main loop, and holds the new
+    // connection's events back until the main loop acknowledges it
+    let (reconnect_tx, mut reconnect_rx) = mpsc::channel::<oneshot::Sender<()>>(1);
+
+    let stream_handler = match &config.replay {
+        Some(replay) => {
+            info!(
+                "Replaying {} at {:?}",
+                replay.events_path.display(),
+                replay.speed
+            );
+            let mut source = FileReplaySource::open(&replay.events_path).await?;
+            tokio::spawn(async move {
+                forward_events(
+                    &mut source,
+                    pacer,
+                    &event_tx,
+                    &mut shutdown_rx,
+                    timer,
+                    duration,
+                )
+                .await?;
+                Ok(())
+            })
+        }
+        None => {
+            // Establish connection
+            let (mut conn, _) = BinanceWebSocketClient::connect_async_default()
+                .await
+                .expect("Failed to connect");
+
+            // Subscribe to streams
+            let request_id = conn.subscribe(streams.iter()).await;
+            subscriptions.track(request_id, stream_names.clone());
+
+            tokio::spawn(async move {
+                let mut connected = Some(conn);
+                let mut failed_reconnects = 0;
+                loop {
+                    let mut conn = match connected.take() {
+                        Some(conn) => conn,
+                        None => match BinanceWebSocketClient::connect_async_default().await {
+                            Ok((mut conn, _)) => {
+                                failed_reconnects = 0;
+                                conn.subscribe(streams.iter()).await;
+                                let (ack_tx, ack_rx) = oneshot::channel();
+                                if reconnect_tx.send(ack_tx).await.is_err() || ack_rx.await.is_err()
+                                {
+                                    info!("Main loop stopped, not resuming the stream");
+                                    break;
+                                }
+                                conn
+                            }
+                            Err(e) if failed_reconnects < MAX_RECONNECT_ATTEMPTS => {
+                                failed_reconnects += 1;
+                                warn!("Failed to reconnect, retrying: {}", e);
+                                tokio::time::sleep(RECONNECT_DELAY).await;
+                                continue;
+                            }
+                            Err(e) => return Err(e.into()),
+                        },
+                    };
+
+                    let ran_dry = {
+                        let frames = conn
+                            .as_mut()
+                            .take_while(|message| ready(message.is_ok()))
+                            .filter_map(|message| {
+                                ready(message.ok().and_then(|m| m.into_text().ok()))
+                            });
+                        let mut source = BinanceStreamSource::new(frames);
+                        forward_events(
+                            &mut source,
+                            None,
+                            &event_tx,
+                            &mut shutdown_rx,
+                            timer,
+                            duration,
+                        )
+                        .await
+                    };
+                    if let Err(e) = conn.close().await {
+                        debug!("Closing the connection failed: {}", e);
+                    }
+                    if !ran_dry? {
+                        info!("Exiting stream handler, closed connection");
+                        break;
+                    }
+                    warn!("Connection lost, reconnecting");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+                Ok::<_, anyhow::Error>(())
+            })
+        }
+    };
 
     let sender = tokio::spawn(async move {
-        while let Some(message) = message_rx.recv().await {
+        let mut subscribed_tx = Some(subscribed_tx);
+        while let Some(event) = event_rx.recv().await {
             total_messages += 1;
             messages_since_last_check += 1;
             // Check throughput every second
             if last_check.elapsed() >= check_interval {
-                let pending = message_rx.len();
+                let pending = event_rx.len();
                 let messages_per_second =
                     messages_since_last_check as f64 / last_check.elapsed().as_secs_f64();
 
@@ -123,105 +219,135 @@ async fn main() -> Result<()> {
                 last_check = tokio::time::Instant::now();
             }
 
-            let binary_data = message.into_text()?;
-            match BinanceMessage::from_str_into_market_data(&binary_data) {
-                Ok(event) => match event {
-                    BinanceEvent::AggTrade(trade) => {
-                        agg_tx.send(trade).await.expect("Failed to send trade");
-                    }
-                    BinanceEvent::DepthUpdate(depth) => {
-                        depth_tx.send(depth).await.expect("Failed to send depth");
-                    }
-                    BinanceEvent::BookTicker(ticker) => {
-                        book_ticker_tx
-                            .send(ticker)
-                            .await
-                            .expect("Failed to send book ticker");
-                    }
-                    BinanceEvent::MiniTicker(ticker) => {
-                        mini_ticker_tx
-                            .send(ticker)
-                            .await
-                            .expect("Failed to send mini ticker");
-                    }
-                    BinanceEvent::Ticker(ticker) => {
-                        ticker_tx.send(ticker).await.expect("Failed to send ticker");
-                    }
-                    BinanceEvent::AvgPrice(avg_price) => {
-                        avg_price_tx
-                            .send(avg_price)
-                            .await
-                            .expect("Failed to send avg price");
-                    }
-                    BinanceEvent::Kline(kline) => {
-                        kline_tx.send(kline).await.expect("Failed to send kline");
-                    }
-                    BinanceEvent::Trade(trade) => {
-                        trade_tx.send(trade).await.expect("Failed to send trade");
-                    }
-                    BinanceEvent::WindowTicker(ticker) => {
-                        window_ticker_tx
-                            .send(ticker)
-                            .await
-                            .expect("Failed to send window ticker");
-                    }
-                },
-                Err(e) => {
-                    if let Some(e) = e {
-                        error!("Failed to parse event: {}", e);
-                        error!(
-                            "Data: {:?}",
-                            serde_json::from_str::<serde_json::Value>(&binary_data)
-                        );
-                    }
+            if let Some(result) = subscriptions.on_event(&event) {
+                let result = result.map(|streams| info!("Subscribed to {}", streams.join(", ")));
+                if (result.is_err() || subscriptions.is_complete())
+                    && let Some(subscribed_tx) = subscribed_tx.take()
+                {
+                    let _ = subscribed_tx.send(result);
                 }
+                continue;
+            }
+
+            // A closed channel means the main loop has stopped reading
+            let sent = match event {
+                BinanceEvent::AggTrade(trade) => agg_tx.send(trade).await.is_ok(),
+                BinanceEvent::DepthUpdate(depth) => depth_tx.send(depth).await.is_ok(),
+                BinanceEvent::PartialDepth(snapshot) => {
+                    partial_depth_tx.send(snapshot).await.is_ok()
+                }
+                BinanceEvent::BookTicker(ticker) => book_ticker_tx.send(ticker).await.is_ok(),
+                BinanceEvent::MiniTicker(ticker) => mini_ticker_tx.send(ticker).await.is_ok(),
+                BinanceEvent::Ticker(ticker) => ticker_tx.send(ticker).await.is_ok(),
+                BinanceEvent::AvgPrice(avg_price) => avg_price_tx.send(avg_price).await.is_ok(),
+                BinanceEvent::Kline(kline) => kline_tx.send(kline).await.is_ok(),
+                BinanceEvent::Trade(trade) => trade_tx.send(trade).await.is_ok(),
+                BinanceEvent::WindowTicker(ticker) => window_ticker_tx.send(ticker).await.is_ok(),
+                BinanceEvent::StreamError(e) => {
+                    // Most likely a rejected subscription, which would otherwise just
+                    // mean no data ever arrives for that stream
+                    warn!("Stream error: {}", e);
+                    true
+                }
+                BinanceEvent::RequestAck(id) => {
+                    debug!("Untracked request {} acknowledged", id);
+                    true
+                }
+            };
+            if !sent {
+                info!("Main loop stopped receiving, exiting sender loop.");
+                break;
             }
         }
         Ok::<_, anyhow::Error>(())
     });
 
-    warn!("Sleeping for 5 seconds to allow for snapshot processing...");
-    tokio::time::sleep(Duration::from_secs(5)).await;
-    warn!("Waking up...");
+    // A rejected subscription would otherwise just mean no data ever arrives for that stream.
+    // A replay has nothing to subscribe to.
+    if config.replay.is_none() {
+        match tokio::time::timeout(SUBSCRIBE_TIMEOUT, subscribed_rx).await {
+            Ok(Ok(result)) => result?,
+            Ok(Err(_)) => anyhow::bail!("Stream closed before the subscriptions were confirmed"),
+            Err(_) => {
+                return Err(SubscriptionError::TimedOut {
+                    streams: stream_names,
+                }
+                .into());
+            }
+        }
+    }
+
     let mut rt = RecentTrades::new(100);
-    let data = client
-        .send(market::depth(symbol).limit(5_000))
-        .await
-        .expect("Failed to get depth")
-        .into_body_str()
-        .await
-        .expect("Failed to read response body");
-    let snapshot =
-        serde_json::from_str::<DepthSnapshot>(&data).expect("Failed to parse depth snapshot");
-
-    order_book_state.apply_snapshot(snapshot);
-
-    info!("Processing buffered updates...");
-    let mut buffer = Vec::new();
-    depth_rx.recv_many(&mut buffer, usize::MAX).await;
-    let buffer = buffer.into_iter().collect::<VecDeque<_>>();
 
-    order_book_state.process_buffer(buffer)?;
+    bootstrap_book(&config, &mut order_book_state, &mut depth_rx).await?;
     // Start normal processing
     info!("Starting normal update processing...");
+    let mut trade_feed = TradeFeed::new(config.trade_source);
     let mut buffer = Vec::new();
     agg_rx.recv_many(&mut buffer, usize::MAX).await;
-    rt.update_many(buffer.into_iter());
-    let mut market_maker = MarketMaker::new(MarketMakerConfig::default(), order_book_state, rt);
+    rt.update_many(
+        buffer
+            .into_iter()
+            .flat_map(|trade| trade_feed.on_agg_trade(trade)),
+    );
+    let mut buffer = Vec::new();
+    trade_rx.recv_many(&mut buffer, usize::MAX).await;
+    rt.update_many(
+        buffer
+            .into_iter()
+            .filter_map(|trade| trade_feed.on_trade(trade)),
+    );
+    let mut market_maker = MarketMaker::with_clock(
+        config.market_maker.clone(),
+        order_book_state,
+        rt,
+        clock.clone(),
+    );
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+    let mut maintenance = tokio::time::interval(config.maintenance_interval);
     let mut i = 0;
     loop {
         i += 1;
         select! {
-            Some(depth) = depth_rx.recv() => {
+            result = &mut shutdown => {
+                match result {
+                    Ok(()) => info!("Received Ctrl-C, shutting down"),
+                    Err(e) => error!("Failed to listen for Ctrl-C, shutting down: {}", e),
+                }
+                break;
+            }
+            _ = maintenance.tick() => {
+                market_maker.maintenance(clock.now());
+            }
+            Some(ack) = reconnect_rx.recv() => {
+                info!("Reconnected, resyncing the book");
+                let _ = ack.send(());
+                market_maker.handle_reconnect();
+                bootstrap_book(&config, &mut market_maker.order_book, &mut depth_rx)
+                    .await?;
+            }
+            depth = depth_rx.recv() => {
+                let Some(depth) = depth else {
+                    info!("Market data ended, exiting loop.");
+                    break;
+                };
                 info!("Depth Update");
                 market_maker.handle_depth_update(depth)?;
             }
             Some(trade) = agg_rx.recv() => {
                 info!("AggTrade");
-                market_maker.handle_trade(trade)?;
+                for trade in trade_feed.on_agg_trade(trade) {
+                    market_maker.handle_trade(trade)?;
+                }
+            }
+            Some(snapshot) = partial_depth_rx.recv() => {
+                info!("Partial Depth");
+                market_maker.handle_partial_depth(snapshot)?;
             }
             Some(book_ticker) = book_ticker_rx.recv() => {
-                info!("BookTicker: {:?}", book_ticker);
+                debug!("BookTicker: {:?}", book_ticker);
+                market_maker.handle_book_ticker(&book_ticker)?;
 
             }
             Some(mini_ticker) = mini_ticker_rx.recv() => {
@@ -242,14 +368,19 @@ async fn main() -> Result<()> {
             Some(kline) = kline_rx.recv() => {
                 info!("Kline");
                 debug!("Kline: {:?}", kline);
+                market_maker.handle_kline(&kline);
             }
             Some(trade) = trade_rx.recv() => {
                 info!("Trade");
                 debug!("Trade: {:?}", trade);
+                if let Some(trade) = trade_feed.on_trade(trade) {
+                    market_maker.handle_trade(trade)?;
+                }
             }
             Some(window_ticker) = window_ticker_rx.recv() => {
                 info!("WindowTicker");
                 debug!("WindowTicker: {:?}", window_ticker);
+                market_maker.handle_window_ticker(&window_ticker);
             }
             else => {
                 break;
@@ -264,18 +395,49 @@ async fn main() -> Result<()> {
         }
 
         if timer.elapsed() >= duration {
-            info!("10 seconds elapsed, exiting loop.");
-            break; // Exit the loop after 10 seconds
+            info!("Run duration elapsed, exiting loop.");
+            break; // Exit the loop after the run duration
         }
     }
 
-    drop(depth_rx);
-    drop(agg_rx);
+    // The raw trades still being aggregated when the loop ended
+    if let Some(trade) = trade_feed.flush() {
+        market_maker.handle_trade(trade)?;
+    }
+
+    // Stop subscribing, whether the timer or Ctrl-C ended the loop
+    let _ = shutdown_tx.send(true);
+    // Every receiver goes, so the sender can't be left blocked on a full channel
+    drop((
+        depth_rx,
+        partial_depth_rx,
+        agg_rx,
+        book_ticker_rx,
+        mini_ticker_rx,
+        ticker_rx,
+        avg_price_rx,
+        kline_rx,
+        trade_rx,
+        window_ticker_rx,
+        reconnect_rx,
+    ));
 
     let (_, _) = tokio::join!(stream_handler, sender);
     info!("Exiting main loop");
 
+    market_maker.flatten();
+    if let Err(e) = market_maker.save_adaptive_state() {
+        error!("Failed to save adaptive state: {:#}", e);
+    }
     info!("{:?}", market_maker);
+    info!("Final statistics: {}", market_maker.get_statistics());
+
+    if let Some(path) = &config.session_export_path {
+        match market_maker.export_session(path) {
+            Ok(()) => info!("Exported session to {}", path.display()),
+            Err(e) => error!("Failed to export session: {:#}", e),
+        }
+    }
 
     let total_time = start_time.elapsed();
     let average_throughput = total_messages as f64 / total_time.as_secs_f64();
@@ -287,3 +449,108 @@ async fn main() -> Result<()> {
     );
     Ok(())
 }
+
+/// Forwards events from `source` to the sender task until it runs dry, the run duration
+/// elapses or shutdown is requested. With a `pacer` each event waits for its recorded time.
+///
+/// Returns whether `source` ran dry rather than being stopped.
+async fn forward_events(
+    source: &mut impl MarketDataSource,
+    mut pacer: Option<ReplayPacer>,
+    event_tx: &mpsc::Sender<BinanceEvent>,
+    shutdown_rx: &mut watch::Receiver<bool>,
+    timer: Instant,
+    duration: Duration,
+) -> Result<bool> {
+    loop {
+        let event = select! {
+            event = source.next_event() => event,
+            _ = shutdown_rx.changed() => {
+                info!("Shutdown requested, exiting stream handler loop.");
+                return Ok(false);
+            }
+        };
+        let Some(event) = event else {
+            return Ok(true);
+        };
+        match event {
+            Ok(event) => {
+                if let Some(pacer) = &mut pacer {
+                    select! {
+                        _ = pacer.pace_event(&event) => {}
+                        _ = shutdown_rx.changed() => {
+                            info!("Shutdown requested, exiting stream handler loop.");
+                            return Ok(false);
+                        }
+                    }
+                }
+                event_tx.send(event).await?
+            }
+            Err(e) => error!("{:#}", e),
+        }
+        if timer.elapsed() >= duration {
+            info!("Run duration elapsed, exiting loop.");
+            return Ok(false); // Exit the loop after the run duration
+        }
+    }
+}
+
+/// Builds `book` from a depth snapshot joined to the updates buffered from `depth_rx`,
+/// refetching the snapshot until the two line up
+async fn bootstrap_book(
+    config: &AppConfig,
+    book: &mut OrderBookState,
+    depth_rx: &mut mpsc::Receiver<DepthUpdate>,
+) -> Result<()> {
+    let client = BinanceHttpClient::default();
+    let symbol = config.symbol.as_str();
+
+    // Keep buffering depth updates across attempts, a newer snapshot may still bridge them
+    let mut depth_buffer = Vec::new();
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let data = match &config.replay {
+            Some(replay) => std::fs::read_to_string(&replay.snapshot_path).with_context(|| {
+                format!(
+                    "Failed to read replay snapshot {}",
+                    replay.snapshot_path.display()
+                )
+            })?,
+            None => match read_body(client.send(market::depth(symbol).limit(5_000)).await).await {
+                Ok(data) => data,
+                Err(e @ (RestError::RateLimited { .. } | RestError::Banned { .. }))
+                    if attempt < MAX_BOOTSTRAP_ATTEMPTS =>
+                {
+                    let wait = e.retry_after().unwrap_or(DEFAULT_RETRY_AFTER);
+                    warn!("{}, waiting {:?} before refetching snapshot", e, wait);
+                    tokio::time::sleep(wait).await;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            },
+        };
+        let snapshot =
+            serde_json::from_str::<DepthSnapshot>(&data).with_context(|| match &config.replay {
+                Some(replay) => format!(
+                    "Failed to parse depth snapshot {}",
+                    replay.snapshot_path.display()
+                ),
+                None => "Failed to parse depth snapshot".to_string(),
+            })?;
+
+        depth_rx.recv_many(&mut depth_buffer, usize::MAX).await;
+        match book.bootstrap(snapshot, &depth_buffer) {
+            Ok(()) => break,
+            Err(e) if attempt < MAX_BOOTSTRAP_ATTEMPTS => {
+                warn!(
+                    "Bootstrap attempt {} failed, refetching snapshot: {}",
+                    attempt, e
+                );
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use futures_util::{Stream, StreamExt};
+use std::{future::Future, ops::Deref, path::Path};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader, Lines},
+};
+use tracing::error;
+
+use crate::binance::{BinanceMessage, data::BinanceEvent};
+
+/// Where market data events come from, so the same consumer code runs live and in replays
+pub trait MarketDataSource {
+    /// The next event, `None` once the source is exhausted.
+    ///
+    /// Heartbeats are skipped, frames that fail to parse are returned as errors without ending
+    /// the source. Failing to read the frames at all ends it.
+    fn next_event(&mut self) -> impl Future<Output = Option<Result<BinanceEvent>>> + Send;
+}
+
+/// Events decoded from the raw text frames of a Binance websocket connection
+#[derive(Debug)]
+pub struct BinanceStreamSource<S> {
+    frames: S,
+}
+
+impl<S> BinanceStreamSource<S> {
+    pub fn new(frames: S) -> Self {
+        Self { frames }
+    }
+}
+
+impl<S, T> MarketDataSource for BinanceStreamSource<S>
+where
+    S: Stream<Item = T> + Unpin + Send,
+    T: Deref<Target = str> + Send,
+{
+    async fn next_event(&mut self) -> Option<Result<BinanceEvent>> {
+        loop {
+            let frame = self.frames.next().await?;
+            if let Some(event) = decode_frame(&frame).transpose() {
+                return Some(event);
+            }
+        }
+    }
+}
+
+/// Events replayed from a file of recorded websocket frames, one per line.
+///
+/// Events are yielded as fast as they're read, pair with a
+/// [`ReplayPacer`](crate::replay::ReplayPacer) to deliver them on their original timing.
+#[derive(Debug)]
+pub struct FileReplaySource {
+    lines: Lines<BufReader<File>>,
+}
+
+impl FileReplaySource {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .await
+            .with_context(|| format!("Failed to open replay file {}", path.display()))?;
+        Ok(Self {
+            lines: BufReader::new(file).lines(),
+        })
+    }
+}
+
+impl MarketDataSource for FileReplaySource {
+    async fn next_event(&mut self) -> Option<Result<BinanceEvent>> {
+        loop {
+            let line = match self.lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                // Reading again would only fail the same way
+                Err(e) => {
+                    error!("Failed to read replay file, ending the replay: {}", e);
+                    return None;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(event) = decode_frame(&line).transpose() {
+                return Some(event);
+            }
+        }
+    }
+}
+
+/// Parses a raw frame, `None` for heartbeats
+fn decode_frame(frame: &str) -> Result<Option<BinanceEvent>> {
+    match BinanceMessage::from_str_into_market_data(frame) {
+        Ok(event) => Ok(Some(event)),
+        Err(None) => Ok(None),
+        Err(Some(e)) => Err(e).with_context(|| format!("Failed to parse event: {frame}")),
+    }
+}
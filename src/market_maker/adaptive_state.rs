@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+use tracing::{info, warn};
+
+use super::MarketMaker;
+
+/// What the maker learned over a run, kept across restarts so it doesn't start over from
+/// [`super::MarketMakerConfig::base_k`], see [`super::MarketMakerConfig::state_path`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveState {
+    pub saved_at: DateTime<Utc>,
+    pub current_k: Decimal,
+    pub successful_fill_count: usize,
+    pub attempt_count: usize,
+}
+
+impl AdaptiveState {
+    /// Reads state written by [`Self::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read adaptive state {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse adaptive state {}", path.display()))
+    }
+
+    /// Writes the state to `path` through a temporary file, so a crash mid-write can't leave
+    /// a truncated state behind
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        fs::write(&partial, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write adaptive state {}", partial.display()))?;
+        fs::rename(&partial, path)
+            .with_context(|| format!("Failed to replace adaptive state {}", path.display()))
+    }
+
+    /// Whether both hold the same k-factor and counts, whenever they were saved
+    fn same_adaptation(&self, other: &Self) -> bool {
+        self.current_k == other.current_k
+            && self.successful_fill_count == other.successful_fill_count
+            && self.attempt_count == other.attempt_count
+    }
+}
+
+impl MarketMaker {
+    /// The k-factor and fill counts as of now, what [`Self::save_adaptive_state`] writes
+    pub fn adaptive_state(&self) -> AdaptiveState {
+        AdaptiveState {
+            saved_at: self.clock.now(),
+            current_k: self.current_k,
+            successful_fill_count: self.successful_fill_count,
+            attempt_count: self.attempt_count,
+        }
+    }
+
+    /// Writes [`Self::adaptive_state`] to the configured state path, if any
+    pub fn save_adaptive_state(&self) -> Result<()> {
+        match &self.config.state_path {
+            Some(path) => self.adaptive_state().save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the adaptive state if it changed since it was restored or last written here, so
+    /// an idle maker doesn't rewrite the same file on every maintenance tick
+    pub(super) fn save_changed_adaptive_state(&mut self) -> Result<()> {
+        let state = self.adaptive_state();
+        if state.same_adaptation(&self.saved_adaptive_state) {
+            return Ok(());
+        }
+        self.save_adaptive_state()?;
+        self.saved_adaptive_state = state;
+        Ok(())
+    }
+
+    /// Picks up where the last run left off, unless its state is missing, unreadable or older
+    /// than [`super::MarketMakerConfig::max_state_age`]
+    pub(super) fn restore_adaptive_state(&mut self) {
+        let Some(path) = &self.config.state_path else {
+            return;
+        };
+        if !path.exists() {
+            info!(
+                "No adaptive state at {}, starting from base k",
+                path.display()
+            );
+            return;
+        }
+        let state = match AdaptiveState::load(path) {
+            Ok(state) => state,
+            Err(e) => {
+                warn!("{:#}, starting from base k", e);
+                return;
+            }
+        };
+        let age = self.clock.now() - state.saved_at;
+        if age
+            .to_std()
+            .is_ok_and(|age| age > self.config.max_state_age)
+        {
+            info!(
+                "Adaptive state saved at {} is stale, starting from base k",
+                state.saved_at
+            );
+            return;
+        }
+        if state.current_k <= Decimal::ZERO {
+            warn!(
+                "Adaptive state has a non-positive k of {}, starting from base k",
+                state.current_k
+            );
+            return;
+        }
+
+        info!(
+            "Restored k of {} after {}/{} fills from {}",
+            state.current_k,
+            state.successful_fill_count,
+            state.attempt_count,
+            path.display()
+        );
+        self.current_k = state.current_k;
+        self.successful_fill_count = state.successful_fill_count;
+        self.attempt_count = state.attempt_count;
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::io::Write;
+
+use super::{CancelReason, Order};
+
+/// A lifecycle transition of an order
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", content = "reason")]
+pub enum OrderEvent {
+    Placed,
+    Amended,
+    /// Part of the order filled, with the filled size, the rest still rests
+    PartiallyFilled,
+    Filled,
+    Cancelled(CancelReason),
+}
+
+/// A single machine-readable entry in the order audit trail
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub order_id: String,
+    #[serde(flatten)]
+    pub event: OrderEvent,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub k_factor: Decimal,
+    pub imbalance_at_placement: Decimal,
+}
+
+/// Append-only ledger of order lifecycle transitions, used to reconstruct decisions after a run
+#[derive(Debug, Clone, Default)]
+pub struct OrderAuditLog {
+    enabled: bool,
+    records: Vec<AuditRecord>,
+}
+
+impl OrderAuditLog {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, order: &Order, event: OrderEvent, timestamp: DateTime<Utc>) {
+        if !self.enabled {
+            return;
+        }
+        self.records.push(AuditRecord {
+            timestamp,
+            order_id: order.id.clone(),
+            event,
+            price: order.price,
+            size: order.size,
+            k_factor: order.k_factor_used,
+            imbalance_at_placement: order.imbalance_at_placement,
+        });
+    }
+
+    pub fn records(&self) -> &[AuditRecord] {
+        &self.records
+    }
+
+    /// All transitions recorded for a single order, oldest first
+    pub fn for_order<'a>(&'a self, order_id: &'a str) -> impl Iterator<Item = &'a AuditRecord> {
+        self.records.iter().filter(move |r| r.order_id == order_id)
+    }
+
+    /// Writes the ledger as JSON lines, one record per line
+    pub fn write_json_lines(&self, mut writer: impl Write) -> anyhow::Result<()> {
+        for record in &self.records {
+            serde_json::to_writer(&mut writer, record)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
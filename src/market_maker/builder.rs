@@ -0,0 +1,502 @@
+use anyhow::{Result, ensure};
+use rust_decimal::Decimal;
+use std::{path::PathBuf, time::Duration};
+
+use super::{
+    FeeModel, FillSource, ImbalanceMethod, ImbalanceNormalization, MarketMakerConfig, MidSource,
+    QuoteMode, ReconnectPolicy, RegimeThresholds, SuppressionWindow, SymbolPrecision,
+    ValueAreaBids, VolatilitySource,
+};
+use crate::order_book_state::{BookQualityWeights, DepthWeighting};
+
+/// Fluent builder for [`MarketMakerConfig`], starting from the defaults and validating on `build`
+#[derive(Debug, Clone, Default)]
+pub struct MarketMakerConfigBuilder {
+    config: MarketMakerConfig,
+}
+
+impl MarketMakerConfig {
+    pub fn builder() -> MarketMakerConfigBuilder {
+        MarketMakerConfigBuilder::default()
+    }
+
+    /// Checks the parameters are consistent with each other
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.base_k > Decimal::ZERO, "base_k must be positive");
+        ensure!(
+            self.order_size > Decimal::ZERO,
+            "order_size must be positive"
+        );
+        ensure!(
+            self.max_active_orders > 0,
+            "max_active_orders must be at least 1"
+        );
+        ensure!(
+            self.strong_imbalance_threshold < self.moderate_imbalance_threshold,
+            "strong_imbalance_threshold ({}) must be below moderate_imbalance_threshold ({})",
+            self.strong_imbalance_threshold,
+            self.moderate_imbalance_threshold
+        );
+        ensure!(
+            self.strong_imbalance_threshold >= Decimal::NEGATIVE_ONE
+                && self.moderate_imbalance_threshold <= Decimal::ONE,
+            "imbalance thresholds must be within [-1, 1]"
+        );
+        ensure!(
+            self.imbalance_hysteresis >= Decimal::ZERO && self.imbalance_hysteresis < Decimal::ONE,
+            "imbalance_hysteresis must be in [0, 1), got {}",
+            self.imbalance_hysteresis
+        );
+        if let QuoteMode::JoinSpread {
+            min_spread_bps,
+            max_volatility,
+        } = self.quote_mode
+        {
+            ensure!(
+                min_spread_bps >= Decimal::ZERO && max_volatility > Decimal::ZERO,
+                "join spread min_spread_bps must not be negative and max_volatility must be positive"
+            );
+        }
+        let regime = &self.regime_thresholds;
+        ensure!(
+            regime.high_volatility > Decimal::ZERO && regime.trend_pct > Decimal::ZERO,
+            "regime high_volatility and trend_pct must be positive"
+        );
+        ensure!(
+            self.vol_dampening > Decimal::ZERO,
+            "vol_dampening must be positive"
+        );
+        ensure!(
+            self.learning_rate > Decimal::ZERO && self.learning_rate < Decimal::ONE,
+            "learning_rate must be in (0, 1), got {}",
+            self.learning_rate
+        );
+        ensure!(
+            self.min_distance_pct >= Decimal::ZERO,
+            "min_distance_pct must not be negative"
+        );
+        ensure!(
+            self.imbalance_depth > 0,
+            "imbalance_depth must be at least 1"
+        );
+        match self.imbalance_weighting {
+            DepthWeighting::Exponential { decay } => ensure!(
+                decay > Decimal::ZERO && decay <= Decimal::ONE,
+                "imbalance weighting decay must be in (0, 1], got {}",
+                decay
+            ),
+            DepthWeighting::Distance { scale_bps } => ensure!(
+                scale_bps > Decimal::ZERO,
+                "imbalance weighting scale_bps must be positive, got {}",
+                scale_bps
+            ),
+            DepthWeighting::Harmonic | DepthWeighting::Linear => {}
+        }
+        if let ImbalanceNormalization::Tanh { scale } = self.imbalance_normalization {
+            ensure!(
+                scale > Decimal::ZERO,
+                "imbalance normalization scale must be positive, got {}",
+                scale
+            );
+        }
+        ensure!(self.mid_depth > 0, "mid_depth must be at least 1");
+        ensure!(
+            self.min_edge_buffer_bps >= Decimal::ZERO,
+            "min_edge_buffer_bps must not be negative"
+        );
+        ensure!(
+            self.cancel_far_multiplier > Decimal::ZERO
+                && self.cancel_near_multiplier >= Decimal::ZERO,
+            "cancel band multipliers must not be negative"
+        );
+        ensure!(
+            self.min_volatility > Decimal::ZERO && self.min_volatility <= self.max_volatility,
+            "volatility bounds must satisfy 0 < min_volatility <= max_volatility"
+        );
+        ensure!(
+            self.trend_sensitivity >= Decimal::ZERO,
+            "trend_sensitivity must not be negative"
+        );
+        ensure!(
+            self.max_trend_adjustment >= Decimal::ZERO && self.max_trend_adjustment < Decimal::ONE,
+            "max_trend_adjustment must be in [0, 1)"
+        );
+        if let VolatilitySource::Atr { period, .. } = self.volatility_source {
+            ensure!(period > 0, "ATR period must be at least 1");
+        }
+        if let Some(min_quality) = self.min_book_quality {
+            ensure!(
+                (Decimal::ZERO..=Decimal::ONE).contains(&min_quality),
+                "min_book_quality must be in [0, 1], got {}",
+                min_quality
+            );
+        }
+        let quality = &self.book_quality_weights;
+        ensure!(
+            quality.spread >= Decimal::ZERO
+                && quality.depth >= Decimal::ZERO
+                && quality.stability >= Decimal::ZERO
+                && quality.spread + quality.depth + quality.stability > Decimal::ZERO,
+            "book quality weights must not be negative and must not all be zero"
+        );
+        ensure!(
+            quality.target_spread_bps >= Decimal::ZERO
+                && quality.target_spread_bps < quality.max_spread_bps,
+            "book quality spread bounds must satisfy 0 <= target_spread_bps < max_spread_bps"
+        );
+        ensure!(
+            quality.depth_band_bps > Decimal::ZERO
+                && quality.target_depth > Decimal::ZERO
+                && quality.max_move_bps > Decimal::ZERO,
+            "book quality depth_band_bps, target_depth and max_move_bps must be positive"
+        );
+        if let Some(bps) = self.volume_profile_band_bps {
+            ensure!(
+                bps > Decimal::ZERO,
+                "volume_profile_band_bps must be positive, got {}",
+                bps
+            );
+        }
+        if let Some(min_sigmas) = self.min_distance_sigmas {
+            ensure!(
+                min_sigmas >= Decimal::ZERO,
+                "min_distance_sigmas must not be negative, got {}",
+                min_sigmas
+            );
+        }
+        if let Some(delta) = self.adverse_imbalance_delta {
+            ensure!(
+                delta > Decimal::ZERO && delta <= Decimal::TWO,
+                "adverse_imbalance_delta must be in (0, 2], got {}",
+                delta
+            );
+        }
+        if let Some(threshold) = self.replace_edge_threshold_bps {
+            ensure!(
+                threshold >= Decimal::ZERO,
+                "replace_edge_threshold_bps must not be negative, got {}",
+                threshold
+            );
+        }
+        if let Some(max_drawdown) = self.max_drawdown {
+            ensure!(
+                max_drawdown > Decimal::ZERO,
+                "max_drawdown must be positive, got {}",
+                max_drawdown
+            );
+        }
+        if let Some(resume) = self.drawdown_resume {
+            ensure!(
+                self.max_drawdown
+                    .is_some_and(|max_drawdown| resume < max_drawdown)
+                    && resume >= Decimal::ZERO,
+                "drawdown_resume must be in [0, max_drawdown), got {}",
+                resume
+            );
+        }
+        for window in &self.suppression_windows {
+            let valid = match *window {
+                SuppressionWindow::Daily { duration, .. } => {
+                    !duration.is_zero() && duration < Duration::from_secs(24 * 60 * 60)
+                }
+                SuppressionWindow::Once { start, end } => start < end,
+            };
+            ensure!(
+                valid,
+                "suppression window {:?} must be non-empty and daily ones shorter than a day",
+                window
+            );
+        }
+        if let Some(threshold) = self.regime_shift_threshold {
+            ensure!(
+                threshold > Decimal::ZERO && threshold <= Decimal::TWO,
+                "regime_shift_threshold must be in (0, 2], got {}",
+                threshold
+            );
+        }
+        ensure!(
+            self.inventory_skew_factor >= Decimal::ZERO,
+            "inventory_skew_factor must not be negative"
+        );
+        ensure!(
+            self.adverse_fill_threshold_pct >= Decimal::ZERO,
+            "adverse_fill_threshold_pct must not be negative"
+        );
+        ensure!(
+            self.max_inventory > Decimal::ZERO,
+            "max_inventory must be positive"
+        );
+        ensure!(
+            self.max_orders_per_second > 0 && self.max_orders_per_minute > 0,
+            "order rate limits must be at least 1"
+        );
+        ensure!(
+            self.volume_profile_bucket_size > Decimal::ZERO,
+            "volume_profile_bucket_size must be positive"
+        );
+        ensure!(
+            self.intensity_bucket_size > Decimal::ZERO,
+            "intensity_bucket_size must be positive"
+        );
+        ensure!(
+            self.precision.tick_size > Decimal::ZERO && self.precision.step_size > Decimal::ZERO,
+            "tick_size and step_size must be positive"
+        );
+        Ok(())
+    }
+}
+
+impl MarketMakerConfigBuilder {
+    pub fn base_k(mut self, base_k: Decimal) -> Self {
+        self.config.base_k = base_k;
+        self
+    }
+
+    pub fn order_size(mut self, order_size: Decimal) -> Self {
+        self.config.order_size = order_size;
+        self
+    }
+
+    pub fn max_active_orders(mut self, max_active_orders: usize) -> Self {
+        self.config.max_active_orders = max_active_orders;
+        self
+    }
+
+    pub fn imbalance_thresholds(mut self, strong: Decimal, moderate: Decimal) -> Self {
+        self.config.strong_imbalance_threshold = strong;
+        self.config.moderate_imbalance_threshold = moderate;
+        self
+    }
+
+    pub fn imbalance_hysteresis(mut self, hysteresis: Decimal) -> Self {
+        self.config.imbalance_hysteresis = hysteresis;
+        self
+    }
+
+    pub fn quote_mode(mut self, quote_mode: QuoteMode) -> Self {
+        self.config.quote_mode = quote_mode;
+        self
+    }
+
+    pub fn regime_thresholds(mut self, thresholds: RegimeThresholds) -> Self {
+        self.config.regime_thresholds = thresholds;
+        self
+    }
+
+    pub fn vol_dampening(mut self, vol_dampening: Decimal) -> Self {
+        self.config.vol_dampening = vol_dampening;
+        self
+    }
+
+    pub fn learning_rate(mut self, learning_rate: Decimal) -> Self {
+        self.config.learning_rate = learning_rate;
+        self
+    }
+
+    pub fn min_distance_pct(mut self, min_distance_pct: Decimal) -> Self {
+        self.config.min_distance_pct = min_distance_pct;
+        self
+    }
+
+    pub fn audit_log_enabled(mut self, audit_log_enabled: bool) -> Self {
+        self.config.audit_log_enabled = audit_log_enabled;
+        self
+    }
+
+    pub fn imbalance(mut self, method: ImbalanceMethod, depth: usize) -> Self {
+        self.config.imbalance_method = method;
+        self.config.imbalance_depth = depth;
+        self
+    }
+
+    pub fn imbalance_weighting(mut self, weighting: DepthWeighting) -> Self {
+        self.config.imbalance_weighting = weighting;
+        self
+    }
+
+    pub fn imbalance_normalization(mut self, normalization: ImbalanceNormalization) -> Self {
+        self.config.imbalance_normalization = normalization;
+        self
+    }
+
+    pub fn mid_source(mut self, source: MidSource, depth: usize) -> Self {
+        self.config.mid_source = source;
+        self.config.mid_depth = depth;
+        self
+    }
+
+    pub fn fee_model(mut self, fee_model: FeeModel) -> Self {
+        self.config.fee_model = fee_model;
+        self
+    }
+
+    pub fn min_edge_buffer_bps(mut self, bps: Decimal) -> Self {
+        self.config.min_edge_buffer_bps = bps;
+        self
+    }
+
+    pub fn volatility_source(mut self, volatility_source: VolatilitySource) -> Self {
+        self.config.volatility_source = volatility_source;
+        self
+    }
+
+    pub fn volatility_bounds(mut self, min: Decimal, max: Decimal) -> Self {
+        self.config.min_volatility = min;
+        self.config.max_volatility = max;
+        self
+    }
+
+    pub fn max_book_staleness(mut self, max_book_staleness: Duration) -> Self {
+        self.config.max_book_staleness = max_book_staleness;
+        self
+    }
+
+    pub fn min_book_quality(mut self, min_quality: Option<Decimal>) -> Self {
+        self.config.min_book_quality = min_quality;
+        self
+    }
+
+    pub fn book_quality_weights(mut self, weights: BookQualityWeights) -> Self {
+        self.config.book_quality_weights = weights;
+        self
+    }
+
+    /// Cancels orders resting longer than `order_ttl`, zero keeps them indefinitely
+    pub fn order_ttl(mut self, order_ttl: Duration) -> Self {
+        self.config.order_ttl = order_ttl;
+        self
+    }
+
+    pub fn warmup(mut self, trades: usize, book_updates: usize, duration: Duration) -> Self {
+        self.config.warmup_trades = trades;
+        self.config.warmup_book_updates = book_updates;
+        self.config.warmup_duration = duration;
+        self
+    }
+
+    pub fn exclude_own_orders(mut self, exclude_own_orders: bool) -> Self {
+        self.config.exclude_own_orders = exclude_own_orders;
+        self
+    }
+
+    pub fn decay_book_on_trades(mut self, decay_book_on_trades: bool) -> Self {
+        self.config.decay_book_on_trades = decay_book_on_trades;
+        self
+    }
+
+    pub fn min_distance_sigmas(mut self, min_sigmas: Option<Decimal>) -> Self {
+        self.config.min_distance_sigmas = min_sigmas;
+        self
+    }
+
+    pub fn cancel_band(mut self, far_multiplier: Decimal, near_multiplier: Decimal) -> Self {
+        self.config.cancel_far_multiplier = far_multiplier;
+        self.config.cancel_near_multiplier = near_multiplier;
+        self
+    }
+
+    pub fn adverse_fill(mut self, lookback: usize, threshold_pct: Decimal) -> Self {
+        self.config.adverse_fill_lookback = lookback;
+        self.config.adverse_fill_threshold_pct = threshold_pct;
+        self
+    }
+
+    pub fn regime_shift_threshold(mut self, threshold: Option<Decimal>) -> Self {
+        self.config.regime_shift_threshold = threshold;
+        self
+    }
+
+    pub fn adverse_imbalance_delta(mut self, delta: Option<Decimal>) -> Self {
+        self.config.adverse_imbalance_delta = delta;
+        self
+    }
+
+    pub fn replace_edge_threshold_bps(mut self, threshold_bps: Option<Decimal>) -> Self {
+        self.config.replace_edge_threshold_bps = threshold_bps;
+        self
+    }
+
+    pub fn inventory_skew(mut self, skew_factor: Decimal, max_inventory: Decimal) -> Self {
+        self.config.inventory_skew_factor = skew_factor;
+        self.config.max_inventory = max_inventory;
+        self
+    }
+
+    pub fn max_drawdown(mut self, max_drawdown: Option<Decimal>, resume: Option<Decimal>) -> Self {
+        self.config.max_drawdown = max_drawdown;
+        self.config.drawdown_resume = resume;
+        self
+    }
+
+    /// Scheduled windows to pull every order and stop quoting in
+    pub fn suppression_windows(mut self, windows: Vec<SuppressionWindow>) -> Self {
+        self.config.suppression_windows = windows;
+        self
+    }
+
+    /// Restores and saves the adaptive state at `path`, ignoring saved state older than
+    /// `max_age`
+    pub fn state_path(mut self, path: impl Into<PathBuf>, max_age: Duration) -> Self {
+        self.config.state_path = Some(path.into());
+        self.config.max_state_age = max_age;
+        self
+    }
+
+    pub fn order_rate_limits(mut self, per_second: u32, per_minute: u32) -> Self {
+        self.config.max_orders_per_second = per_second;
+        self.config.max_orders_per_minute = per_minute;
+        self
+    }
+
+    pub fn value_area_bids(mut self, value_area_bids: ValueAreaBids, bucket_size: Decimal) -> Self {
+        self.config.value_area_bids = value_area_bids;
+        self.config.volume_profile_bucket_size = bucket_size;
+        self
+    }
+
+    pub fn intensity_bucket_size(mut self, bucket_size: Decimal) -> Self {
+        self.config.intensity_bucket_size = bucket_size;
+        self
+    }
+
+    pub fn fill_source(mut self, fill_source: FillSource) -> Self {
+        self.config.fill_source = fill_source;
+        self
+    }
+
+    pub fn partial_fills(mut self, partial_fills: bool) -> Self {
+        self.config.partial_fills = partial_fills;
+        self
+    }
+
+    pub fn trend_filter(mut self, sensitivity: Decimal, max_adjustment: Decimal) -> Self {
+        self.config.trend_sensitivity = sensitivity;
+        self.config.max_trend_adjustment = max_adjustment;
+        self
+    }
+
+    pub fn volume_profile_band_bps(mut self, bps: Option<Decimal>) -> Self {
+        self.config.volume_profile_band_bps = bps;
+        self
+    }
+
+    pub fn reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self {
+        self.config.reconnect_policy = reconnect_policy;
+        self
+    }
+
+    pub fn precision(mut self, precision: SymbolPrecision) -> Self {
+        self.config.precision = precision;
+        self
+    }
+
+    pub fn display_precision(mut self, display_precision: u32) -> Self {
+        self.config.display_precision = display_precision;
+        self
+    }
+
+    pub fn build(self) -> Result<MarketMakerConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::io::Write;
+
+/// Number of levels used for the depth imbalance features
+pub const FEATURE_DEPTH: usize = 5;
+
+/// Market features observed at a single decision point
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureSnapshot {
+    pub timestamp: DateTime<Utc>,
+    pub mid_price: Option<Decimal>,
+    pub microprice: Option<Decimal>,
+    pub spread: Option<Decimal>,
+    pub relative_spread: Option<Decimal>,
+    pub imbalance: Option<Decimal>,
+    pub depth_imbalance: Option<Decimal>,
+    pub weighted_imbalance: Option<Decimal>,
+    pub volatility: Option<Decimal>,
+    pub trade_flow_imbalance: Option<Decimal>,
+    pub order_placed: bool,
+}
+
+impl FeatureSnapshot {
+    const CSV_HEADER: &'static str = "timestamp,mid_price,microprice,spread,relative_spread,imbalance,depth_imbalance,weighted_imbalance,volatility,trade_flow_imbalance,order_placed";
+
+    fn to_csv_row(&self) -> String {
+        let field = |value: Option<Decimal>| value.map(|v| v.to_string()).unwrap_or_default();
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.timestamp.to_rfc3339(),
+            field(self.mid_price),
+            field(self.microprice),
+            field(self.spread),
+            field(self.relative_spread),
+            field(self.imbalance),
+            field(self.depth_imbalance),
+            field(self.weighted_imbalance),
+            field(self.volatility),
+            field(self.trade_flow_imbalance),
+            self.order_placed
+        )
+    }
+}
+
+/// Writes feature snapshots as CSV rows, emitting the header before the first row
+#[derive(Debug)]
+pub struct FeatureCsvWriter<W: Write> {
+    writer: W,
+    header_written: bool,
+}
+
+impl<W: Write> FeatureCsvWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            header_written: false,
+        }
+    }
+
+    pub fn write(&mut self, snapshot: &FeatureSnapshot) -> std::io::Result<()> {
+        if !self.header_written {
+            writeln!(self.writer, "{}", FeatureSnapshot::CSV_HEADER)?;
+            self.header_written = true;
+        }
+        writeln!(self.writer, "{}", snapshot.to_csv_row())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,69 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Serialize;
+
+/// Imbalance at or above which bids outweigh asks enough to be very cautious
+const BUY_PRESSURE_THRESHOLD: Decimal = dec!(0.3);
+
+/// How aggressively stink bids are priced for the order book imbalance, from the most
+/// aggressive band to the most cautious
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum ImbalanceBand {
+    /// Below the strong threshold, halves k
+    StrongSellPressure,
+    /// Below the moderate threshold, the plain k
+    SellPressure,
+    /// Up to light buy pressure, 1.5 times k
+    #[default]
+    Balanced,
+    /// At or above 0.3, 2.5 times k
+    BuyPressure,
+}
+
+impl ImbalanceBand {
+    /// Multiplier the band applies to the k-factor
+    pub fn k_multiplier(self) -> Decimal {
+        match self {
+            ImbalanceBand::StrongSellPressure => dec!(0.5),
+            ImbalanceBand::SellPressure => Decimal::ONE,
+            ImbalanceBand::Balanced => dec!(1.5),
+            ImbalanceBand::BuyPressure => dec!(2.5),
+        }
+    }
+
+    /// The band `imbalance` falls in, ignoring the current one
+    pub fn classify(imbalance: Decimal, strong: Decimal, moderate: Decimal) -> Self {
+        if imbalance < strong {
+            ImbalanceBand::StrongSellPressure
+        } else if imbalance < moderate {
+            ImbalanceBand::SellPressure
+        } else if imbalance < BUY_PRESSURE_THRESHOLD {
+            ImbalanceBand::Balanced
+        } else {
+            ImbalanceBand::BuyPressure
+        }
+    }
+
+    /// The band after seeing `imbalance` while in this one.
+    ///
+    /// A band is entered once the imbalance is `hysteresis` past its boundary and left only
+    /// once it's `hysteresis` back past it the other way, so an imbalance hovering around a
+    /// threshold doesn't flip the band on every update. Zero hysteresis is [`Self::classify`].
+    pub fn next(
+        self,
+        imbalance: Decimal,
+        strong: Decimal,
+        moderate: Decimal,
+        hysteresis: Decimal,
+    ) -> Self {
+        let more_aggressive = Self::classify(imbalance + hysteresis, strong, moderate);
+        if more_aggressive < self {
+            return more_aggressive;
+        }
+        let more_cautious = Self::classify(imbalance - hysteresis, strong, moderate);
+        if more_cautious > self {
+            return more_cautious;
+        }
+        self
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, MathematicalOps};
+use std::collections::BTreeMap;
+
+/// Fitted order arrival intensity `λ(δ) = A·e^(−κδ)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntensityEstimate {
+    /// Fill rate right at the mid, in fills per second
+    pub a: Decimal,
+    /// How fast the fill rate decays per unit of price distance from the mid
+    pub kappa: Decimal,
+}
+
+/// Estimates the order arrival intensity from the distances our fills came at.
+///
+/// Fills are counted in buckets of distance from the mid at placement, and each bucket's fill
+/// rate is its count over the time observed. A log-linear regression of the rates on the
+/// bucket distances gives `-κ` as the slope and `ln A` as the intercept. The observed time
+/// divides every rate equally, so it only moves `A` and the fit is only redone on new fills.
+#[derive(Debug, Clone)]
+pub struct ArrivalIntensity {
+    bucket_size: Decimal,
+    started_at: DateTime<Utc>,
+    /// Fill counts by bucket index, bucket `i` covering distances `[i, i + 1) * bucket_size`
+    fills: BTreeMap<i64, u64>,
+    /// Slope and intercept of ln(count) against distance, once two buckets have fills
+    fit: Option<(Decimal, Decimal)>,
+}
+
+impl ArrivalIntensity {
+    pub fn new(bucket_size: Decimal, started_at: DateTime<Utc>) -> Self {
+        Self {
+            bucket_size,
+            started_at,
+            fills: BTreeMap::new(),
+            fit: None,
+        }
+    }
+
+    /// Counts a fill `distance` below the mid and refits
+    pub fn record_fill(&mut self, distance: Decimal) {
+        let Some(bucket) = (distance / self.bucket_size).floor().try_into().ok() else {
+            return;
+        };
+        *self.fills.entry(bucket).or_default() += 1;
+        self.fit = self.fit();
+    }
+
+    pub fn fill_count(&self) -> u64 {
+        self.fills.values().sum()
+    }
+
+    /// The intensity as of `now`, `None` until fills landed in two different buckets.
+    ///
+    /// A non-positive `kappa` means deeper fills haven't been rarer so far.
+    pub fn estimate(&self, now: DateTime<Utc>) -> Option<IntensityEstimate> {
+        let (slope, intercept) = self.fit?;
+        let elapsed_secs =
+            Decimal::from((now - self.started_at).num_milliseconds()) / Decimal::ONE_THOUSAND;
+        if elapsed_secs <= Decimal::ZERO {
+            return None;
+        }
+        Some(IntensityEstimate {
+            a: intercept.checked_exp()? / elapsed_secs,
+            kappa: -slope,
+        })
+    }
+
+    /// Least squares fit of ln(count) against the distance at each bucket's centre
+    fn fit(&self) -> Option<(Decimal, Decimal)> {
+        if self.fills.len() < 2 {
+            return None;
+        }
+        let points: Vec<(Decimal, Decimal)> = self
+            .fills
+            .iter()
+            .map(|(&bucket, &count)| {
+                let distance =
+                    (Decimal::from(bucket) + Decimal::ONE / Decimal::TWO) * self.bucket_size;
+                (distance, Decimal::from(count).ln())
+            })
+            .collect();
+        let n = Decimal::from(points.len());
+        let mean_x = points.iter().map(|&(x, _)| x).sum::<Decimal>() / n;
+        let mean_y = points.iter().map(|&(_, y)| y).sum::<Decimal>() / n;
+        let (sxx, sxy) =
+            points
+                .iter()
+                .fold((Decimal::ZERO, Decimal::ZERO), |(sxx, sxy), &(x, y)| {
+                    let dx = x - mean_x;
+                    (sxx + dx * dx, sxy + dx * (y - mean_y))
+                });
+        let slope = sxy.checked_div(sxx)?;
+        Some((slope, mean_y - slope * mean_x))
+    }
+}
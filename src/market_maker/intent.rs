@@ -0,0 +1,135 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::{CancelReason, KOutcome, MarketMaker, OrderSide, QuoteMode};
+
+/// An order action the maker would take, see [`MarketMaker::preview`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Action {
+    Place(StinkBidIntent),
+    /// A quote inside the spread, see [`QuoteMode::JoinSpread`]
+    Join(JoinQuoteIntent),
+    Cancel(CancelIntent),
+}
+
+/// A stink bid to place, with the values that priced it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StinkBidIntent {
+    pub price: Decimal,
+    pub size: Decimal,
+    pub mid_price: Decimal,
+    pub best_bid: Decimal,
+    /// Clamped, dampened per-trade return volatility
+    pub volatility: Decimal,
+    pub imbalance: Decimal,
+    /// k-factor after the imbalance and trend adjustments
+    pub k_used: Decimal,
+    /// Discount to the mid as a percentage
+    pub discount_pct: Decimal,
+    /// Discount to the mid in price volatilities, only worked out with
+    /// [`MarketMakerConfig::min_distance_sigmas`](super::MarketMakerConfig::min_distance_sigmas)
+    /// set
+    pub distance_sigmas: Option<Decimal>,
+}
+
+/// A quote a tick inside the spread, see [`QuoteMode::JoinSpread`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JoinQuoteIntent {
+    pub side: OrderSide,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub mid_price: Decimal,
+    /// Quoted spread it goes inside of, in basis points
+    pub spread_bps: Decimal,
+}
+
+/// An active order to cancel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CancelIntent {
+    /// Position in the active orders
+    #[serde(skip)]
+    pub(super) index: usize,
+    pub order_id: String,
+    pub price: Decimal,
+    pub reason: CancelReason,
+    /// Distance below the best bid as a percentage, `None` when the whole book is cancelled
+    pub distance_pct: Option<Decimal>,
+}
+
+impl MarketMaker {
+    /// What the maker would do on the next book update given the current state, without
+    /// changing anything.
+    ///
+    /// A stale book, a halt or a suppression window cancels everything. Otherwise
+    /// cancellations come first, as they free order slots and raise the k-factor before
+    /// placement. With every slot taken, a placement replacing a worse order comes right after
+    /// its cancel. The order throttle isn't consulted, so throttled actions show up too. In
+    /// [`QuoteMode::JoinSpread`] the placements are quotes inside the spread for each side
+    /// without one.
+    pub fn preview(&self) -> Vec<Action> {
+        let cancel_all_reason = if self.is_book_stale(self.clock.now()) {
+            Some(CancelReason::StaleBook)
+        } else if self.is_halted() {
+            Some(CancelReason::Halted)
+        } else if self.is_suppressed(self.clock.now()) {
+            Some(CancelReason::Suppressed)
+        } else {
+            None
+        };
+        if let Some(reason) = cancel_all_reason {
+            return self
+                .active_orders
+                .iter()
+                .enumerate()
+                .map(|(index, order)| {
+                    Action::Cancel(CancelIntent {
+                        index,
+                        order_id: order.id.clone(),
+                        price: order.price,
+                        reason,
+                        distance_pct: None,
+                    })
+                })
+                .collect();
+        }
+
+        if let QuoteMode::JoinSpread { .. } = self.config.quote_mode {
+            let cancels = self.join_cancel_intents();
+            let joins: Vec<Action> = self
+                .join_quote_intents()
+                .into_iter()
+                .filter(|quote| {
+                    !self.active_orders.iter().enumerate().any(|(index, order)| {
+                        order.side == quote.side
+                            && !cancels.iter().any(|cancel| cancel.index == index)
+                    })
+                })
+                .map(Action::Join)
+                .collect();
+            return cancels
+                .into_iter()
+                .map(Action::Cancel)
+                .chain(joins)
+                .collect();
+        }
+
+        let cancels = self.cancel_intents();
+        let current_k = if cancels.is_empty() {
+            self.current_k
+        } else {
+            self.k_policy.next_k(self.current_k, &KOutcome::Cancelled)
+        };
+        let remaining_orders = self.active_orders.len() - cancels.len();
+
+        let mut actions: Vec<Action> = cancels.into_iter().map(Action::Cancel).collect();
+        if let Some(intent) = self.stink_bid_intent(current_k) {
+            if remaining_orders < self.config.max_active_orders {
+                actions.push(Action::Place(intent));
+            } else if let Some(replaced) = self.replacement_for(&intent) {
+                actions.push(Action::Cancel(replaced));
+                actions.push(Action::Place(intent));
+            }
+        }
+        actions
+    }
+}
@@ -0,0 +1,18 @@
+use rust_decimal::Decimal;
+
+/// Price shift that leans quotes against the current position
+///
+/// `skew_factor * net_inventory / max_inventory * price_volatility`, with the inventory ratio
+/// capped to [-1, 1]. Subtracting it from quote prices lowers bids and tightens asks when
+/// long, and does the reverse when short.
+pub fn inventory_skew(
+    skew_factor: Decimal,
+    net_inventory: Decimal,
+    max_inventory: Decimal,
+    price_volatility: Decimal,
+) -> Decimal {
+    let Some(ratio) = net_inventory.checked_div(max_inventory) else {
+        return Decimal::ZERO;
+    };
+    skew_factor * ratio.clamp(Decimal::NEGATIVE_ONE, Decimal::ONE) * price_volatility
+}
@@ -0,0 +1,171 @@
+use rust_decimal::Decimal;
+use tracing::{debug, info};
+
+use super::{
+    CancelIntent, CancelReason, JoinQuoteIntent, MarketMaker, MarketRegime, Order, OrderEvent,
+    OrderSide, OrderStatus, QuoteMode,
+};
+
+impl MarketMaker {
+    /// Quotes a tick inside the spread on each side the inventory allows, empty when the
+    /// spread is too tight, volatility too high, the regime wrong or the maker not yet warmed
+    /// up. See [`QuoteMode::JoinSpread`].
+    pub(super) fn join_quote_intents(&self) -> Vec<JoinQuoteIntent> {
+        let QuoteMode::JoinSpread {
+            min_spread_bps,
+            max_volatility,
+        } = self.config.quote_mode
+        else {
+            return Vec::new();
+        };
+        if !self.is_warmed_up() || self.is_book_stale(self.clock.now()) {
+            return Vec::new();
+        }
+        let regime = self.market_regime();
+        if !matches!(regime, MarketRegime::Normal | MarketRegime::LowLiquidity) {
+            debug!("Not quoting inside the spread in a {:?} market", regime);
+            return Vec::new();
+        }
+        if self.last_volatility.is_zero() || self.last_volatility > max_volatility {
+            debug!(
+                "Volatility {} outside (0, {}], not quoting inside the spread",
+                self.config.for_display(self.last_volatility),
+                max_volatility
+            );
+            return Vec::new();
+        }
+        let (Some((best_bid, _)), Some((best_ask, _)), Some(mid_price), Some(spread_bps)) = (
+            self.order_book.best_bid,
+            self.order_book.best_ask,
+            self.order_book.mid_price,
+            self.order_book.spread_bps,
+        ) else {
+            return Vec::new();
+        };
+
+        // The spread has to pay for both fills too
+        let min_edge_bps =
+            self.config.fee_model.round_trip_maker_bps() + self.config.min_edge_buffer_bps;
+        if spread_bps < min_spread_bps || spread_bps < min_edge_bps {
+            debug!(
+                "Spread of {}bps below {}bps, not quoting inside it",
+                self.config.for_display(spread_bps),
+                min_spread_bps.max(min_edge_bps)
+            );
+            return Vec::new();
+        }
+
+        let tick = self.config.precision.tick_size;
+        let bid = self.config.precision.floor_to_tick(best_bid + tick);
+        let ask = self.config.precision.floor_to_tick(best_ask - tick);
+        // A spread of two ticks or less leaves no room for both quotes
+        if bid >= ask {
+            return Vec::new();
+        }
+
+        let size = self.config.order_size;
+        let max_inventory = self.config.max_inventory;
+        let mut intents = Vec::new();
+        if self.net_inventory + size <= max_inventory {
+            intents.push(JoinQuoteIntent {
+                side: OrderSide::Buy,
+                price: bid,
+                size,
+                mid_price,
+                spread_bps,
+            });
+        }
+        if self.net_inventory - size >= -max_inventory {
+            intents.push(JoinQuoteIntent {
+                side: OrderSide::Sell,
+                price: ask,
+                size,
+                mid_price,
+                spread_bps,
+            });
+        }
+        intents
+    }
+
+    /// Join spread quotes to pull: stepped ahead of, or on a side that shouldn't be quoted
+    /// any more
+    pub(super) fn join_cancel_intents(&self) -> Vec<CancelIntent> {
+        let quotes = self.join_quote_intents();
+        self.active_orders
+            .iter()
+            .enumerate()
+            .filter(|(_, order)| {
+                let still_quoted = quotes.iter().any(|quote| quote.side == order.side);
+                // Our own quote may be the touch, only a better price from someone else counts
+                let stepped_ahead = match order.side {
+                    OrderSide::Buy => self
+                        .order_book
+                        .best_bid
+                        .is_some_and(|(best_bid, _)| best_bid > order.price),
+                    OrderSide::Sell => self
+                        .order_book
+                        .best_ask
+                        .is_some_and(|(best_ask, _)| best_ask < order.price),
+                };
+                !still_quoted || stepped_ahead
+            })
+            .map(|(index, order)| CancelIntent {
+                index,
+                order_id: order.id.clone(),
+                price: order.price,
+                reason: CancelReason::Requote,
+                distance_pct: None,
+            })
+            .collect()
+    }
+
+    /// Quotes inside the spread on each side without an active quote
+    pub(super) fn place_join_quotes(&mut self) {
+        if self.is_halted() || self.is_suppressed(self.clock.now()) {
+            return;
+        }
+        for intent in self.join_quote_intents() {
+            if self
+                .active_orders
+                .iter()
+                .any(|order| order.side == intent.side)
+                || !self.try_order_action("join placement")
+            {
+                continue;
+            }
+            let now = self.clock.now();
+            let order = Order {
+                id: self.next_order_id(&format!("join-{:?}", intent.side).to_lowercase()),
+                price: intent.price,
+                size: intent.size,
+                side: intent.side.clone(),
+                status: OrderStatus::Placed,
+                created_at: now,
+                filled_at: None,
+                reference_mid: intent.mid_price,
+                reference_best_bid: self
+                    .order_book
+                    .best_bid
+                    .map(|(price, _)| price)
+                    .unwrap_or_default(),
+                k_factor_used: Decimal::ZERO,
+                imbalance_at_placement: self.imbalance_reading(),
+                distance_sigmas: None,
+                // A new price level, nobody is ahead of us
+                queue_ahead: Decimal::ZERO,
+                cancel_reason: None,
+                fill_quality: None,
+                fill_source: None,
+            };
+            info!(
+                "Quoting {:?} inside the {}bps spread at {}",
+                intent.side,
+                self.config.for_display(intent.spread_bps),
+                self.config.precision.format_price(intent.price)
+            );
+            self.audit_log.record(&order, OrderEvent::Placed, now);
+            self.active_orders.push(order);
+            self.attempt_count += 1;
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::TimeDelta;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::fmt::Debug;
+
+/// What happened to a stink bid, as fed to a [`KFactorPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KOutcome {
+    Filled {
+        /// Fill price below the mid at placement, as a percentage of that mid
+        distance_pct: Decimal,
+        /// Whether the price was still falling through the order, see
+        /// [`FillQuality::Adverse`](super::FillQuality::Adverse)
+        adverse: bool,
+        /// Time the order rested before filling
+        time_to_fill: TimeDelta,
+    },
+    /// Pulled for sitting too far from or too close to the best bid
+    Cancelled,
+}
+
+/// How the k-factor adapts to order outcomes
+pub trait KFactorPolicy: Debug + Send + Sync {
+    /// The k-factor to use from now on, given the current one and what just happened
+    fn next_k(&self, current_k: Decimal, outcome: &KOutcome) -> Decimal;
+}
+
+/// Scales k down by the learning rate after a benign fill and up after anything else, within
+/// fixed bounds. The maker's default policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiplicativePolicy {
+    pub learning_rate: Decimal,
+    pub min_k: Decimal,
+    pub max_k: Decimal,
+}
+
+impl MultiplicativePolicy {
+    pub fn new(learning_rate: Decimal) -> Self {
+        Self {
+            learning_rate,
+            min_k: dec!(0.1),
+            max_k: dec!(3.0),
+        }
+    }
+}
+
+impl KFactorPolicy for MultiplicativePolicy {
+    fn next_k(&self, current_k: Decimal, outcome: &KOutcome) -> Decimal {
+        match outcome {
+            // If order was filled successfully, slightly decrease k to be more aggressive
+            KOutcome::Filled { adverse: false, .. } => {
+                (current_k * (Decimal::ONE - self.learning_rate)).max(self.min_k)
+            }
+            // If order wasn't filled, or we were run over, increase k to be more conservative
+            KOutcome::Filled { adverse: true, .. } | KOutcome::Cancelled => {
+                (current_k * (Decimal::ONE + self.learning_rate)).min(self.max_k)
+            }
+        }
+    }
+}
@@ -1,17 +1,67 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, MathematicalOps};
 use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::{debug, info, warn};
 
 use crate::{
-    binance::data::{AggregateTrade, DepthUpdate},
-    order_book_state::OrderBookState,
-    recent_trades::{self, RecentTrades, Trade},
+    binance::{
+        VolumeProfile,
+        data::{BookTickerEvent, DepthSnapshot, DepthUpdate, KlineEventData, WindowTickerData},
+    },
+    clock::{Clock, ClockSkew, SystemClock},
+    flow_spike::{FlowSpikeConfig, FlowSpikeDetector},
+    kline_store::KlineStore,
+    order_book_state::{BookQualityWeights, DepthWeighting, OrderBookState},
+    realized_volatility::RealizedVolatility,
+    recent_trades::{RecentTrades, Trade},
+    symbol::Symbol,
 };
 
+mod adaptive_state;
+mod audit;
+mod builder;
+mod features;
+mod imbalance_band;
+mod intensity;
+mod intent;
+mod inventory;
+mod join_spread;
+mod k_policy;
+mod precision;
+mod report;
+mod schedule;
+mod session;
+mod signal_research;
+mod throttle;
+
+pub use adaptive_state::AdaptiveState;
+pub use audit::{AuditRecord, OrderAuditLog, OrderEvent};
+pub use builder::MarketMakerConfigBuilder;
+pub use features::{FEATURE_DEPTH, FeatureCsvWriter, FeatureSnapshot};
+pub use imbalance_band::ImbalanceBand;
+pub use intensity::{ArrivalIntensity, IntensityEstimate};
+pub use intent::{Action, CancelIntent, JoinQuoteIntent, StinkBidIntent};
+pub use inventory::inventory_skew;
+pub use k_policy::{KFactorPolicy, KOutcome, MultiplicativePolicy};
+pub use precision::{SymbolPrecision, format_to_increment};
+pub use report::{VALUE_AREA_FRACTION, VolumeProfileReport};
+pub use schedule::SuppressionWindow;
+pub use session::SessionExport;
+pub use signal_research::{ForwardHorizon, ForwardReturnJoiner, SignalOutcome};
+pub use throttle::OrderThrottle;
+
 /// Configuration parameters for the simplified market maker
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MarketMakerConfig {
     /// Base k-factor for stink bid distance (multiplier of volatility)
     pub base_k: Decimal,
@@ -19,16 +69,145 @@ pub struct MarketMakerConfig {
     pub order_size: Decimal,
     /// Maximum number of active orders
     pub max_active_orders: usize,
-    /// Strong imbalance threshold for aggressive stink bids
+    /// Imbalance below which stink bids get aggressive. Imbalances are positive when bids
+    /// outweigh asks, so strong sell pressure is a negative threshold.
     pub strong_imbalance_threshold: Decimal,
-    /// Moderate imbalance threshold for normal stink bids
+    /// Imbalance below which stink bids use the plain k, negative like the strong threshold
     pub moderate_imbalance_threshold: Decimal,
+    /// How far past an imbalance threshold the imbalance has to go to change the stink bid
+    /// aggression, either way. Zero switches right at the thresholds.
+    pub imbalance_hysteresis: Decimal,
+    /// Whether to place stink bids or quote inside the spread
+    pub quote_mode: QuoteMode,
+    /// Where [`MarketMaker::market_regime`] draws the lines between regimes
+    pub regime_thresholds: RegimeThresholds,
     /// Volatility dampening factor
     pub vol_dampening: Decimal,
     /// Learning rate for k-factor adaptation
     pub learning_rate: Decimal,
-    /// Minimum distance between stink bid and best bid (as percentage)
+    /// Minimum distance between stink bid and best bid, as a percentage of the best bid.
+    /// Bids always rest at least one tick below the best bid, whatever this is set to.
     pub min_distance_pct: Decimal,
+    /// Record every order lifecycle transition in the audit log
+    pub audit_log_enabled: bool,
+    /// Which order book imbalance metric drives the strategy
+    pub imbalance_method: ImbalanceMethod,
+    /// Number of levels per side used by the depth based imbalance methods
+    pub imbalance_depth: usize,
+    /// How [`ImbalanceMethod::Weighted`] weights the levels below the touch
+    pub imbalance_weighting: DepthWeighting,
+    /// Squashing applied to the imbalance before the maker acts on it
+    pub imbalance_normalization: ImbalanceNormalization,
+    /// Fair value that stink bids are priced from
+    pub mid_source: MidSource,
+    /// Number of levels per side used by [`MidSource::WeightedMid`]
+    pub mid_depth: usize,
+    /// Exchange fees applied when booking fills
+    pub fee_model: FeeModel,
+    /// Edge over the round trip maker fees a stink bid's discount must clear, in basis points
+    pub min_edge_buffer_bps: Decimal,
+    /// Where the volatility used for pricing comes from
+    pub volatility_source: VolatilitySource,
+    /// Lower bound on the (dampened) per-trade return volatility used for pricing
+    pub min_volatility: Decimal,
+    /// Upper bound on the (dampened) per-trade return volatility used for pricing
+    pub max_volatility: Decimal,
+    /// Longest gap since the last book update before quoting is paused, in milliseconds
+    #[serde(with = "crate::config::duration_ms")]
+    pub max_book_staleness: Duration,
+    /// Longest an order may rest before it's cancelled, in milliseconds. Zero disables expiry.
+    #[serde(with = "crate::config::duration_ms")]
+    pub order_ttl: Duration,
+    /// Trades to see before placing stink bids, so the volatility estimate has some history
+    pub warmup_trades: usize,
+    /// Book updates to see before placing stink bids
+    pub warmup_book_updates: usize,
+    /// Time since the maker started before placing stink bids, in milliseconds
+    #[serde(with = "crate::config::duration_ms")]
+    pub warmup_duration: Duration,
+    /// Only place stink bids while the book's quality score is at least this, `None` disables
+    /// the gate. See [`OrderBookState::quality_score`].
+    pub min_book_quality: Option<Decimal>,
+    /// Weights and scales of the book quality score
+    pub book_quality_weights: BookQualityWeights,
+    /// Don't place, and cancel, stink bids shallower than this many price volatilities below
+    /// the fair value, so the threshold holds across volatility regimes. `None` disables it.
+    pub min_distance_sigmas: Option<Decimal>,
+    /// Cancel an order once it's more than `cancel_far_multiplier * k` percent below the best bid
+    pub cancel_far_multiplier: Decimal,
+    /// Cancel an order once it's less than `cancel_near_multiplier * min_distance_pct` percent
+    /// below the best bid
+    pub cancel_near_multiplier: Decimal,
+    /// Exclude our own resting orders from the book's imbalance metrics. Only enable when
+    /// orders are actually resting on the exchange, simulated orders never enter the book.
+    pub exclude_own_orders: bool,
+    /// Take the liquidity a trade printed through out of the book straight away, rather than
+    /// waiting for the depth update confirming it. A heuristic, so off by default.
+    pub decay_book_on_trades: bool,
+    /// Cancel orders immediately when the imbalance moves by at least this much in a single
+    /// update and differs from the order's placement imbalance by as much. `None` disables it.
+    pub regime_shift_threshold: Option<Decimal>,
+    /// Cancel an order once the imbalance has fallen at least this far below its imbalance at
+    /// placement, i.e. sell pressure built up behind it. `None` disables it.
+    pub adverse_imbalance_delta: Option<Decimal>,
+    /// With every order slot taken, replace the resting order with the lowest expected edge
+    /// when a new stink bid's is at least this many basis points higher. `None` keeps the
+    /// slots first come first served.
+    pub replace_edge_threshold_bps: Option<Decimal>,
+    /// Number of recent trades the price move at fill time is measured over
+    pub adverse_fill_lookback: usize,
+    /// A fill is adverse when the price fell at least this many percent over the lookback
+    pub adverse_fill_threshold_pct: Decimal,
+    /// How strongly quotes lean against the position, in multiples of the price volatility
+    /// at `max_inventory`. Zero disables the skew.
+    pub inventory_skew_factor: Decimal,
+    /// Position (in base asset) at which the inventory skew is at its full strength
+    pub max_inventory: Decimal,
+    /// Halt, cancelling everything and placing nothing, once equity falls this far (in quote
+    /// asset) below its peak. `None` disables the circuit breaker.
+    pub max_drawdown: Option<Decimal>,
+    /// Lift a drawdown halt once the drawdown is back to at most this, `None` keeps it until
+    /// [`MarketMaker::reset_halt`]
+    pub drawdown_resume: Option<Decimal>,
+    /// Scheduled times to pull every order and stop quoting, such as funding or a data
+    /// release. Empty quotes around the clock.
+    pub suppression_windows: Vec<SuppressionWindow>,
+    /// File the adaptive k-factor and fill counts are restored from at startup and saved to
+    /// by maintenance ticks that find them changed, `None` starts every run from `base_k`
+    pub state_path: Option<PathBuf>,
+    /// Oldest saved state restored at startup, in milliseconds. Older state describes a
+    /// market that has moved on.
+    #[serde(with = "crate::config::duration_ms")]
+    pub max_state_age: Duration,
+    /// Most order actions (placements and cancels) sent in any one second
+    pub max_orders_per_second: u32,
+    /// Most order actions (placements and cancels) sent in any one minute
+    pub max_orders_per_minute: u32,
+    /// Whether stink bids are held to the value area low to POC support zone
+    pub value_area_bids: ValueAreaBids,
+    /// Price bucket size of the volume profile behind [`Self::value_area_bids`]
+    pub volume_profile_bucket_size: Decimal,
+    /// Only profile prices within this many basis points of the mid, unbounded when unset
+    pub volume_profile_band_bps: Option<Decimal>,
+    /// Width of the fill distance buckets behind [`MarketMaker::arrival_intensity`], in price
+    pub intensity_bucket_size: Decimal,
+    /// What fills are taken from, execution reports live and trade inference in simulation
+    pub fill_source: FillSource,
+    /// Let an inferred fill take only part of an order when the trade that reached it is too
+    /// small for all of it, leaving the rest resting. Off, such an order doesn't fill at all.
+    pub partial_fills: bool,
+    /// How active orders are treated after a reconnect without exchange state to check against
+    pub reconnect_policy: ReconnectPolicy,
+    /// Fractional k reduction per percent of rolling window price change, zero turns the trend
+    /// filter off. An uptrend tightens stink bids to buy the dip, a downtrend widens them.
+    pub trend_sensitivity: Decimal,
+    /// Largest fractional k change the trend filter makes either way, below 1
+    pub max_trend_adjustment: Decimal,
+    /// Tick and step size of the traded symbol, used when logging prices and sizes
+    pub precision: SymbolPrecision,
+    /// Decimal places for volatility, imbalance, k-factor and percentages in logs and
+    /// statistics. Display only, computations keep full precision.
+    pub display_precision: u32,
 }
 impl Default for MarketMakerConfig {
     fn default() -> Self {
@@ -38,19 +217,258 @@ impl Default for MarketMakerConfig {
             max_active_orders: 3,   // Maximum concurrent orders
             strong_imbalance_threshold: dec!(-0.7), // Strong sell pressure
             moderate_imbalance_threshold: dec!(-0.3), // Moderate sell pressure
-            vol_dampening: dec!(0.8), // Reduce volatility impact
-            learning_rate: dec!(0.05), // 5% adjustment per success/failure
+            imbalance_hysteresis: Decimal::ZERO,
+            quote_mode: QuoteMode::StinkBids,
+            regime_thresholds: RegimeThresholds::default(),
+            vol_dampening: dec!(0.8),     // Reduce volatility impact
+            learning_rate: dec!(0.05),    // 5% adjustment per success/failure
             min_distance_pct: dec!(0.05), // Minimum 0.05% distance from best bid
+            audit_log_enabled: true,
+            imbalance_method: ImbalanceMethod::TopOfBook,
+            imbalance_depth: 5,
+            imbalance_weighting: DepthWeighting::Harmonic,
+            imbalance_normalization: ImbalanceNormalization::Raw,
+            mid_source: MidSource::Mid,
+            mid_depth: 5,
+            fee_model: FeeModel::default(),
+            min_edge_buffer_bps: dec!(0),
+            volatility_source: VolatilitySource::Trades,
+            min_volatility: dec!(0.00000001), // 0.000001% per trade
+            max_volatility: dec!(0.01),       // 1% per trade
+            max_book_staleness: Duration::from_secs(5), // Depth stream updates every 100ms
+            order_ttl: Duration::ZERO,
+            warmup_trades: 0,
+            warmup_book_updates: 0,
+            warmup_duration: Duration::ZERO,
+            min_book_quality: None,
+            book_quality_weights: BookQualityWeights::default(),
+            exclude_own_orders: false,
+            decay_book_on_trades: false,
+            min_distance_sigmas: None,
+            cancel_far_multiplier: dec!(5), // k of 0.5 cancels beyond 2.5% below best bid
+            cancel_near_multiplier: dec!(0.5), // Half the minimum placement distance
+            regime_shift_threshold: None,
+            adverse_imbalance_delta: None,
+            replace_edge_threshold_bps: None,
+            adverse_fill_lookback: 10,
+            adverse_fill_threshold_pct: dec!(0.05),
+            inventory_skew_factor: Decimal::ZERO,
+            max_inventory: dec!(0.1), // Ten default sized fills
+            max_drawdown: None,
+            drawdown_resume: None,
+            suppression_windows: Vec::new(),
+            state_path: None,
+            max_state_age: Duration::from_secs(60 * 60),
+            max_orders_per_second: 5, // Binance spot allows 50 orders per 10s
+            max_orders_per_minute: 200,
+            value_area_bids: ValueAreaBids::Off,
+            volume_profile_bucket_size: dec!(10), // 10 USDT buckets for BTCUSDT
+            volume_profile_band_bps: None,
+            intensity_bucket_size: dec!(10),
+            fill_source: FillSource::TradeInference,
+            partial_fills: false,
+            reconnect_policy: ReconnectPolicy::AssumeUnknown,
+            trend_sensitivity: Decimal::ZERO,
+            max_trend_adjustment: dec!(0.5),
+            precision: SymbolPrecision::default(),
+            display_precision: 4,
+        }
+    }
+}
+
+/// Exchange commission rates in basis points of traded notional
+///
+/// Negative rates represent rebates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeeModel {
+    /// Fee for liquidity-providing (resting) fills
+    pub maker_bps: Decimal,
+    /// Fee for liquidity-taking fills
+    pub taker_bps: Decimal,
+}
+
+impl Default for FeeModel {
+    fn default() -> Self {
+        Self {
+            // 0.01%, Binance spot's base tier is 10 bps (0.1%) before discounts
+            maker_bps: dec!(1.0),
+            taker_bps: dec!(1.0),
+        }
+    }
+}
+
+impl MarketMakerConfig {
+    /// Rounds a metric to `display_precision` for logging, never feed the result back into
+    /// pricing
+    pub fn for_display(&self, value: Decimal) -> Decimal {
+        value.round_dp(self.display_precision)
+    }
+}
+
+impl FeeModel {
+    /// Fees for buying and selling back with resting orders, in basis points
+    pub fn round_trip_maker_bps(&self) -> Decimal {
+        self.maker_bps * Decimal::TWO
+    }
+
+    pub fn maker_fee(&self, price: Decimal, size: Decimal) -> Decimal {
+        price * size * self.maker_bps / dec!(10000)
+    }
+
+    pub fn taker_fee(&self, price: Decimal, size: Decimal) -> Decimal {
+        price * size * self.taker_bps / dec!(10000)
+    }
+}
+
+/// Fair value used as the reference for stink bid pricing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MidSource {
+    /// Halfway between the best bid and ask
+    Mid,
+    /// Top of book mid weighted by the opposite side's size
+    Microprice,
+    /// Average of the bid and ask VWAPs over the top `mid_depth` levels
+    WeightedMid,
+    /// Price of the latest trade in the recent trades
+    LastTrade,
+}
+
+/// Where the maker learns its orders filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FillSource {
+    /// Inferred from public trades printing at or through the order's price, for simulation
+    TradeInference,
+    /// Reported by the execution client, see [`MarketMaker::handle_execution_fill`]. Public
+    /// trades then never fill an order.
+    ExecutionReport,
+}
+
+/// How stink bids outside the volume profile's support zone are handled
+///
+/// The zone runs from the value area low up to the point of control, below the POC there's
+/// traded volume to lean on, above it there isn't.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ValueAreaBids {
+    /// Price purely from volatility
+    #[default]
+    Off,
+    /// Move bids outside the zone to its nearest edge
+    Snap,
+    /// Don't place bids outside the zone
+    Skip,
+}
+
+/// Volatility input for stink bid pricing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VolatilitySource {
+    /// Standard deviation of trade-to-trade returns, see [`RecentTrades::volatility`]
+    Trades,
+    /// Average True Range of closed candles as a fraction of the last close. Steadier than
+    /// trade returns when tick flow is thin, falling back to them until enough candles closed.
+    ///
+    /// This is a per-candle rather than per-trade move, so it usually wants a smaller `base_k`.
+    Atr { interval: String, period: usize },
+}
+
+/// How the maker quotes, picked per symbol
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuoteMode {
+    /// Deep bids below the fair value, waiting for a flush to fill them
+    #[default]
+    StinkBids,
+    /// Earn the spread on wide, quiet books: a bid a tick above the best bid and an ask a tick
+    /// below the best ask, within [`MarketMakerConfig::max_inventory`] either way. Only in a
+    /// [`MarketRegime::Normal`] or [`MarketRegime::LowLiquidity`] market.
+    JoinSpread {
+        /// Relative spread in basis points from which quotes go inside it
+        min_spread_bps: Decimal,
+        /// Highest dampened per-trade return volatility to quote inside the spread at
+        max_volatility: Decimal,
+    },
+}
+
+/// Thresholds [`MarketMaker::market_regime`] classifies the market by, checked in field order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegimeThresholds {
+    /// Dampened per-trade return volatility from which the market is highly volatile
+    pub high_volatility: Decimal,
+    /// Rolling window price change percentage, either way, from which the market is trending
+    pub trend_pct: Decimal,
+    /// Book quality score below which liquidity is low, see [`OrderBookState::quality_score`]
+    pub low_liquidity_quality: Decimal,
+}
+
+impl Default for RegimeThresholds {
+    fn default() -> Self {
+        Self {
+            high_volatility: dec!(0.001), // 10bps per trade
+            trend_pct: dec!(2),
+            low_liquidity_quality: dec!(0.3),
+        }
+    }
+}
+
+/// Order book imbalance metric used for stink bid decisions
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ImbalanceMethod {
+    /// Best bid vs best ask volume only
+    TopOfBook,
+    /// Summed volume over the top `imbalance_depth` levels
+    Depth,
+    /// Volume over the top `imbalance_depth` levels, weighted towards the touch by
+    /// `imbalance_weighting`
+    Weighted,
+}
+
+/// Squashing of the imbalance the maker acts on, see [`MarketMaker::current_imbalance`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImbalanceNormalization {
+    /// The metric as the order book reports it
+    #[default]
+    Raw,
+    /// Clipped to [-1, 1]
+    Clip,
+    /// `tanh(scale * imbalance)`, flattening out towards ±1 so a nearly empty side of the book
+    /// can't push the reading to the extremes. Bigger scales flatten out sooner.
+    Tanh { scale: Decimal },
+}
+
+impl ImbalanceNormalization {
+    pub fn apply(self, imbalance: Decimal) -> Decimal {
+        match self {
+            ImbalanceNormalization::Raw => imbalance,
+            ImbalanceNormalization::Clip => imbalance.clamp(Decimal::NEGATIVE_ONE, Decimal::ONE),
+            ImbalanceNormalization::Tanh { scale } => {
+                // tanh is 1 to Decimal precision long before e^2x overflows
+                let x = (scale * imbalance).abs().min(dec!(20));
+                let magnitude = (x * Decimal::TWO)
+                    .checked_exp()
+                    .and_then(|e| (e - Decimal::ONE).checked_div(e + Decimal::ONE))
+                    .unwrap_or(Decimal::ONE);
+                // Odd, so computed on the magnitude for an exactly mirrored result
+                if imbalance.is_sign_negative() {
+                    -magnitude
+                } else {
+                    magnitude
+                }
+            }
         }
     }
 }
 
 /// Represents a single order in the market
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Order {
     pub id: String,
     pub price: Decimal,
     pub size: Decimal,
+    /// Stink bids are always buys, [`QuoteMode::JoinSpread`] quotes both sides
+    pub side: OrderSide,
     pub status: OrderStatus,
     pub created_at: DateTime<Utc>,
     pub filled_at: Option<DateTime<Utc>>,
@@ -58,16 +476,103 @@ pub struct Order {
     pub reference_best_bid: Decimal,
     pub k_factor_used: Decimal,
     pub imbalance_at_placement: Decimal,
+    /// Depth below the fair value at placement in units of the price volatility then, see
+    /// [`distance_in_sigmas`]. Only recorded with [`MarketMakerConfig::min_distance_sigmas`]
+    /// set.
+    pub distance_sigmas: Option<Decimal>,
+    /// Estimated volume resting ahead of this order at its price level
+    pub queue_ahead: Decimal,
+    pub cancel_reason: Option<CancelReason>,
+    /// Whether the price was still falling through the order when it filled
+    pub fill_quality: Option<FillQuality>,
+    /// What reported the fill
+    pub fill_source: Option<FillSource>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl Order {
+    /// Size with the sign of the position change a fill makes, negative for sells
+    pub fn signed_size(&self) -> Decimal {
+        match self.side {
+            OrderSide::Buy => self.size,
+            OrderSide::Sell => -self.size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum OrderStatus {
     New,
     Placed,
     Filled,
+    /// A slice of an order that filled with the rest still resting, see
+    /// [`MarketMakerConfig::partial_fills`]
+    PartiallyFilled,
     Cancelled,
 }
-#[derive(Debug, Clone, PartialEq)]
+
+/// How a fill looked relative to the price move around it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum FillQuality {
+    /// The price had settled, the fill has a fair chance of reverting in our favour
+    Benign,
+    /// The price was still falling fast, we likely caught a falling knife
+    Adverse,
+}
+
+/// Why an order was taken off the book
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum CancelReason {
+    /// Market moved away, leaving the order too deep to be useful
+    TooFar,
+    /// Best bid came too close, or the fair value did in volatility units, risking an
+    /// immediate fill
+    TooClose,
+    /// The order book stopped updating so the order's reference prices can't be trusted
+    StaleBook,
+    /// The imbalance flipped hard since the order was placed, so its pricing is out of date
+    RegimeShift,
+    /// Sell pressure built up since the order was placed, so a fill would likely be adverse
+    AdverseImbalance,
+    /// Gave up its slot to a stink bid with a higher expected edge, see
+    /// [`MarketMakerConfig::replace_edge_threshold_bps`]
+    Replaced,
+    /// Rested longer than [`MarketMakerConfig::order_ttl`]
+    Expired,
+    /// The maker is shutting down, see [`MarketMaker::flatten`]
+    Shutdown,
+    /// Missing from the exchange's open orders after a reconnect, so it may have filled or been
+    /// cancelled during the gap, see [`MarketMaker::reconcile_orders`]
+    Unconfirmed,
+    /// The maker halted, see [`MarketMaker::halt_reason`]
+    Halted,
+    /// Within a [`MarketMakerConfig::suppression_windows`] window or the pause after a flow
+    /// spike, see [`MarketMaker::with_flow_spike_detector`]
+    Suppressed,
+    /// A [`QuoteMode::JoinSpread`] quote was stepped ahead of, or the conditions for quoting
+    /// inside the spread no longer hold
+    Requote,
+}
+
+/// Why the maker stopped quoting until it recovers or is reset
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum HaltReason {
+    /// Equity fell at least [`MarketMakerConfig::max_drawdown`] below its peak
+    MaxDrawdown {
+        peak_equity: Decimal,
+        equity: Decimal,
+    },
+}
+
+/// What happens to our active orders when the market data connection is re-established
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReconnectPolicy {
+    /// Keep them, for when the gap was too short to matter
+    Trust,
+    /// Treat them all as unconfirmed, for simulation where there's no open orders state
+    AssumeUnknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum OrderSide {
     Buy,
     Sell,
@@ -85,7 +590,8 @@ pub struct MarketState {
     pub regime: MarketRegime,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Broad state of the market, see [`MarketMaker::market_regime`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum MarketRegime {
     Normal,
     HighVolatility,
@@ -94,25 +600,70 @@ pub enum MarketRegime {
     LowLiquidity,
 }
 /// Simplified manager for stink bid strategy
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MarketMaker {
     pub config: MarketMakerConfig,
     pub order_book: OrderBookState,
     pub recent_trades: RecentTrades,
+    /// Closed candles from the kline streams, for slower timeframe context
+    pub klines: KlineStore,
+    /// Traded volume by price over the session, see [`MarketMakerConfig::value_area_bids`]
+    pub volume_profile: VolumeProfile,
+    /// One minute realized volatility, for reporting rather than pricing
+    pub realized_volatility: RealizedVolatility,
+    /// Fill rate by distance from the mid, fitted from our own fills
+    pub intensity: ArrivalIntensity,
     pub active_orders: Vec<Order>,
     pub filled_orders: Vec<Order>,
     pub cancelled_orders: Vec<Order>,
+    pub audit_log: OrderAuditLog,
+    feature_tx: Option<UnboundedSender<FeatureSnapshot>>,
+    /// Pulls quotes on fast one-sided flow, see [`MarketMaker::with_flow_spike_detector`]
+    flow_spikes: Option<FlowSpikeDetector>,
+    /// Our time quoting resumes after the last flow spike
+    flow_spike_until: Option<DateTime<Utc>>,
+    /// Pairs imbalance readings with the forward return, see [`MarketMaker::set_signal_research`]
+    signal_research: Option<(ForwardReturnJoiner, UnboundedSender<SignalOutcome>)>,
+    clock: Arc<dyn Clock>,
+    /// Offset of `clock` from the exchange's, see [`MarketMaker::exchange_now`]
+    clock_skew: ClockSkew,
+    throttle: OrderThrottle,
+    /// Shared rather than boxed so the maker can be cloned
+    k_policy: Arc<dyn KFactorPolicy>,
+    /// Orders placed so far, numbering the order ids
+    order_sequence: u64,
 
     // Adaptive parameters
     current_k: Decimal,
     successful_fill_count: usize,
     attempt_count: usize,
+    /// Adaptive state as last restored or saved by maintenance
+    saved_adaptive_state: AdaptiveState,
 
     // Performance tracking
-    last_imbalance: Decimal,
+    /// Latest imbalance reading, `None` until the book has produced one
+    last_imbalance: Option<Decimal>,
+    /// Aggression band of the imbalance, see [`MarketMakerConfig::imbalance_hysteresis`]
+    imbalance_band: ImbalanceBand,
+    /// Dampened per-trade return volatility, see [`RecentTrades::volatility`]
     last_volatility: Decimal,
+    /// Price change percentage of the latest rolling window ticker
+    window_price_change_pct: Option<Decimal>,
+    gross_pnl: Decimal,
+    fees_paid: Decimal,
+    /// Position in the base asset built up from fills, positive when long
+    net_inventory: Decimal,
+    /// Highest [`MarketMaker::equity`] seen, the drawdown is measured from it
+    peak_equity: Decimal,
+    halt_reason: Option<HaltReason>,
+    throttle_hits: usize,
+    /// See [`MarketMaker::fill_divergences`]
+    fill_divergences: usize,
 
     // State tracking
+    started_at: DateTime<Utc>,
+    trades_seen: usize,
+    book_updates_seen: usize,
     last_update_time: DateTime<Utc>,
     debug_mode: bool,
 }
@@ -123,308 +674,1669 @@ impl MarketMaker {
         order_book: OrderBookState,
         recent_trades: RecentTrades,
     ) -> Self {
-        Self {
+        Self::with_clock(config, order_book, recent_trades, Arc::new(SystemClock))
+    }
+
+    /// Creates a market maker that takes the time from `clock` rather than the system clock
+    pub fn with_clock(
+        config: MarketMakerConfig,
+        mut order_book: OrderBookState,
+        recent_trades: RecentTrades,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        order_book.reserve_metric_depth(config.imbalance_depth.max(config.mid_depth));
+        let order_book = order_book.with_quality_weights(config.book_quality_weights.clone());
+        let mut maker = Self {
             current_k: config.base_k,
+            // Set from the restored state below
+            saved_adaptive_state: AdaptiveState {
+                saved_at: clock.now(),
+                current_k: config.base_k,
+                successful_fill_count: 0,
+                attempt_count: 0,
+            },
+            k_policy: Arc::new(MultiplicativePolicy::new(config.learning_rate)),
+            order_sequence: 0,
+            throttle: OrderThrottle::new(
+                config.max_orders_per_second,
+                config.max_orders_per_minute,
+            ),
+            audit_log: OrderAuditLog::new(config.audit_log_enabled),
+            feature_tx: None,
+            flow_spikes: None,
+            flow_spike_until: None,
+            signal_research: None,
+            volume_profile: match config.volume_profile_band_bps {
+                Some(bps) => {
+                    VolumeProfile::new(config.volume_profile_bucket_size).with_price_band_bps(bps)
+                }
+                None => VolumeProfile::new(config.volume_profile_bucket_size),
+            },
+            intensity: ArrivalIntensity::new(config.intensity_bucket_size, clock.now()),
+            imbalance_band: ImbalanceBand::classify(
+                Decimal::ZERO,
+                config.strong_imbalance_threshold,
+                config.moderate_imbalance_threshold,
+            ),
             config,
             order_book,
             recent_trades,
+            klines: KlineStore::default(),
+            realized_volatility: RealizedVolatility::default(),
             active_orders: Vec::new(),
             filled_orders: Vec::new(),
             cancelled_orders: Vec::new(),
             successful_fill_count: 0,
             attempt_count: 0,
-            last_imbalance: Decimal::ZERO,
+            last_imbalance: None,
             last_volatility: Decimal::ZERO,
-            last_update_time: Utc::now(),
+            window_price_change_pct: None,
+            gross_pnl: Decimal::ZERO,
+            fees_paid: Decimal::ZERO,
+            net_inventory: Decimal::ZERO,
+            peak_equity: Decimal::ZERO,
+            halt_reason: None,
+            throttle_hits: 0,
+            fill_divergences: 0,
+            started_at: clock.now(),
+            trades_seen: 0,
+            book_updates_seen: 0,
+            last_update_time: clock.now(),
+            clock,
+            clock_skew: ClockSkew::default(),
             debug_mode: true, // Set to true for detailed logging
+        };
+        maker.restore_adaptive_state();
+        maker.saved_adaptive_state = maker.adaptive_state();
+        maker
+    }
+
+    /// Adapts the k-factor with `k_policy` instead of the default [`MultiplicativePolicy`]
+    pub fn with_k_policy(mut self, k_policy: impl KFactorPolicy + 'static) -> Self {
+        self.k_policy = Arc::new(k_policy);
+        self
+    }
+
+    /// Pulls every order and stops placing for a [`FlowSpikeConfig::window`] whenever the
+    /// trade flow spikes, see [`FlowSpikeDetector`]
+    pub fn with_flow_spike_detector(mut self, config: FlowSpikeConfig) -> Self {
+        self.flow_spikes = Some(FlowSpikeDetector::new(config));
+        self
+    }
+
+    /// Symbol being traded, if the order book was given one
+    pub fn symbol(&self) -> Option<&Symbol> {
+        self.order_book.symbol()
+    }
+
+    /// Whether a bid filled now would likely be adversely selected: the price fell by at least
+    /// `adverse_fill_threshold_pct` over the last `adverse_fill_lookback` trades.
+    pub fn is_adverse_selection_likely(&self) -> bool {
+        self.recent_trades
+            .price_movement(self.config.adverse_fill_lookback)
+            .is_some_and(|movement| movement.pct <= -self.config.adverse_fill_threshold_pct)
+    }
+
+    fn fill_quality(&self) -> FillQuality {
+        if self.is_adverse_selection_likely() {
+            FillQuality::Adverse
+        } else {
+            FillQuality::Benign
         }
     }
+
+    /// Percentage of filled orders classified as [`FillQuality::Adverse`]
+    pub fn adverse_fill_rate(&self) -> Decimal {
+        let adverse = self
+            .filled_orders
+            .iter()
+            .filter(|order| order.fill_quality == Some(FillQuality::Adverse))
+            .count();
+        Decimal::from(adverse)
+            .checked_div(Decimal::from(self.filled_orders.len()))
+            .map_or(Decimal::ZERO, |rate| rate * dec!(100))
+    }
+
     /// Updates order book state with a new depth update
     pub fn handle_depth_update(&mut self, update: DepthUpdate) -> Result<()> {
+        self.clock_skew.record(self.clock.now(), update.event_time);
         // Process the update to our order book
         self.order_book.process_update(update)?;
+        self.on_book_change()
+    }
+
+    /// Corrects the top of the book from a partial depth snapshot, see
+    /// [`OrderBookState::apply_partial_snapshot`]
+    pub fn handle_partial_depth(&mut self, snapshot: DepthSnapshot) -> Result<()> {
+        if !self.order_book.apply_partial_snapshot(snapshot) {
+            return Ok(());
+        }
+        self.on_book_change()
+    }
+
+    /// Reacts to a top of book move reported by the bookTicker stream ahead of the depth stream
+    pub fn handle_book_ticker(&mut self, ticker: &BookTickerEvent) -> Result<()> {
+        if !self.order_book.apply_book_ticker(ticker) {
+            return Ok(());
+        }
+        self.on_book_change()
+    }
+
+    /// Re-evaluates orders against the current book
+    fn on_book_change(&mut self) -> Result<()> {
+        self.book_updates_seen += 1;
+        if let Some(mid) = self.order_book.mid_price {
+            self.volume_profile.recenter(mid);
+        }
 
         // Update tracking values
-        if let Some(imbalance) = self.order_book.imbalance {
-            self.last_imbalance = imbalance;
+        let imbalance = self.current_imbalance();
+        if let Some(imbalance) = imbalance {
+            let previous = self.last_imbalance.replace(imbalance);
+            self.update_imbalance_band();
+            // A first reading has nothing to have shifted from
+            if let Some(previous) = previous {
+                self.cancel_on_regime_shift(previous);
+            }
         }
+        self.research_signal(self.order_book.last_update_time(), imbalance.is_some());
+        // The mid marks the inventory
+        self.update_drawdown();
+
+        // Cancels at our price levels shrink the queue ahead of us
+        self.update_queue_positions();
 
         // Check if any orders should be cancelled
         self.manage_existing_orders()?;
 
         // Create new orders if needed
-        self.place_stink_bids()?;
+        let attempts_before = self.attempt_count;
+        match self.config.quote_mode {
+            QuoteMode::StinkBids => self.place_stink_bids()?,
+            QuoteMode::JoinSpread { .. } => self.place_join_quotes(),
+        }
+        self.sync_own_orders();
+
+        self.emit_features(self.attempt_count > attempts_before);
 
         Ok(())
     }
 
+    /// Updates with a kline event from any subscribed interval
+    pub fn handle_kline(&mut self, event: &KlineEventData) {
+        self.klines.update(event);
+        self.update_volatility();
+    }
+
+    /// Updates the higher timeframe trend from a rolling window ticker
+    pub fn handle_window_ticker(&mut self, ticker: &WindowTickerData) {
+        self.window_price_change_pct = Some(ticker.price_change_percent);
+    }
+
+    /// Fractional k reduction from the rolling window trend, positive in an uptrend.
+    ///
+    /// Zero until a window ticker arrives or with the trend filter off.
+    pub fn trend_bias(&self) -> Decimal {
+        let max = self.config.max_trend_adjustment;
+        self.window_price_change_pct
+            .map(|pct| (pct * self.config.trend_sensitivity).clamp(-max, max))
+            .unwrap_or_default()
+    }
+
+    /// Refreshes the volatility used for pricing from the configured source
+    fn update_volatility(&mut self) {
+        let volatility = match &self.config.volatility_source {
+            VolatilitySource::Trades => None,
+            VolatilitySource::Atr { interval, period } => self
+                .klines
+                .atr(interval, *period)
+                .and_then(|atr| atr.checked_div(self.klines.latest(interval)?.close)),
+        }
+        .or(self.recent_trades.volatility);
+
+        if let Some(vol) = volatility {
+            // Apply dampening to reduce noise in volatility
+            self.last_volatility = vol * self.config.vol_dampening;
+        }
+    }
+
+    /// Emits a [`FeatureSnapshot`] on every depth update decision to the given channel
+    pub fn set_feature_sender(&mut self, feature_tx: UnboundedSender<FeatureSnapshot>) {
+        self.feature_tx = Some(feature_tx);
+    }
+
+    /// Diagnostic mode for signal research: sends every imbalance reading the maker acts on,
+    /// joined with the mid's return over `horizon`, to the given channel. Trading is
+    /// unaffected.
+    pub fn set_signal_research(
+        &mut self,
+        horizon: ForwardHorizon,
+        outcome_tx: UnboundedSender<SignalOutcome>,
+    ) {
+        self.signal_research = Some((ForwardReturnJoiner::new(horizon), outcome_tx));
+    }
+
+    /// Resolves the research signals whose horizon passed by `now`, recording the current
+    /// imbalance as a new one when `record` is set
+    fn research_signal(&mut self, now: DateTime<Utc>, record: bool) {
+        let (Some((joiner, outcome_tx)), Some(mid_price)) =
+            (&mut self.signal_research, self.order_book.mid_price)
+        else {
+            return;
+        };
+        let receiver_dropped = joiner
+            .on_mid(now, mid_price)
+            .into_iter()
+            .any(|outcome| outcome_tx.send(outcome).is_err());
+        if receiver_dropped {
+            warn!("Signal research receiver dropped, no longer joining forward returns");
+            self.signal_research = None;
+        } else if record {
+            joiner.record(now, self.last_imbalance.unwrap_or_default(), mid_price);
+        }
+    }
+
+    fn emit_features(&mut self, order_placed: bool) {
+        let Some(feature_tx) = &self.feature_tx else {
+            return;
+        };
+
+        // Depth metrics are only meaningful with both sides of the book populated
+        let two_sided = self.order_book.mid_price.is_some();
+        let snapshot = FeatureSnapshot {
+            timestamp: self.order_book.last_update_time(),
+            mid_price: self.order_book.mid_price,
+            microprice: self.order_book.microprice,
+            spread: self.order_book.spread,
+            relative_spread: self.order_book.relative_spread,
+            imbalance: self.order_book.imbalance,
+            depth_imbalance: two_sided
+                .then(|| self.order_book.imbalance_depth(FEATURE_DEPTH))
+                .flatten(),
+            weighted_imbalance: two_sided
+                .then(|| {
+                    self.order_book
+                        .weighted_relative_imbalance(FEATURE_DEPTH, self.config.imbalance_weighting)
+                })
+                .flatten(),
+            volatility: self.recent_trades.volatility,
+            trade_flow_imbalance: self.recent_trades.trade_flow_imbalance(),
+            order_placed,
+        };
+
+        if feature_tx.send(snapshot).is_err() {
+            warn!("Feature receiver dropped, no longer emitting feature snapshots");
+            self.feature_tx = None;
+        }
+    }
+
+    /// Reads the configured imbalance metric from the order book
+    ///
+    /// Depth based methods use at most as many levels as both sides of the book hold. The
+    /// reading goes through [`MarketMakerConfig::imbalance_normalization`].
+    pub fn current_imbalance(&self) -> Option<Decimal> {
+        let available = self.order_book.bids.len().min(self.order_book.asks.len());
+        let depth = self.config.imbalance_depth.min(available);
+        if depth < self.config.imbalance_depth {
+            debug!(
+                "Imbalance depth {} exceeds available levels, using {}",
+                self.config.imbalance_depth, depth
+            );
+        }
+
+        let imbalance = match self.config.imbalance_method {
+            ImbalanceMethod::TopOfBook => self.order_book.imbalance,
+            _ if depth == 0 => None,
+            ImbalanceMethod::Depth => self.order_book.imbalance_depth(depth),
+            ImbalanceMethod::Weighted => self
+                .order_book
+                .weighted_relative_imbalance(depth, self.config.imbalance_weighting),
+        };
+        imbalance.map(|imbalance| self.config.imbalance_normalization.apply(imbalance))
+    }
+
+    /// How aggressively stink bids are priced for the current imbalance
+    pub fn imbalance_band(&self) -> ImbalanceBand {
+        self.imbalance_band
+    }
+
+    /// Latest imbalance reading, balanced until there is one
+    fn imbalance_reading(&self) -> Decimal {
+        self.last_imbalance.unwrap_or_default()
+    }
+
+    fn update_imbalance_band(&mut self) {
+        let band = self.imbalance_band.next(
+            self.imbalance_reading(),
+            self.config.strong_imbalance_threshold,
+            self.config.moderate_imbalance_threshold,
+            self.config.imbalance_hysteresis,
+        );
+        if band != self.imbalance_band {
+            debug!(
+                "Imbalance {} moved stink bids from {:?} to {:?}",
+                self.config.for_display(self.imbalance_reading()),
+                self.imbalance_band,
+                band
+            );
+            self.imbalance_band = band;
+        }
+    }
+
+    /// Broad state of the market by [`MarketMakerConfig::regime_thresholds`]: volatility
+    /// first, then the rolling window trend, then the book quality
+    pub fn market_regime(&self) -> MarketRegime {
+        let thresholds = &self.config.regime_thresholds;
+        if self.last_volatility >= thresholds.high_volatility {
+            return MarketRegime::HighVolatility;
+        }
+        if let Some(change_pct) = self.window_price_change_pct
+            && change_pct.abs() >= thresholds.trend_pct
+        {
+            return if change_pct.is_sign_positive() {
+                MarketRegime::TrendingUp
+            } else {
+                MarketRegime::TrendingDown
+            };
+        }
+        if self
+            .order_book
+            .quality_score(&self.recent_trades)
+            .is_none_or(|quality| quality < thresholds.low_liquidity_quality)
+        {
+            return MarketRegime::LowLiquidity;
+        }
+        MarketRegime::Normal
+    }
+
+    /// Fair value stink bids are priced from, per the configured [`MidSource`]
+    pub fn fair_value(&self) -> Option<Decimal> {
+        match self.config.mid_source {
+            MidSource::Mid => self.order_book.mid_price,
+            MidSource::Microprice => self.order_book.microprice,
+            MidSource::WeightedMid => {
+                let available = self.order_book.bids.len().min(self.order_book.asks.len());
+                self.order_book
+                    .weighted_mid(self.config.mid_depth.min(available))
+            }
+            MidSource::LastTrade => self.recent_trades.last_price(),
+        }
+    }
+
     /// Updates with a new trade
     pub fn handle_trade(&mut self, trade: impl Into<Trade>) -> Result<()> {
         let trade = trade.into();
 
-        // Update our record of recent trades
-        self.recent_trades.update(trade);
+        // Update our record of recent trades, a redelivered trade was already acted on
+        if !self.recent_trades.update(trade) {
+            return Ok(());
+        }
+        self.trades_seen += 1;
+        self.realized_volatility.update(&trade);
+        self.volume_profile.update_from(&trade);
 
-        // Update volatility tracking
-        if let Some(vol) = self.recent_trades.volatility {
-            // Apply dampening to reduce noise in volatility
-            self.last_volatility = vol * self.config.vol_dampening;
+        if self.config.decay_book_on_trades {
+            self.order_book.apply_trade_decay(
+                trade.price,
+                trade.quantity,
+                trade.buyer_market_maker,
+            );
         }
 
+        // Update volatility tracking
+        self.update_volatility();
+
         // Check if any of our stink bids were filled
         self.check_order_fills(&trade)?;
 
+        if let Some((joiner, _)) = &mut self.signal_research {
+            joiner.on_trade();
+        }
+        self.research_signal(trade.trade_time(), false);
+
+        if let Some(detector) = &mut self.flow_spikes
+            && let Some(spike) = detector.update(trade)
+        {
+            let until = self.clock.now() + detector.config().window;
+            warn!(
+                "Trade flow spike, {} trades/s against {} with imbalance {}, pulling quotes until {}",
+                self.config.for_display(spike.trades_per_second),
+                self.config.for_display(spike.baseline_trades_per_second),
+                self.config.for_display(spike.imbalance),
+                until
+            );
+            self.flow_spike_until = Some(until);
+            self.cancel_all(CancelReason::Suppressed);
+        }
+
+        // Trades keep flowing even if the depth stream stalls
+        if self.is_book_stale(self.clock.now()) {
+            self.cancel_all(CancelReason::StaleBook);
+        }
+        self.sync_own_orders();
+
         Ok(())
     }
 
+    /// Tells the order book which of its liquidity is ours
+    fn sync_own_orders(&mut self) {
+        if self.config.exclude_own_orders {
+            self.order_book.set_own_bids(
+                self.active_orders
+                    .iter()
+                    .filter(|order| order.side == OrderSide::Buy)
+                    .map(|order| (order.price, order.size)),
+            );
+        }
+    }
+
+    /// Whether enough trades, book updates and time have gone by since the maker started for
+    /// its estimates to be trusted, see [`MarketMakerConfig::warmup_trades`]
+    pub fn is_warmed_up(&self) -> bool {
+        let elapsed = self.clock.now() - self.started_at;
+        self.trades_seen >= self.config.warmup_trades
+            && self.book_updates_seen >= self.config.warmup_book_updates
+            && elapsed
+                .to_std()
+                .is_ok_and(|elapsed| elapsed >= self.config.warmup_duration)
+    }
+
+    /// The exchange's time now, going by the skew of depth update event times from our clock
+    pub fn exchange_now(&self) -> DateTime<Utc> {
+        self.clock_skew.to_exchange_time(self.clock.now())
+    }
+
+    /// Estimated offset of our clock from the exchange's, see [`ClockSkew`]
+    pub fn clock_skew(&self) -> &ClockSkew {
+        &self.clock_skew
+    }
+
+    /// Whether the order book hasn't been updated within `max_book_staleness` of our time
+    /// `now`.
+    ///
+    /// Depth updates are stamped by the exchange, so `now` is converted to exchange time first
+    /// and the skew between the clocks doesn't count towards the age. A snapshot is stamped
+    /// with our time, which is compared with `now` as it is.
+    pub fn is_book_stale(&self, now: DateTime<Utc>) -> bool {
+        let now = if self.order_book.is_stamped_locally() {
+            now
+        } else {
+            self.clock_skew.to_exchange_time(now)
+        };
+        let age = now - self.order_book.last_update_time();
+        age.to_std()
+            .is_ok_and(|age| age > self.config.max_book_staleness)
+    }
+
+    /// Time based housekeeping that can't wait for the next book update: cancels everything on
+    /// a stale book, while halted or within a suppression window, otherwise orders past
+    /// [`MarketMakerConfig::order_ttl`]. Then saves the adaptive state if it changed, see
+    /// [`MarketMakerConfig::state_path`].
+    ///
+    /// Meant to run on a timer, so a frozen feed doesn't leave orders resting unmanaged.
+    pub fn maintenance(&mut self, now: DateTime<Utc>) {
+        if self.is_book_stale(now) {
+            self.cancel_all(CancelReason::StaleBook);
+        } else if self.is_halted() {
+            self.cancel_all(CancelReason::Halted);
+        } else if self.is_suppressed(now) {
+            self.cancel_all(CancelReason::Suppressed);
+        } else {
+            let mut expired_any = false;
+            for index in (0..self.active_orders.len()).rev() {
+                if !self.is_expired(&self.active_orders[index], now)
+                    || !self.try_order_action("expiry cancel")
+                {
+                    continue;
+                }
+                info!(
+                    "Cancelling stink bid at {}, resting since {}",
+                    self.config
+                        .precision
+                        .format_price(self.active_orders[index].price),
+                    self.active_orders[index].created_at
+                );
+                self.cancel_order(index, CancelReason::Expired, now);
+                expired_any = true;
+            }
+            // Went unfilled, same as any other cancel
+            if expired_any {
+                self.adjust_k_factor(&KOutcome::Cancelled);
+            }
+        }
+        self.sync_own_orders();
+        if let Err(e) = self.save_changed_adaptive_state() {
+            warn!("{:#}", e);
+        }
+    }
+
+    /// Whether `order` has rested past its time to live as of `now`
+    fn is_expired(&self, order: &Order, now: DateTime<Utc>) -> bool {
+        !self.config.order_ttl.is_zero()
+            && (now - order.created_at)
+                .to_std()
+                .is_ok_and(|age| age >= self.config.order_ttl)
+    }
+
+    /// Moves the active order at `index` to the cancelled orders
+    fn cancel_order(&mut self, index: usize, reason: CancelReason, now: DateTime<Utc>) {
+        let mut order = self.active_orders.remove(index);
+        order.status = OrderStatus::Cancelled;
+        order.cancel_reason = Some(reason);
+        self.audit_log
+            .record(&order, OrderEvent::Cancelled(reason), now);
+        self.cancelled_orders.push(order);
+    }
+
+    /// Cancels every active order for the same reason
+    ///
+    /// This is a single cancel-all request, so it only takes one action from the throttle.
+    fn cancel_all(&mut self, reason: CancelReason) {
+        if self.active_orders.is_empty() || !self.try_order_action("cancel all") {
+            return;
+        }
+        self.cancel_all_now(reason);
+    }
+
+    /// Cancels every active order before shutting down, ignoring the throttle since nothing
+    /// may be left resting once we stop watching the book.
+    ///
+    /// Fills already booked stay as inventory, there's no execution client to sell it back.
+    pub fn flatten(&mut self) {
+        self.cancel_all_now(CancelReason::Shutdown);
+        if !self.net_inventory.is_zero() {
+            warn!(
+                "Shutting down holding {} of inventory",
+                self.config.precision.format_size(self.net_inventory)
+            );
+        }
+    }
+
+    /// Drops active orders the exchange no longer reports as open, given the ids from a fresh
+    /// open orders query after a reconnect.
+    ///
+    /// They're moved to the cancelled orders as [`CancelReason::Unconfirmed`], since a fill
+    /// during the gap can only be told apart by the execution reports.
+    pub fn reconcile_orders(&mut self, exchange_open: &[String]) {
+        let now = self.clock.now();
+        let (open, missing): (Vec<_>, Vec<_>) = std::mem::take(&mut self.active_orders)
+            .into_iter()
+            .partition(|order| exchange_open.contains(&order.id));
+        self.active_orders = open;
+        for mut order in missing {
+            warn!(
+                "Order {} at {} is no longer open on the exchange",
+                order.id,
+                self.config.precision.format_price(order.price)
+            );
+            order.status = OrderStatus::Cancelled;
+            order.cancel_reason = Some(CancelReason::Unconfirmed);
+            self.audit_log.record(
+                &order,
+                OrderEvent::Cancelled(CancelReason::Unconfirmed),
+                now,
+            );
+            self.cancelled_orders.push(order);
+        }
+    }
+
+    /// Applies the [`ReconnectPolicy`] to active orders once the market data stream is back,
+    /// for when there's no open orders state to [`Self::reconcile_orders`] against
+    pub fn handle_reconnect(&mut self) {
+        match self.config.reconnect_policy {
+            ReconnectPolicy::Trust => {}
+            ReconnectPolicy::AssumeUnknown => self.reconcile_orders(&[]),
+        }
+    }
+
+    fn cancel_all_now(&mut self, reason: CancelReason) {
+        if self.active_orders.is_empty() {
+            return;
+        }
+        info!(
+            "Cancelling all {} stink bids: {:?}",
+            self.active_orders.len(),
+            reason
+        );
+        let now = self.clock.now();
+        for mut order in self.active_orders.drain(..) {
+            order.status = OrderStatus::Cancelled;
+            order.cancel_reason = Some(reason);
+            self.audit_log
+                .record(&order, OrderEvent::Cancelled(reason), now);
+            self.cancelled_orders.push(order);
+        }
+    }
+
+    /// Cancels orders placed under a different imbalance regime when the imbalance jumps
+    /// by at least `regime_shift_threshold` from `previous` in a single update
+    fn cancel_on_regime_shift(&mut self, previous: Decimal) {
+        let Some(threshold) = self.config.regime_shift_threshold else {
+            return;
+        };
+        let current = self.imbalance_reading();
+        if (current - previous).abs() < threshold {
+            return;
+        }
+
+        let (shifted, kept): (Vec<_>, Vec<_>) = self
+            .active_orders
+            .drain(..)
+            .partition(|order| (current - order.imbalance_at_placement).abs() >= threshold);
+        self.active_orders = kept;
+        if shifted.is_empty() {
+            return;
+        }
+
+        info!(
+            "Imbalance shifted from {} to {}, cancelling {} stink bids",
+            self.config.for_display(previous),
+            self.config.for_display(current),
+            shifted.len()
+        );
+        let now = self.clock.now();
+        for mut order in shifted {
+            // Cancels the throttle won't allow yet stay active and are retried next update
+            if !self.try_order_action("regime shift cancel") {
+                self.active_orders.push(order);
+                continue;
+            }
+            order.status = OrderStatus::Cancelled;
+            order.cancel_reason = Some(CancelReason::RegimeShift);
+            self.audit_log.record(
+                &order,
+                OrderEvent::Cancelled(CancelReason::RegimeShift),
+                now,
+            );
+            self.cancelled_orders.push(order);
+        }
+    }
+
+    /// Takes one order action from the rate limit budget, counting a throttle hit if there
+    /// is none left
+    fn try_order_action(&mut self, action: &str) -> bool {
+        self.try_order_actions(1, action)
+    }
+
+    /// [`Self::try_order_action`] for `count` actions that only make sense together
+    fn try_order_actions(&mut self, count: u32, action: &str) -> bool {
+        if self.throttle.try_acquire_many(self.clock.now(), count) {
+            return true;
+        }
+        self.throttle_hits += 1;
+        debug!("Order rate limit reached, deferring {}", action);
+        false
+    }
+
+    /// Active orders on `side`, best price first: highest bid or lowest ask. Ties keep
+    /// placement order.
+    pub fn active_orders_by_price(&self, side: OrderSide) -> Vec<&Order> {
+        let mut orders: Vec<_> = self
+            .active_orders
+            .iter()
+            .filter(|order| order.side == side)
+            .collect();
+        match side {
+            OrderSide::Buy => orders.sort_by_key(|order| Reverse(order.price)),
+            OrderSide::Sell => orders.sort_by_key(|order| order.price),
+        }
+        orders
+    }
+
+    /// Active order on `side` furthest from the touch, the last of
+    /// [`Self::active_orders_by_price`]
+    pub fn worst_order(&self, side: OrderSide) -> Option<&Order> {
+        self.active_orders_by_price(side).last().copied()
+    }
+
+    /// Size resting in active orders on `side`, what's at risk of filling
+    pub fn total_active_size(&self, side: OrderSide) -> Decimal {
+        self.active_orders
+            .iter()
+            .filter(|order| order.side == side)
+            .map(|order| order.size)
+            .sum()
+    }
+
+    /// Position in the base asset, positive when long
+    pub fn net_inventory(&self) -> Decimal {
+        self.net_inventory
+    }
+
+    /// Overrides the tracked position, e.g. with the balance reported by the exchange
+    pub fn set_net_inventory(&mut self, net_inventory: Decimal) {
+        self.net_inventory = net_inventory;
+    }
+
+    /// Cost of closing the whole position with a market order right now: the slippage of the
+    /// opposite side's VWAP from the mid plus the taker fee, in quote asset.
+    ///
+    /// Weighed against the risk of holding, this tells whether to flatten aggressively or
+    /// join the book and wait. Zero when flat, `None` without a mid or with too little depth to
+    /// absorb the position.
+    pub fn flatten_cost(&self) -> Option<Decimal> {
+        if self.net_inventory.is_zero() {
+            return Some(Decimal::ZERO);
+        }
+        let mid = self.order_book.mid_price?;
+        let size = self.net_inventory.abs();
+        let (vwap, slippage) = if self.net_inventory.is_sign_positive() {
+            // Long: sell into the bids
+            let vwap = self.order_book.vwap_for_size(OrderSide::Sell, size)?;
+            (vwap, mid - vwap)
+        } else {
+            let vwap = self.order_book.vwap_for_size(OrderSide::Buy, size)?;
+            (vwap, vwap - mid)
+        };
+        Some(slippage * size + self.config.fee_model.taker_fee(vwap, size))
+    }
+
+    /// Order actions skipped or deferred because of the rate limit
+    pub fn throttle_hits(&self) -> usize {
+        self.throttle_hits
+    }
+
+    /// Inferred fills held back because they'd have taken more than the trade that reached
+    /// them printed. A backtest with many of these assumes liquidity that wasn't there.
+    pub fn fill_divergences(&self) -> usize {
+        self.fill_divergences
+    }
+
     /// Checks if any orders were filled by recent trades
+    ///
+    /// A trade at an order's price only consumes the queue ahead of it, the order is
+    /// filled once that queue has been exhausted. A trade through the price means the
+    /// whole level was taken out, so the queue ahead is cleared. The trade's volume
+    /// goes to the best priced orders first, and fills no more than it printed. An order it
+    /// can't fill whole fills partly with [`MarketMakerConfig::partial_fills`], otherwise not
+    /// at all, see [`Self::fill_divergences`]. Either way the volume that reached it is spent,
+    /// worse priced orders never fill ahead of it.
     fn check_order_fills(&mut self, trade: &Trade) -> Result<()> {
-        // Only interested in trades where buyers are market makers (someone sold into a bid)
-        if trade.buyer_market_maker {
-            let mut filled_orders = Vec::new();
-            let mut should_adjust_k_factor = false;
+        // Live fills come from execution reports, the public tape only says we might have
+        if self.config.fill_source != FillSource::TradeInference {
+            return Ok(());
+        }
+        // Someone selling into the bids can fill our bids, someone buying from the asks our asks
+        let filled_side = if trade.buyer_market_maker {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        };
+        // Orders the trade reached, with the volume that got past the queue ahead of each
+        let mut reached_orders = Vec::new();
 
-            // Check each active order to see if it was filled
-            for (idx, order) in self.active_orders.iter().enumerate() {
-                if order.status == OrderStatus::Placed && trade.price <= order.price {
-                    filled_orders.push(idx);
+        // Check each active order to see if it was filled
+        for (idx, order) in self.active_orders.iter_mut().enumerate() {
+            // How far the trade printed through the order's price, negative short of it
+            let through = match order.side {
+                OrderSide::Buy => order.price - trade.price,
+                OrderSide::Sell => trade.price - order.price,
+            };
+            if order.status != OrderStatus::Placed
+                || order.side != filled_side
+                || through < Decimal::ZERO
+            {
+                continue;
+            }
 
-                    // Calculate profit percentage
-                    let profit_pct = (order.reference_mid - trade.price) / trade.price * dec!(100);
+            let reached = if through > Decimal::ZERO {
+                order.queue_ahead = Decimal::ZERO;
+                trade.quantity
+            } else {
+                let reached = (trade.quantity - order.queue_ahead).max(Decimal::ZERO);
+                order.queue_ahead = (order.queue_ahead - trade.quantity).max(Decimal::ZERO);
+                reached
+            };
 
-                    info!(
-                        "🎯 STINK BID FILLED! Price: {}, Size: {}, Profit: {}%, K-factor: {}",
-                        trade.price, order.size, profit_pct, order.k_factor_used
-                    );
+            if order.queue_ahead > Decimal::ZERO {
+                debug!(
+                    "Trade at order price {}, queue ahead now: {}",
+                    order.price, order.queue_ahead
+                );
+                continue;
+            }
 
-                    // Positive reinforcement - adjust k-factor for success
-                    self.successful_fill_count += 1;
+            reached_orders.push((idx, reached));
+        }
 
-                    // Make k-factor slightly more aggressive for next time
-                    should_adjust_k_factor = true;
-                }
+        // The trade's volume goes to the best priced orders first, and can't fill more than
+        // actually traded. Anything past that is an execution the simulation made up.
+        reached_orders.sort_by(|&(a, _), &(b, _)| {
+            let (a, b) = (self.active_orders[a].price, self.active_orders[b].price);
+            match filled_side {
+                OrderSide::Buy => b.cmp(&a),
+                OrderSide::Sell => a.cmp(&b),
             }
-            // Now apply the changes after the iteration is complete
-            if should_adjust_k_factor {
-                // Positive reinforcement - adjust k-factor for success
-                self.successful_fill_count += 1;
-                // Make k-factor slightly more aggressive for next time
-                self.adjust_k_factor(true);
+        });
+        let mut volume_left = trade.quantity;
+        // Orders to fill with the size each gets
+        let mut fills = Vec::new();
+        for (idx, reached) in reached_orders {
+            let order = &self.active_orders[idx];
+            let available = reached.min(volume_left);
+            if order.size <= available {
+                volume_left -= order.size;
+                fills.push((idx, order.size));
+                continue;
             }
-            // Remove filled orders from active orders and add to filled orders
-            for idx in filled_orders.iter().rev() {
-                let mut order = self.active_orders.remove(*idx);
-                order.status = OrderStatus::Filled;
-                order.filled_at = Some(Utc::now());
-                self.filled_orders.push(order);
+            if self.config.partial_fills && available > Decimal::ZERO {
+                fills.push((idx, available));
+            } else {
+                warn!(
+                    "Simulation fidelity: inferred fill of {} at {} exceeds the {} traded at or through it, not filling",
+                    order.size,
+                    self.config.precision.format_price(order.price),
+                    available
+                );
+                self.fill_divergences += 1;
             }
+            // The order is ahead of anything deeper whether it fills or not, so the volume that
+            // reached it doesn't carry on down to worse priced orders
+            volume_left -= available;
+        }
+        fills.sort_unstable_by_key(|&(idx, _)| idx);
+
+        // Resting orders fill at their own price
+        let mut filled_whole = false;
+        for &(idx, size) in fills.iter().rev() {
+            let price = self.active_orders[idx].price;
+            if size < self.active_orders[idx].size {
+                self.book_partial_fill(idx, price, size, FillSource::TradeInference);
+            } else {
+                self.book_fill(idx, price, FillSource::TradeInference);
+                filled_whole = true;
+            }
+        }
+        // Now apply the changes after the iteration is complete, k only learns from orders
+        // that filled whole
+        if filled_whole {
+            self.on_fills();
+        } else if !fills.is_empty() {
+            self.update_drawdown();
         }
 
         Ok(())
     }
 
+    /// Books a fill of the active order `order_id` reported by the execution client.
+    ///
+    /// Returns false, leaving everything as it was, when no such order is active.
+    pub fn handle_execution_fill(&mut self, order_id: &str, fill_price: Decimal) -> bool {
+        let Some(idx) = self
+            .active_orders
+            .iter()
+            .position(|order| order.id == order_id)
+        else {
+            warn!("Execution report for unknown order {}", order_id);
+            return false;
+        };
+        self.book_fill(idx, fill_price, FillSource::ExecutionReport);
+        self.on_fills();
+        true
+    }
+
+    /// Moves the active order at `idx` to the filled orders, booking its PnL and inventory
+    fn book_fill(&mut self, idx: usize, fill_price: Decimal, fill_source: FillSource) {
+        let mut order = self.active_orders.remove(idx);
+        order.status = OrderStatus::Filled;
+        self.book_execution(&mut order, fill_price, fill_source);
+
+        self.successful_fill_count += 1;
+        self.intensity
+            .record_fill((order.reference_mid - fill_price).abs());
+        self.audit_log
+            .record(&order, OrderEvent::Filled, order.filled_at.unwrap());
+        self.filled_orders.push(order);
+    }
+
+    /// Fills `size` of the active order at `idx`. The filled slice goes to the filled orders
+    /// under a fill id of its own, the rest of the order stays active in the same slot, at the
+    /// front of its price level's queue. Only the fill that completes the order counts towards
+    /// the fill count and adapts k.
+    fn book_partial_fill(
+        &mut self,
+        idx: usize,
+        fill_price: Decimal,
+        size: Decimal,
+        fill_source: FillSource,
+    ) {
+        let fill_id = self.next_order_id(&format!("{}-fill", self.active_orders[idx].id));
+        let order = &mut self.active_orders[idx];
+        let mut slice = order.clone();
+        order.size -= size;
+        order.queue_ahead = Decimal::ZERO;
+        slice.size = size;
+        slice.status = OrderStatus::PartiallyFilled;
+        self.book_execution(&mut slice, fill_price, fill_source);
+
+        debug!(
+            "Partially filled {} as {}, {} left resting",
+            slice.id,
+            fill_id,
+            self.config
+                .precision
+                .format_size(self.active_orders[idx].size)
+        );
+        // Audited under the order it came out of
+        self.audit_log.record(
+            &slice,
+            OrderEvent::PartiallyFilled,
+            slice.filled_at.unwrap(),
+        );
+        slice.id = fill_id;
+        self.filled_orders.push(slice);
+    }
+
+    /// Books the PnL, fees and inventory of `order` filling at `fill_price`
+    fn book_execution(&mut self, order: &mut Order, fill_price: Decimal, fill_source: FillSource) {
+        let fill_quality = self.fill_quality();
+        order.filled_at = Some(self.clock.now());
+        order.fill_quality = Some(fill_quality);
+        order.fill_source = Some(fill_source);
+
+        // Buying below the mid or selling above it is the edge
+        let edge = match order.side {
+            OrderSide::Buy => order.reference_mid - fill_price,
+            OrderSide::Sell => fill_price - order.reference_mid,
+        };
+        // Calculate profit percentage
+        let profit_pct = edge / fill_price * dec!(100);
+
+        // Our orders rest on the book, so they're always maker fills
+        let gross = edge * order.size;
+        let fee = self.config.fee_model.maker_fee(fill_price, order.size);
+        self.gross_pnl += gross;
+        self.fees_paid += fee;
+
+        let precision = &self.config.precision;
+        info!(
+            "[FILL] {} {}! Price: {}, Size: {}, Profit: {}%, Net PnL: {}, K-factor: {}, Quality: {:?}, Source: {:?}",
+            match order.side {
+                OrderSide::Buy => "BID",
+                OrderSide::Sell => "ASK",
+            },
+            match order.status {
+                OrderStatus::PartiallyFilled => "PARTIALLY FILLED",
+                _ => "FILLED",
+            },
+            precision.format_price(fill_price),
+            precision.format_size(order.size),
+            self.config.for_display(profit_pct),
+            self.config.for_display(gross - fee),
+            self.config.for_display(order.k_factor_used),
+            fill_quality,
+            fill_source
+        );
+
+        self.net_inventory += order.signed_size();
+    }
+
+    /// Adapts the k-factor once per batch of fills, from the last order filled whole
+    fn on_fills(&mut self) {
+        let Some(order) = self
+            .filled_orders
+            .iter()
+            .rev()
+            .find(|order| order.status == OrderStatus::Filled)
+        else {
+            return;
+        };
+        let outcome = KOutcome::Filled {
+            distance_pct: (order.reference_mid - order.price) / order.reference_mid * dec!(100),
+            adverse: order.fill_quality == Some(FillQuality::Adverse),
+            time_to_fill: order.filled_at.unwrap_or(order.created_at) - order.created_at,
+        };
+        // Positive reinforcement, make k-factor slightly more aggressive for next time unless
+        // we were run over
+        self.adjust_k_factor(&outcome);
+        self.update_drawdown();
+    }
+
+    /// Number of filled orders reported by `fill_source`
+    pub fn fill_count_by_source(&self, fill_source: FillSource) -> usize {
+        self.filled_orders
+            .iter()
+            .filter(|order| order.fill_source == Some(fill_source))
+            .count()
+    }
+
+    /// Caps the queue ahead of each order at the size currently resting at its price level
+    fn update_queue_positions(&mut self) {
+        for order in self.active_orders.iter_mut() {
+            let levels = match order.side {
+                OrderSide::Buy => &self.order_book.bids,
+                OrderSide::Sell => &self.order_book.asks,
+            };
+            let level_size = levels.get(&order.price).copied().unwrap_or_default();
+            order.queue_ahead = order.queue_ahead.min(level_size);
+        }
+    }
+
     /// Manages existing orders (cancel if needed)
     fn manage_existing_orders(&mut self) -> Result<()> {
-        let mut orders_to_cancel = Vec::new();
-        let mut should_adjust_k_factor = false;
-
-        if let Some((best_bid, _)) = self.order_book.best_bid {
-            // Review each active order
-            for (idx, order) in self.active_orders.iter().enumerate() {
-                let distance_to_best = best_bid - order.price;
-                let percent_distance = distance_to_best / best_bid;
-
-                // Cancel if:
-                // 1. Order is too far below current best bid (market moved up)
-                // 2. Order is too close to best bid (risk of immediate fill)
-                let should_cancel =
-                    // Too far below (market moved up significantly)
-                    (percent_distance > dec!(0.01) * order.k_factor_used * dec!(5)) ||
-                    // Too close to best bid (risky)
-                    (percent_distance < self.config.min_distance_pct * dec!(0.5));
-
-                if should_cancel {
-                    orders_to_cancel.push(idx);
-                    info!(
-                        "Cancelling stink bid - Price: {}, Best bid: {}, Distance: {}%",
-                        order.price,
-                        best_bid,
-                        (percent_distance * dec!(100))
-                    );
+        if self.is_book_stale(self.clock.now()) {
+            self.cancel_all(CancelReason::StaleBook);
+            return Ok(());
+        }
+        if self.is_halted() {
+            self.cancel_all(CancelReason::Halted);
+            return Ok(());
+        }
+        if self.is_suppressed(self.clock.now()) {
+            self.cancel_all(CancelReason::Suppressed);
+            return Ok(());
+        }
 
-                    // Mark for adjustment instead of doing it here
-                    should_adjust_k_factor = true;
-                }
+        let orders_to_cancel = match self.config.quote_mode {
+            QuoteMode::StinkBids => self.cancel_intents(),
+            QuoteMode::JoinSpread { .. } => self.join_cancel_intents(),
+        };
+
+        // Cancel orders that no longer make sense, anything throttled is reviewed again on
+        // the next update
+        let mut cancelled_any = false;
+        for intent in orders_to_cancel.iter().rev() {
+            if !self.try_order_action("cancel") {
+                continue;
             }
+            info!(
+                "Cancelling stink bid - Price: {}, Distance: {}%, Reason: {:?}",
+                self.config.precision.format_price(intent.price),
+                intent
+                    .distance_pct
+                    .map(|distance| self.config.for_display(distance))
+                    .unwrap_or_default(),
+                intent.reason
+            );
+            cancelled_any = true;
+            self.cancel_order(intent.index, intent.reason, self.clock.now());
         }
 
-        // Adjust k-factor if needed
-        if should_adjust_k_factor {
-            // Consider this a failed attempt and adjust k-factor
-            self.adjust_k_factor(false);
+        // Consider this a failed attempt and adjust k-factor
+        if cancelled_any {
+            self.adjust_k_factor(&KOutcome::Cancelled);
         }
 
-        // Cancel orders that no longer make sense
-        for idx in orders_to_cancel.iter().rev() {
-            let mut order = self.active_orders.remove(*idx);
-            order.status = OrderStatus::Cancelled;
-            self.cancelled_orders.push(order);
+        Ok(())
+    }
+
+    /// Active orders the best bid has moved too far from or too close to, in placement order
+    fn cancel_intents(&self) -> Vec<CancelIntent> {
+        let Some((best_bid, _)) = self.order_book.best_bid else {
+            return Vec::new();
+        };
+
+        // Review each active order
+        let now = self.clock.now();
+        let sigma_reference = self
+            .fair_value()
+            .filter(|_| !self.last_volatility.is_zero())
+            .map(|mid| (mid, self.clamp_volatility(self.last_volatility) * mid));
+        let mut intents = Vec::new();
+        for (index, order) in self.active_orders.iter().enumerate() {
+            let distance_to_best = best_bid - order.price;
+            // Distance below the best bid as a percentage, same units as the thresholds
+            let percent_distance = distance_to_best / best_bid * dec!(100);
+
+            // Cancel if:
+            // 1. Order has rested past its time to live
+            // 2. Order is too far below current best bid (market moved up)
+            // 3. Order is too close to best bid or, in volatility units, fair value (risk of
+            //    immediate fill)
+            // 4. Sell pressure built up behind it (likely adverse fill)
+            let cancel_reason =
+                // Rested too long without filling
+                if self.is_expired(order, now) {
+                    Some(CancelReason::Expired)
+                // Too far below (market moved up significantly)
+                } else if percent_distance > self.config.cancel_far_multiplier * order.k_factor_used {
+                    Some(CancelReason::TooFar)
+                // Too close to best bid (risky)
+                } else if percent_distance
+                    < self.config.cancel_near_multiplier * self.config.min_distance_pct
+                {
+                    Some(CancelReason::TooClose)
+                // Too close to the fair value for the current volatility
+                } else if let Some(min_sigmas) = self.config.min_distance_sigmas
+                    && let Some((mid, price_volatility)) = sigma_reference
+                    && distance_in_sigmas(order.price, mid, price_volatility)
+                        .is_some_and(|sigmas| sigmas < min_sigmas)
+                {
+                    Some(CancelReason::TooClose)
+                // Imbalance deteriorated since placement
+                } else if self.config.adverse_imbalance_delta.is_some_and(|delta| {
+                    order.imbalance_at_placement - self.imbalance_reading() >= delta
+                }) {
+                    Some(CancelReason::AdverseImbalance)
+                } else {
+                    None
+                };
+
+            if let Some(reason) = cancel_reason {
+                intents.push(CancelIntent {
+                    index,
+                    order_id: order.id.clone(),
+                    price: order.price,
+                    reason,
+                    distance_pct: Some(percent_distance),
+                });
+            }
         }
+        intents
+    }
 
-        Ok(())
+    /// Highest price a stink bid may rest at below `best_bid`.
+    ///
+    /// `min_distance_pct` percent of the best bid below it, and never less than a tick, so the
+    /// bid can't join or improve the best bid.
+    fn max_stink_bid_price(&self, best_bid: Decimal) -> Decimal {
+        let min_price_distance = (best_bid * self.config.min_distance_pct / dec!(100))
+            .max(self.config.precision.tick_size);
+        best_bid - min_price_distance
     }
 
     /// Places stink bids based on current market conditions
     fn place_stink_bids(&mut self) -> Result<()> {
-        // Only create new orders if we haven't reached max active orders
-        if self.active_orders.len() >= self.config.max_active_orders {
+        // Only create new orders if we haven't reached max active orders, or one of them
+        // may give up its slot
+        let at_capacity = self.active_orders.len() >= self.config.max_active_orders;
+        if self.is_halted()
+            || self.is_suppressed(self.clock.now())
+            || (at_capacity && self.config.replace_edge_threshold_bps.is_none())
+        {
+            return Ok(());
+        }
+
+        let Some(intent) = self.stink_bid_intent(self.current_k) else {
+            return Ok(());
+        };
+        if at_capacity {
+            let Some(replaced) = self.replacement_for(&intent) else {
+                return Ok(());
+            };
+            // The cancel alone would just give the slot up, so both must fit the budget
+            if !self.try_order_actions(2, "replacement") {
+                return Ok(());
+            }
+            info!(
+                "Replacing stink bid at {} with one at {}",
+                self.config.precision.format_price(replaced.price),
+                self.config.precision.format_price(intent.price)
+            );
+            // A portfolio decision rather than a failed attempt, so k stays as it is
+            self.cancel_order(replaced.index, CancelReason::Replaced, self.clock.now());
+        } else if !self.try_order_action("placement") {
             return Ok(());
         }
+        // Create the new stink bid order
+        self.place_order(&intent)?;
+        self.attempt_count += 1;
+
+        info!(
+            "Placing stink bid: Price={}, Mid={}, Discount={}%, Imbalance={}, K={}",
+            self.config.precision.format_price(intent.price),
+            self.config.precision.format_price(intent.mid_price),
+            self.config.for_display(intent.discount_pct),
+            self.config.for_display(intent.imbalance),
+            self.config.for_display(intent.k_used)
+        );
+
+        Ok(())
+    }
+
+    /// Expected edge of a bid at `price`, in basis points of `mid_price`.
+    ///
+    /// The discount net of round trip maker fees, weighted by the relative fill rate `e^(−κδ)`
+    /// at its distance once [`Self::arrival_intensity`] has a decaying fit. Until then deeper
+    /// bids simply score higher.
+    fn expected_edge_bps(&self, price: Decimal, mid_price: Decimal) -> Decimal {
+        let distance = mid_price - price;
+        let net_edge_bps =
+            distance / mid_price * dec!(10_000) - self.config.fee_model.round_trip_maker_bps();
+        let fill_weight = match self.arrival_intensity() {
+            Some(estimate) if estimate.kappa > Decimal::ZERO => (-estimate.kappa * distance)
+                .checked_exp()
+                .unwrap_or(Decimal::ZERO),
+            _ => Decimal::ONE,
+        };
+        net_edge_bps * fill_weight
+    }
+
+    /// The active order `intent` should take the slot of when every slot is taken, the one
+    /// with the lowest expected edge if the intent's beats it by more than
+    /// [`MarketMakerConfig::replace_edge_threshold_bps`]
+    fn replacement_for(&self, intent: &StinkBidIntent) -> Option<CancelIntent> {
+        let threshold = self.config.replace_edge_threshold_bps?;
+        let (index, worst, worst_edge) = self
+            .active_orders
+            .iter()
+            .enumerate()
+            .map(|(index, order)| {
+                let edge = self.expected_edge_bps(order.price, intent.mid_price);
+                (index, order, edge)
+            })
+            .min_by_key(|(_, _, edge)| *edge)?;
+        let new_edge = self.expected_edge_bps(intent.price, intent.mid_price);
+        if new_edge - worst_edge <= threshold {
+            return None;
+        }
+        Some(CancelIntent {
+            index,
+            order_id: worst.id.clone(),
+            price: worst.price,
+            reason: CancelReason::Replaced,
+            distance_pct: Some((intent.best_bid - worst.price) / intent.best_bid * dec!(100)),
+        })
+    }
+
+    /// The stink bid current market conditions call for with the k-factor at `current_k`,
+    /// `None` when none should be placed
+    fn stink_bid_intent(&self, current_k: Decimal) -> Option<StinkBidIntent> {
+        if !self.is_warmed_up() {
+            debug!(
+                "Warming up ({} trades, {} book updates), not placing stink bids",
+                self.trades_seen, self.book_updates_seen
+            );
+            return None;
+        }
+
+        if let Some(min_quality) = self.config.min_book_quality {
+            let quality = self.order_book.quality_score(&self.recent_trades);
+            if quality.is_none_or(|quality| quality < min_quality) {
+                debug!(
+                    "Book quality {:?} below {}, not placing stink bids",
+                    quality, min_quality
+                );
+                return None;
+            }
+        }
+
+        // Never quote off a frozen feed
+        if self.is_book_stale(self.clock.now()) {
+            warn!(
+                "Order book stale since {}, not placing stink bids",
+                self.order_book.last_update_time()
+            );
+            return None;
+        }
 
         // Check if we have all the necessary data
-        if let (Some(mid_price), volatility, Some((best_bid, _)), Some((best_ask, _))) = (
-            self.order_book.mid_price,
+        let (Some(mid_price), volatility, Some((best_bid, _)), Some((best_ask, _))) = (
+            self.fair_value(),
             self.last_volatility,
             self.order_book.best_bid,
             self.order_book.best_ask,
-        ) {
-            // Check if volatility is too low to make meaningful bids
-            if volatility < dec!(0.00000001) {
-                if self.debug_mode {
-                    info!(
-                        "Volatility too low for meaningful stink bids: {}",
-                        volatility
-                    );
-                }
-                return Ok(());
+        ) else {
+            if self.debug_mode {
+                // Log why we couldn't place an order
+                info!(
+                    "Missing data for stink bid: fair_value={:?}, volatility={:?}, best_bid={:?}, best_ask={:?}",
+                    self.fair_value(),
+                    self.last_volatility,
+                    self.order_book.best_bid,
+                    self.order_book.best_ask
+                );
             }
+            return None;
+        };
 
-            // Adjust k-factor based on imbalance
-            let imbalance_adjusted_k =
-                if self.last_imbalance < self.config.strong_imbalance_threshold {
-                    // Very strong sell pressure - be aggressive
-                    self.current_k * dec!(0.5)
-                } else if self.last_imbalance < self.config.moderate_imbalance_threshold {
-                    // Moderate sell pressure - use normal k
-                    self.current_k
-                } else if self.last_imbalance < dec!(0.3) {
-                    // Balanced or light buy pressure - be more cautious
-                    self.current_k * dec!(1.5)
-                } else {
-                    // Strong buy pressure - be very cautious
-                    self.current_k * dec!(2.5)
-                };
+        // Without any trade history there's nothing to size the discount from
+        if volatility.is_zero() {
+            if self.debug_mode {
+                info!("No volatility estimate yet for stink bids");
+            }
+            return None;
+        }
+        let volatility = self.clamp_volatility(volatility);
 
-            // Convert volatility from return space to price space
-            let price_volatility = volatility * mid_price;
+        // Adjust k-factor based on imbalance: aggressive under sell pressure, cautious under
+        // buy pressure
+        let imbalance_adjusted_k = current_k * self.imbalance_band.k_multiplier();
 
-            // Absolute minimal distance from best bid (safety)
-            let min_price_distance = best_bid * self.config.min_distance_pct;
+        // Buy dips more eagerly in an uptrend, hold back in a downtrend
+        let imbalance_adjusted_k = imbalance_adjusted_k * (Decimal::ONE - self.trend_bias());
 
-            // Calculate stink bid price: mid_price - (k * volatility)
-            // The larger the k, the deeper the discount
-            let raw_stink_bid_price = mid_price - (imbalance_adjusted_k * price_volatility);
+        // Convert volatility from return space to price space
+        let price_volatility = volatility * mid_price;
 
-            // Ensure minimum distance from best bid
-            let stink_bid_price = if best_bid - raw_stink_bid_price < min_price_distance {
-                best_bid - min_price_distance
-            } else {
-                raw_stink_bid_price
-            };
+        // Highest price a bid may rest at, strictly below the best bid
+        let max_bid_price = self.max_stink_bid_price(best_bid);
+
+        // Calculate stink bid price: mid_price - (k * volatility)
+        // The larger the k, the deeper the discount
+        // Lean against the position: bid lower when long, higher when short
+        let skew = inventory_skew(
+            self.config.inventory_skew_factor,
+            self.net_inventory,
+            self.config.max_inventory,
+            price_volatility,
+        );
+        let raw_stink_bid_price = mid_price - (imbalance_adjusted_k * price_volatility) - skew;
 
-            // Calculate the discount percentage
-            let discount_pct = (mid_price - stink_bid_price) / mid_price * dec!(100);
-
-            // Only place if discount is reasonable (not too small or too large)
-            if discount_pct >= dec!(0.01) && discount_pct <= dec!(5.0) {
-                // Create the new stink bid order
-                self.place_order(
-                    stink_bid_price,
-                    self.config.order_size,
-                    mid_price,
-                    best_bid,
-                    imbalance_adjusted_k,
-                )?;
-                self.attempt_count += 1;
+        // Ensure minimum distance from best bid. A small k with a strong imbalance can put
+        // the raw price at or above the best bid, which this pulls back below it too.
+        let stink_bid_price = raw_stink_bid_price.min(max_bid_price);
+
+        // Keep to the volume supported zone, never moving closer than the minimum distance
+        let stink_bid_price = self.constrain_to_value_area(stink_bid_price, max_bid_price)?;
+        let stink_bid_price = self.config.precision.floor_to_tick(stink_bid_price);
+        if stink_bid_price <= Decimal::ZERO {
+            warn!(
+                "Not placing stink bid - Price={} is not positive",
+                stink_bid_price
+            );
+            return None;
+        }
 
+        // A bid at or through the ask would execute immediately as a taker
+        if is_marketable_bid(stink_bid_price, best_ask) {
+            warn!(
+                "Not placing stink bid - Price={} would cross best ask {}",
+                self.config.precision.format_price(stink_bid_price),
+                self.config.precision.format_price(best_ask)
+            );
+            return None;
+        }
+
+        // Calculate the discount percentage
+        let discount_pct = (mid_price - stink_bid_price) / mid_price * dec!(100);
+
+        // Only place if discount is reasonable (not too small or too large)
+        if discount_pct < dec!(0.01) || discount_pct > dec!(5.0) {
+            if self.debug_mode {
                 info!(
-                    "Placing stink bid: Price={}, Mid={}, Discount={}%, Imbalance={}, K={}",
-                    stink_bid_price,
-                    mid_price,
-                    discount_pct.round_dp(4),
-                    self.last_imbalance,
-                    imbalance_adjusted_k
+                    "Not placing stink bid - Discount {}% outside reasonable range (0.01-5.0%)",
+                    self.config.for_display(discount_pct)
                 );
-            } else if self.debug_mode {
+            }
+            return None;
+        }
+
+        // Never work for the exchange, the discount has to pay for getting in and out
+        let min_edge_bps =
+            self.config.fee_model.round_trip_maker_bps() + self.config.min_edge_buffer_bps;
+        if discount_pct * dec!(100) < min_edge_bps {
+            if self.debug_mode {
                 info!(
-                    "Not placing stink bid - Discount {}% outside reasonable range (0.01-5.0%)",
-                    discount_pct.round_dp(4)
+                    "Not placing stink bid - Discount {}% below round trip fees plus buffer ({}bps)",
+                    self.config.for_display(discount_pct),
+                    min_edge_bps
                 );
             }
-        } else if self.debug_mode {
-            // Log why we couldn't place an order
-            info!(
-                "Missing data for stink bid: mid_price={:?}, volatility={:?}, best_bid={:?}, best_ask={:?}",
-                self.order_book.mid_price,
-                self.last_volatility,
-                self.order_book.best_bid,
-                self.order_book.best_ask
+            return None;
+        }
+
+        // Too shallow for the current volatility, however the percentages look
+        let mut distance_sigmas = None;
+        if let Some(min_sigmas) = self.config.min_distance_sigmas {
+            let sigmas = distance_in_sigmas(stink_bid_price, mid_price, price_volatility)?;
+            if sigmas < min_sigmas {
+                if self.debug_mode {
+                    info!(
+                        "Not placing stink bid - {}σ below fair value, minimum is {}σ",
+                        self.config.for_display(sigmas),
+                        min_sigmas
+                    );
+                }
+                return None;
+            }
+            distance_sigmas = Some(sigmas);
+        }
+
+        Some(StinkBidIntent {
+            price: stink_bid_price,
+            size: self.config.order_size,
+            mid_price,
+            best_bid,
+            volatility,
+            imbalance: self.imbalance_reading(),
+            k_used: imbalance_adjusted_k,
+            discount_pct,
+            distance_sigmas,
+        })
+    }
+
+    /// Applies [`MarketMakerConfig::value_area_bids`] to a stink bid price, `None` when the bid
+    /// shouldn't be placed. Snapped prices never exceed `max_price`.
+    fn constrain_to_value_area(&self, price: Decimal, max_price: Decimal) -> Option<Decimal> {
+        if self.config.value_area_bids == ValueAreaBids::Off {
+            return Some(price);
+        }
+        let (Some(poc), Some((value_area_low, _))) = (
+            self.volume_profile.point_of_control(),
+            self.volume_profile.value_area(VALUE_AREA_FRACTION),
+        ) else {
+            debug!("No volume profile yet, not placing stink bid");
+            return None;
+        };
+
+        let bucket = self.volume_profile.get_price_bucket(price);
+        if (value_area_low..=poc).contains(&bucket) {
+            return Some(price);
+        }
+        match self.config.value_area_bids {
+            ValueAreaBids::Snap => {
+                let snapped = price.clamp(value_area_low, poc).min(max_price);
+                if snapped < value_area_low {
+                    debug!(
+                        "Support zone {}-{} is within the minimum distance, not placing stink bid",
+                        value_area_low, poc
+                    );
+                    return None;
+                }
+                debug!(
+                    "Snapped stink bid from {} to {} (support zone {}-{})",
+                    price, snapped, value_area_low, poc
+                );
+                Some(snapped)
+            }
+            _ => {
+                debug!(
+                    "Stink bid {} outside support zone {}-{}, skipping",
+                    price, value_area_low, poc
+                );
+                None
+            }
+        }
+    }
+
+    /// Clamps the volatility used for pricing into the configured bounds
+    fn clamp_volatility(&self, volatility: Decimal) -> Decimal {
+        let clamped = volatility.clamp(self.config.min_volatility, self.config.max_volatility);
+        if clamped != volatility {
+            debug!(
+                "Clamped volatility {} to {} (bounds: {}-{})",
+                volatility, clamped, self.config.min_volatility, self.config.max_volatility
             );
         }
+        clamped
+    }
 
-        Ok(())
+    /// A new order id, unique for this maker however many orders go out at the same time
+    fn next_order_id(&mut self, prefix: &str) -> String {
+        self.order_sequence += 1;
+        format!("{prefix}-{}", self.order_sequence)
     }
 
     /// Creates and adds a new order to active orders
-    fn place_order(
-        &mut self,
-        price: Decimal,
-        size: Decimal,
-        reference_mid: Decimal,
-        reference_best_bid: Decimal,
-        k_factor_used: Decimal,
-    ) -> Result<()> {
+    fn place_order(&mut self, intent: &StinkBidIntent) -> Result<()> {
         let order = Order {
-            id: format!("order-{}", Utc::now().timestamp_millis()),
-            price,
-            size,
+            id: self.next_order_id("order"),
+            price: intent.price,
+            size: intent.size,
+            side: OrderSide::Buy,
             status: OrderStatus::Placed, // Directly mark as placed
-            created_at: Utc::now(),
+            created_at: self.clock.now(),
             filled_at: None,
-            reference_mid,
-            reference_best_bid,
-            k_factor_used,
-            imbalance_at_placement: self.last_imbalance,
+            reference_mid: intent.mid_price,
+            reference_best_bid: intent.best_bid,
+            k_factor_used: intent.k_used,
+            imbalance_at_placement: self.imbalance_reading(),
+            distance_sigmas: intent.distance_sigmas,
+            queue_ahead: self
+                .order_book
+                .bids
+                .get(&intent.price)
+                .copied()
+                .unwrap_or_default(),
+            cancel_reason: None,
+            fill_quality: None,
+            fill_source: None,
         };
 
+        self.audit_log
+            .record(&order, OrderEvent::Placed, order.created_at);
         self.active_orders.push(order);
 
         Ok(())
     }
 
-    /// Adjusts k-factor based on success or failure
-    fn adjust_k_factor(&mut self, was_successful: bool) {
-        if was_successful {
-            // If order was filled successfully, slightly decrease k to be more aggressive
-            self.current_k =
-                (self.current_k * (dec!(1) - self.config.learning_rate)).max(dec!(0.1)); // Don't go below a minimum threshold
-        } else {
-            // If order wasn't filled, increase k to be more conservative
-            self.current_k =
-                (self.current_k * (dec!(1) + self.config.learning_rate)).min(dec!(3.0)); // Don't go above a maximum threshold
-        }
+    /// Adjusts k-factor with the policy, based on what happened to an order
+    fn adjust_k_factor(&mut self, outcome: &KOutcome) {
+        self.current_k = self.k_policy.next_k(self.current_k, outcome);
 
         debug!(
-            "Adjusted k-factor: {} (after {})",
-            self.current_k,
-            if was_successful {
-                "successful fill"
-            } else {
-                "cancellation"
-            }
+            "Adjusted k-factor: {} (after {:?})",
+            self.config.for_display(self.current_k),
+            outcome
         );
     }
 
+    /// Mean depth of the filled orders at placement in price volatilities, `None` before any
+    /// fill with one recorded
+    pub fn average_fill_depth_sigmas(&self) -> Option<Decimal> {
+        let depths: Vec<Decimal> = self
+            .filled_orders
+            .iter()
+            .filter_map(|order| order.distance_sigmas)
+            .collect();
+        depths
+            .iter()
+            .sum::<Decimal>()
+            .checked_div(Decimal::from(depths.len()))
+    }
+
+    /// PnL of filled orders marked against the mid at placement, before fees
+    pub fn gross_pnl(&self) -> Decimal {
+        self.gross_pnl
+    }
+
+    pub fn fees_paid(&self) -> Decimal {
+        self.fees_paid
+    }
+
+    pub fn net_pnl(&self) -> Decimal {
+        self.gross_pnl - self.fees_paid
+    }
+
+    /// Move of the current mid from the mids filled orders were booked against, zero
+    /// without a two sided book
+    pub fn unrealized_pnl(&self) -> Decimal {
+        let Some(mid) = self.order_book.mid_price else {
+            return Decimal::ZERO;
+        };
+        self.filled_orders
+            .iter()
+            .map(|order| (mid - order.reference_mid) * order.signed_size())
+            .sum()
+    }
+
+    /// Net PnL with the filled inventory marked to the current mid
+    pub fn equity(&self) -> Decimal {
+        self.net_pnl() + self.unrealized_pnl()
+    }
+
+    /// How far equity is below its peak
+    pub fn drawdown(&self) -> Decimal {
+        self.peak_equity - self.equity()
+    }
+
+    /// Whether the maker stopped quoting, see [`Self::halt_reason`]
+    pub fn is_halted(&self) -> bool {
+        self.halt_reason.is_some()
+    }
+
+    /// Why the maker stopped quoting, `None` while it's running
+    pub fn halt_reason(&self) -> Option<HaltReason> {
+        self.halt_reason
+    }
+
+    /// Whether `now` falls within one of the [`MarketMakerConfig::suppression_windows`], or
+    /// the pause after a flow spike, when orders are pulled and none placed
+    pub fn is_suppressed(&self, now: DateTime<Utc>) -> bool {
+        self.flow_spike_until.is_some_and(|until| now < until)
+            || self
+                .config
+                .suppression_windows
+                .iter()
+                .any(|window| window.contains(now))
+    }
+
+    /// Resumes quoting after a halt, measuring the drawdown from the current equity on
+    pub fn reset_halt(&mut self) {
+        if self.halt_reason.take().is_some() {
+            info!("Halt reset, resuming stink bids");
+        }
+        self.peak_equity = self.equity();
+    }
+
+    /// Tracks the equity peak, halting on a [`MarketMakerConfig::max_drawdown`] breach and
+    /// resuming once the drawdown recovers to [`MarketMakerConfig::drawdown_resume`]
+    fn update_drawdown(&mut self) {
+        let equity = self.equity();
+        self.peak_equity = self.peak_equity.max(equity);
+        let drawdown = self.peak_equity - equity;
+
+        match self.halt_reason {
+            None => {
+                if let Some(max_drawdown) = self.config.max_drawdown
+                    && drawdown >= max_drawdown
+                {
+                    warn!(
+                        "[HALT] Drawdown of {} from peak equity {} breached the maximum of {}, cancelling all stink bids",
+                        self.config.for_display(drawdown),
+                        self.config.for_display(self.peak_equity),
+                        self.config.for_display(max_drawdown)
+                    );
+                    self.halt_reason = Some(HaltReason::MaxDrawdown {
+                        peak_equity: self.peak_equity,
+                        equity,
+                    });
+                    self.cancel_all(CancelReason::Halted);
+                }
+            }
+            Some(HaltReason::MaxDrawdown { .. }) => {
+                if self
+                    .config
+                    .drawdown_resume
+                    .is_some_and(|resume| drawdown <= resume)
+                {
+                    info!(
+                        "Drawdown recovered to {}, resuming stink bids",
+                        self.config.for_display(drawdown)
+                    );
+                    self.halt_reason = None;
+                }
+            }
+        }
+    }
+
+    /// Where our fills landed relative to the traded volume in `profile`
+    pub fn volume_profile_report(&self, profile: &VolumeProfile) -> VolumeProfileReport {
+        VolumeProfileReport::new(profile, &self.filled_orders)
+    }
+
+    /// Fitted order arrival intensity `λ(δ) = A·e^(−κδ)` from our fills so far
+    pub fn arrival_intensity(&self) -> Option<IntensityEstimate> {
+        self.intensity.estimate(self.clock.now())
+    }
+
+    /// Writes filled and cancelled orders, final statistics and PnL to `path`, see
+    /// [`SessionExport::write_to`] for the formats
+    pub fn export_session(&self, path: impl AsRef<Path>) -> Result<()> {
+        SessionExport::new(self).write_to(path)
+    }
+
     /// Gets current statistics
     pub fn get_statistics(&self) -> String {
         let win_rate = if self.attempt_count > 0 {
@@ -432,6 +2344,12 @@ impl MarketMaker {
         } else {
             0.0
         };
+        let fmt_opt = |value: Option<Decimal>| {
+            value.map_or_else(
+                || "n/a".to_string(),
+                |value| self.config.for_display(value).to_string(),
+            )
+        };
 
         format!(
             "Stink Bid Statistics:
@@ -440,17 +2358,57 @@ impl MarketMaker {
              - Active Orders: {}
              - Last Imbalance: {}
              - Last Volatility: {}
-             - Total Filled Orders: {}
-             - Total Cancelled Orders: {}",
+             - 1m Realized Volatility: {} (avg {})
+             - Total Filled Orders: {} (inferred {}, reported {})
+             - Total Cancelled Orders: {}
+             - Gross PnL: {}
+             - Fees: {}
+             - Net PnL: {}
+             - Equity: {} (drawdown {}, halted: {:?})
+             - Throttled Actions: {}
+             - Fill Divergences: {}
+             - Adverse Fill Rate: {}%
+             - Avg Fill Depth: {}σ",
             self.successful_fill_count,
             self.attempt_count,
             win_rate,
-            self.current_k,
+            self.config.for_display(self.current_k),
             self.active_orders.len(),
-            self.last_imbalance,
-            self.last_volatility,
+            self.config.for_display(self.imbalance_reading()),
+            self.config.for_display(self.last_volatility),
+            fmt_opt(self.realized_volatility.latest()),
+            fmt_opt(self.realized_volatility.rolling_average()),
             self.filled_orders.len(),
-            self.cancelled_orders.len()
+            self.fill_count_by_source(FillSource::TradeInference),
+            self.fill_count_by_source(FillSource::ExecutionReport),
+            self.cancelled_orders.len(),
+            self.gross_pnl,
+            self.fees_paid,
+            self.net_pnl(),
+            self.equity(),
+            self.drawdown(),
+            self.halt_reason,
+            self.throttle_hits,
+            self.fill_divergences,
+            self.config.for_display(self.adverse_fill_rate()),
+            fmt_opt(self.average_fill_depth_sigmas())
         )
     }
 }
+
+/// How many price volatilities `price` sits below `mid`, `None` without a positive volatility
+pub fn distance_in_sigmas(
+    price: Decimal,
+    mid: Decimal,
+    price_volatility: Decimal,
+) -> Option<Decimal> {
+    if price_volatility <= Decimal::ZERO {
+        return None;
+    }
+    Some((mid - price) / price_volatility)
+}
+
+/// Whether a buy at `price` would take liquidity against `best_ask` instead of resting
+pub fn is_marketable_bid(price: Decimal, best_ask: Decimal) -> bool {
+    price >= best_ask
+}
@@ -0,0 +1,45 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+/// Price and quantity increments of a symbol, used to render values at exchange precision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolPrecision {
+    /// Smallest price increment
+    pub tick_size: Decimal,
+    /// Smallest quantity increment
+    pub step_size: Decimal,
+}
+
+impl Default for SymbolPrecision {
+    fn default() -> Self {
+        // BTCUSDT
+        Self {
+            tick_size: dec!(0.01),
+            step_size: dec!(0.00001),
+        }
+    }
+}
+
+impl SymbolPrecision {
+    pub fn format_price(&self, price: Decimal) -> String {
+        format_to_increment(price, self.tick_size)
+    }
+
+    /// Rounds `price` down onto the tick grid
+    pub fn floor_to_tick(&self, price: Decimal) -> Decimal {
+        (price / self.tick_size).floor() * self.tick_size
+    }
+
+    pub fn format_size(&self, size: Decimal) -> String {
+        format_to_increment(size, self.step_size)
+    }
+}
+
+/// Renders `value` with exactly as many decimal places as `increment` has significant ones,
+/// so `95123.45000000` with a tick of `0.01000000` prints as `95123.45`
+pub fn format_to_increment(value: Decimal, increment: Decimal) -> String {
+    let dp = increment.normalize().scale() as usize;
+    format!("{:.*}", dp, value.round_dp(dp as u32))
+}
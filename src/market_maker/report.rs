@@ -0,0 +1,93 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::{cmp::Ordering, fmt};
+
+use super::Order;
+use crate::binance::VolumeProfile;
+
+/// Share of traded volume covered by the value area in reports
+pub const VALUE_AREA_FRACTION: Decimal = dec!(0.7);
+
+/// End of run summary of where the market traded and where our fills landed relative to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeProfileReport {
+    pub point_of_control: Option<Decimal>,
+    /// Lowest and highest bucket of the value area
+    pub value_area: Option<(Decimal, Decimal)>,
+    pub buy_volume: Decimal,
+    pub sell_volume: Decimal,
+    pub fills_below_poc: usize,
+    pub fills_at_poc: usize,
+    pub fills_above_poc: usize,
+    pub fills_in_value_area: usize,
+    /// Mean fill distance from the point of control, as a percentage of it. Negative below.
+    pub mean_fill_distance_pct: Option<Decimal>,
+}
+
+impl VolumeProfileReport {
+    pub fn new(profile: &VolumeProfile, fills: &[Order]) -> Self {
+        let point_of_control = profile.point_of_control();
+        let value_area = profile.value_area(VALUE_AREA_FRACTION);
+        let mut report = Self {
+            point_of_control,
+            value_area,
+            buy_volume: profile.total_buy_volume(),
+            sell_volume: profile.total_sell_volume(),
+            fills_below_poc: 0,
+            fills_at_poc: 0,
+            fills_above_poc: 0,
+            fills_in_value_area: 0,
+            mean_fill_distance_pct: None,
+        };
+        let Some(poc) = point_of_control else {
+            return report;
+        };
+
+        let mut total_distance = Decimal::ZERO;
+        for fill in fills {
+            let bucket = profile.get_price_bucket(fill.price);
+            match bucket.cmp(&poc) {
+                Ordering::Less => report.fills_below_poc += 1,
+                Ordering::Equal => report.fills_at_poc += 1,
+                Ordering::Greater => report.fills_above_poc += 1,
+            }
+            if value_area.is_some_and(|(low, high)| (low..=high).contains(&bucket)) {
+                report.fills_in_value_area += 1;
+            }
+            total_distance += (fill.price - poc) / poc * Decimal::ONE_HUNDRED;
+        }
+        if !fills.is_empty() {
+            report.mean_fill_distance_pct = Some(total_distance / Decimal::from(fills.len()));
+        }
+        report
+    }
+}
+
+impl fmt::Display for VolumeProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt_opt = |value: Option<Decimal>| {
+            value.map_or_else(|| "n/a".to_string(), |value| value.to_string())
+        };
+        writeln!(f, "Volume Profile:")?;
+        writeln!(f, "  POC: {}", fmt_opt(self.point_of_control))?;
+        match self.value_area {
+            Some((low, high)) => writeln!(f, "  Value Area: {} - {}", low, high)?,
+            None => writeln!(f, "  Value Area: n/a")?,
+        }
+        writeln!(
+            f,
+            "  Volume: buy {}, sell {}",
+            self.buy_volume, self.sell_volume
+        )?;
+        writeln!(
+            f,
+            "  Fills vs POC: {} below, {} at, {} above ({} in value area)",
+            self.fills_below_poc, self.fills_at_poc, self.fills_above_poc, self.fills_in_value_area
+        )?;
+        write!(
+            f,
+            "  Mean Fill Distance: {}%",
+            fmt_opt(self.mean_fill_distance_pct.map(|pct| pct.round_dp(4)))
+        )
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, NaiveTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// A stretch of time, in UTC, around a scheduled high impact event (funding, a data release)
+/// during which the maker pulls its orders and doesn't quote, see
+/// [`super::MarketMakerConfig::suppression_windows`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SuppressionWindow {
+    /// Recurs every day from `start`, running past midnight when it's late enough. Funding
+    /// every 8 hours is three of them.
+    Daily {
+        start: NaiveTime,
+        /// Under a day, in milliseconds
+        #[serde(with = "crate::config::duration_ms")]
+        duration: Duration,
+    },
+    /// A single window, from `start` up to `end`
+    Once {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+}
+
+impl SuppressionWindow {
+    /// Whether `now` falls within the window, start inclusive and end exclusive
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        match *self {
+            SuppressionWindow::Daily { start, duration } => {
+                let Ok(duration) = TimeDelta::from_std(duration) else {
+                    return false;
+                };
+                let started_today = now.date_naive().and_time(start).and_utc();
+                // Yesterday's window may still be running past midnight
+                [started_today, started_today - TimeDelta::days(1)]
+                    .iter()
+                    .any(|&started| started <= now && now < started + duration)
+            }
+            SuppressionWindow::Once { start, end } => start <= now && now < end,
+        }
+    }
+}
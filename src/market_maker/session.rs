@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use super::{MarketMaker, Order};
+use crate::symbol::Symbol;
+
+/// Everything a run produced, written out at shutdown for offline aggregation across runs
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionExport<'a> {
+    pub exported_at: DateTime<Utc>,
+    pub symbol: Option<&'a Symbol>,
+    pub successful_fill_count: usize,
+    pub attempt_count: usize,
+    pub final_k: Decimal,
+    pub adverse_fill_rate: Decimal,
+    pub throttle_hits: usize,
+    pub net_inventory: Decimal,
+    pub gross_pnl: Decimal,
+    pub fees_paid: Decimal,
+    pub net_pnl: Decimal,
+    pub filled_orders: &'a [Order],
+    pub cancelled_orders: &'a [Order],
+}
+
+impl<'a> SessionExport<'a> {
+    const CSV_HEADER: &'static str = "status,id,price,size,side,created_at,filled_at,reference_mid,reference_best_bid,k_factor_used,imbalance_at_placement,distance_sigmas,queue_ahead,cancel_reason,fill_quality,fill_source";
+
+    pub fn new(maker: &'a MarketMaker) -> Self {
+        Self {
+            exported_at: maker.clock.now(),
+            symbol: maker.symbol(),
+            successful_fill_count: maker.successful_fill_count,
+            attempt_count: maker.attempt_count,
+            final_k: maker.current_k,
+            adverse_fill_rate: maker.adverse_fill_rate(),
+            throttle_hits: maker.throttle_hits,
+            net_inventory: maker.net_inventory,
+            gross_pnl: maker.gross_pnl,
+            fees_paid: maker.fees_paid,
+            net_pnl: maker.net_pnl(),
+            filled_orders: &maker.filled_orders,
+            cancelled_orders: &maker.cancelled_orders,
+        }
+    }
+
+    /// Writes the session to `path`, as CSV when the extension is `csv` and JSON otherwise.
+    ///
+    /// The CSV has one row per order, with the session totals in leading `#` comment lines.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create session export {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        let is_csv = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            self.write_csv(&mut writer)?;
+        } else {
+            serde_json::to_writer_pretty(&mut writer, self)?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write session export {}", path.display()))
+    }
+
+    fn write_csv(&self, writer: &mut impl Write) -> Result<()> {
+        writeln!(writer, "# exported_at: {}", self.exported_at.to_rfc3339())?;
+        if let Some(symbol) = self.symbol {
+            writeln!(writer, "# symbol: {symbol}")?;
+        }
+        writeln!(
+            writer,
+            "# fills: {}/{}",
+            self.successful_fill_count, self.attempt_count
+        )?;
+        writeln!(writer, "# final_k: {}", self.final_k)?;
+        writeln!(writer, "# adverse_fill_rate: {}", self.adverse_fill_rate)?;
+        writeln!(writer, "# throttle_hits: {}", self.throttle_hits)?;
+        writeln!(writer, "# net_inventory: {}", self.net_inventory)?;
+        writeln!(writer, "# gross_pnl: {}", self.gross_pnl)?;
+        writeln!(writer, "# fees_paid: {}", self.fees_paid)?;
+        writeln!(writer, "# net_pnl: {}", self.net_pnl)?;
+        writeln!(writer, "{}", Self::CSV_HEADER)?;
+        for order in self.filled_orders.iter().chain(self.cancelled_orders) {
+            writeln!(writer, "{}", csv_row(order))?;
+        }
+        Ok(())
+    }
+}
+
+fn csv_row(order: &Order) -> String {
+    let field = |value: Option<String>| value.unwrap_or_default();
+    format!(
+        "{:?},{},{},{},{:?},{},{},{},{},{},{},{},{},{},{},{}",
+        order.status,
+        order.id,
+        order.price,
+        order.size,
+        order.side,
+        order.created_at.to_rfc3339(),
+        field(order.filled_at.map(|at| at.to_rfc3339())),
+        order.reference_mid,
+        order.reference_best_bid,
+        order.k_factor_used,
+        order.imbalance_at_placement,
+        field(order.distance_sigmas.map(|sigmas| sigmas.to_string())),
+        order.queue_ahead,
+        field(order.cancel_reason.map(|reason| format!("{reason:?}"))),
+        field(order.fill_quality.map(|quality| format!("{quality:?}"))),
+        field(order.fill_source.map(|source| format!("{source:?}")))
+    )
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// How far past a signal [`ForwardReturnJoiner`] measures the mid's return
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardHorizon {
+    /// At the first mid seen once this many more trades have printed
+    Trades(u64),
+    /// At the first mid seen once this much time has passed
+    Time(TimeDelta),
+}
+
+/// A signal paired with what the mid did after it, see [`ForwardReturnJoiner`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SignalOutcome {
+    pub signal_time: DateTime<Utc>,
+    pub imbalance: Decimal,
+    pub mid_price: Decimal,
+    pub outcome_time: DateTime<Utc>,
+    pub forward_mid_price: Decimal,
+    /// Fractional change from `mid_price` to `forward_mid_price`
+    pub forward_return: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PendingSignal {
+    time: DateTime<Utc>,
+    imbalance: Decimal,
+    mid_price: Decimal,
+    trades_seen: u64,
+}
+
+/// Holds on to imbalance readings until the forward return over [`ForwardHorizon`] is known,
+/// then hands them back joined with it, for correlating the signal with what followed offline.
+///
+/// Signals resolve in the order they were recorded, as both horizons only move forward.
+#[derive(Debug, Clone)]
+pub struct ForwardReturnJoiner {
+    horizon: ForwardHorizon,
+    pending: VecDeque<PendingSignal>,
+    trades_seen: u64,
+}
+
+impl ForwardReturnJoiner {
+    pub fn new(horizon: ForwardHorizon) -> Self {
+        Self {
+            horizon,
+            pending: VecDeque::new(),
+            trades_seen: 0,
+        }
+    }
+
+    /// Records an imbalance reading taken at `time` against `mid_price`
+    pub fn record(&mut self, time: DateTime<Utc>, imbalance: Decimal, mid_price: Decimal) {
+        self.pending.push_back(PendingSignal {
+            time,
+            imbalance,
+            mid_price,
+            trades_seen: self.trades_seen,
+        });
+    }
+
+    /// Counts a trade towards [`ForwardHorizon::Trades`]
+    pub fn on_trade(&mut self) {
+        self.trades_seen += 1;
+    }
+
+    /// Resolves every signal whose horizon has passed by `now` against `mid_price`, oldest
+    /// first
+    pub fn on_mid(&mut self, now: DateTime<Utc>, mid_price: Decimal) -> Vec<SignalOutcome> {
+        let mut outcomes = Vec::new();
+        while let Some(signal) = self.pending.front() {
+            let due = match self.horizon {
+                ForwardHorizon::Trades(trades) => self.trades_seen >= signal.trades_seen + trades,
+                ForwardHorizon::Time(delay) => now >= signal.time + delay,
+            };
+            if !due {
+                break;
+            }
+            if let Some(forward_return) =
+                (mid_price - signal.mid_price).checked_div(signal.mid_price)
+            {
+                outcomes.push(SignalOutcome {
+                    signal_time: signal.time,
+                    imbalance: signal.imbalance,
+                    mid_price: signal.mid_price,
+                    outcome_time: now,
+                    forward_mid_price: mid_price,
+                    forward_return,
+                });
+            }
+            self.pending.pop_front();
+        }
+        outcomes
+    }
+
+    /// Signals still waiting on their horizon
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+/// Token bucket refilling continuously at `capacity` tokens per `period_ms`
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    capacity: Decimal,
+    tokens: Decimal,
+    period_ms: Decimal,
+    last_refill: Option<DateTime<Utc>>,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, period_ms: i64) -> Self {
+        Self {
+            capacity: Decimal::from(capacity),
+            tokens: Decimal::from(capacity),
+            period_ms: Decimal::from(period_ms),
+            last_refill: None,
+        }
+    }
+
+    fn refill(&mut self, now: DateTime<Utc>) {
+        if let Some(last_refill) = self.last_refill {
+            // A clock going backwards never takes tokens away
+            let elapsed_ms = Decimal::from((now - last_refill).num_milliseconds().max(0));
+            self.tokens =
+                (self.tokens + elapsed_ms * self.capacity / self.period_ms).min(self.capacity);
+        }
+        self.last_refill = Some(now);
+    }
+
+    fn has_tokens(&self, count: Decimal) -> bool {
+        self.tokens >= count
+    }
+}
+
+/// Limits order actions (placements and cancels) to exchange style rate limits
+///
+/// An action is only allowed when both the per-second and the per-minute bucket have a
+/// token left, and then takes one from each.
+#[derive(Debug, Clone)]
+pub struct OrderThrottle {
+    per_second: TokenBucket,
+    per_minute: TokenBucket,
+}
+
+impl OrderThrottle {
+    pub fn new(max_per_second: u32, max_per_minute: u32) -> Self {
+        Self {
+            per_second: TokenBucket::new(max_per_second, 1_000),
+            per_minute: TokenBucket::new(max_per_minute, 60_000),
+        }
+    }
+
+    /// Takes a token for one order action at `now`, returning false if the budget is exhausted
+    pub fn try_acquire(&mut self, now: DateTime<Utc>) -> bool {
+        self.try_acquire_many(now, 1)
+    }
+
+    /// Takes tokens for `count` order actions at `now` that must all go ahead or none at all,
+    /// returning false and taking nothing if the budget can't cover every one
+    pub fn try_acquire_many(&mut self, now: DateTime<Utc>, count: u32) -> bool {
+        let count = Decimal::from(count);
+        self.per_second.refill(now);
+        self.per_minute.refill(now);
+        if !(self.per_second.has_tokens(count) && self.per_minute.has_tokens(count)) {
+            return false;
+        }
+        self.per_second.tokens -= count;
+        self.per_minute.tokens -= count;
+        true
+    }
+}
@@ -1,29 +1,491 @@
-use crate::binance::data::{DepthSnapshot, DepthUpdate, OfferData};
+use crate::{
+    binance::data::{BookTickerEvent, DepthSnapshot, DepthUpdate, OfferData},
+    book_history::{BookHistory, BookSample},
+    market_maker::OrderSide,
+    recent_trades::RecentTrades,
+    symbol::Symbol,
+};
 use anyhow::Result;
-use chrono::{DateTime, Utc};
-use rust_decimal::Decimal;
-use std::collections::{BTreeMap, VecDeque};
+use chrono::{DateTime, TimeDelta, Utc};
+use rust_decimal::{Decimal, MathematicalOps};
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::Duration,
+};
 use tracing::{debug, info, warn};
 
 type Price = Decimal;
 type Size = Decimal;
 
-#[derive(Debug, Clone, Default)]
+/// Levels per side the cached [`OrderBookState::weighted_imbalance`] is taken over
+pub const WEIGHTED_IMBALANCE_DEPTH: usize = 5;
+
+/// Local copy of a symbol's order book and the metrics derived from it.
+///
+/// Ratios, including every imbalance metric, are plain fractions (an imbalance is in
+/// [-1, 1]). Only methods and fields suffixed `_bps` are in basis points.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct OrderBookState {
     pub bids: BTreeMap<Price, Size>,
     pub asks: BTreeMap<Price, Size>,
     last_update_id: u64,
     last_update_time: DateTime<Utc>,
+    /// Whether `last_update_time` is our clock's rather than an event time, see
+    /// [`Self::is_stamped_locally`]
+    stamped_locally: bool,
+    /// Latest partial snapshot applied, see [`Self::apply_partial_snapshot`]
+    last_partial_update_id: u64,
     pub spread: Option<Decimal>,
+    /// Spread as a fraction of the mid price
     pub relative_spread: Option<Decimal>,
+    /// Spread in basis points of the mid price
+    pub spread_bps: Option<Decimal>,
     pub mid_price: Option<Decimal>,
+    pub microprice: Option<Decimal>,
     pub imbalance: Option<Decimal>,
+    /// [`Self::weighted_relative_imbalance`] over [`WEIGHTED_IMBALANCE_DEPTH`] levels with the
+    /// default weighting
     pub weighted_imbalance: Option<Decimal>,
     pub best_bid: Option<(Price, Size)>,
     pub best_ask: Option<(Price, Size)>,
+    pub update_semantics: UpdateSemantics,
+    /// Mid price and spread after each update
+    pub history: BookHistory,
+    /// Our own resting bids by price
+    own_bids: BTreeMap<Price, Size>,
+    /// Levels each side needs before the cached metrics are populated, see [`Self::liquidity_ok`]
+    min_levels: usize,
+    /// Sizes provisionally taken out by trades, see [`Self::apply_trade_decay`]
+    pending_trade_decay: Vec<(OrderSide, Price, Size)>,
+    /// Most levels kept per side, the furthest from the touch are dropped beyond it
+    max_levels_per_side: Option<usize>,
+    /// Symbol this book is for, updates for any other symbol are rejected
+    symbol: Option<Symbol>,
+    /// Top of book from a bookTicker ahead of the levels, see [`Self::apply_book_ticker`]
+    ticker_top: Option<TickerTop>,
+    /// How [`Self::quality_score`] combines its components
+    quality_weights: BookQualityWeights,
+}
+
+/// Weights and scales of [`OrderBookState::quality_score`].
+///
+/// Each component scores from 0 (unfavourable) to 1 (favourable) and the score is their
+/// weighted mean, so only the ratios between the weights matter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BookQualityWeights {
+    /// Weight of the spread component
+    pub spread: Decimal,
+    /// Weight of the near-mid depth component
+    pub depth: Decimal,
+    /// Weight of the mid stability component
+    pub stability: Decimal,
+    /// Spread at or below which the spread scores full marks. Tighter isn't better beyond
+    /// this, there's no edge left in it.
+    pub target_spread_bps: Decimal,
+    /// Spread at or above which the spread scores zero
+    pub max_spread_bps: Decimal,
+    /// Depth counts the size of levels within this many basis points of the mid
+    pub depth_band_bps: Decimal,
+    /// Combined bid and ask size in the band scoring full marks, in the base asset
+    pub target_depth: Decimal,
+    /// Stability is the trade price move over this long, in milliseconds
+    #[serde(with = "crate::config::duration_ms")]
+    pub stability_window: Duration,
+    /// Price move at or above which stability scores zero
+    pub max_move_bps: Decimal,
+}
+
+impl Default for BookQualityWeights {
+    fn default() -> Self {
+        Self {
+            spread: dec!(0.4),
+            depth: dec!(0.3),
+            stability: dec!(0.3),
+            target_spread_bps: dec!(1),
+            max_spread_bps: dec!(20),
+            depth_band_bps: dec!(10),
+            target_depth: dec!(10),
+            stability_window: Duration::from_secs(60),
+            max_move_bps: dec!(50),
+        }
+    }
+}
+
+/// How [`OrderBookState::weighted_relative_imbalance`] weights the levels below the touch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DepthWeighting {
+    /// The `i`th level from the touch gets `1 / (i + 1)`
+    #[default]
+    Harmonic,
+    /// Falls in equal steps from 1 at the touch to `1 / depth` at the deepest level
+    Linear,
+    /// The `i`th level from the touch gets `decay^i`
+    Exponential { decay: Decimal },
+    /// By how far a level is from the mid rather than how many levels down it is, so sparse
+    /// and dense books are weighted alike. Halves at `scale_bps` basis points from the mid.
+    Distance { scale_bps: Decimal },
+}
+
+impl DepthWeighting {
+    /// Weight of the `index`th level from the touch, `distance_bps` from the mid
+    fn weight(self, index: usize, depth: usize, distance_bps: Decimal) -> Option<Decimal> {
+        match self {
+            DepthWeighting::Harmonic => Decimal::ONE.checked_div(Decimal::from(index + 1)),
+            DepthWeighting::Linear => {
+                Decimal::from(depth - index).checked_div(Decimal::from(depth))
+            }
+            DepthWeighting::Exponential { decay } => decay.checked_powu(index as u64),
+            DepthWeighting::Distance { scale_bps } => {
+                scale_bps.checked_div(scale_bps + distance_bps)
+            }
+        }
+    }
+}
+
+/// Best bid and ask from a bookTicker event
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TickerTop {
+    update_id: u64,
+    bid: (Price, Size),
+    ask: (Price, Size),
+}
+
+/// Levels changed by a depth update
+///
+/// Changed levels carry their new total size, which covers both new and resized levels.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookDelta {
+    pub changed_bids: Vec<(Price, Size)>,
+    pub changed_asks: Vec<(Price, Size)>,
+    pub removed_bids: Vec<Price>,
+    pub removed_asks: Vec<Price>,
+}
+
+impl BookDelta {
+    /// Whether the update left the book as it was
+    pub fn is_empty(&self) -> bool {
+        self.changed_bids.is_empty()
+            && self.changed_asks.is_empty()
+            && self.removed_bids.is_empty()
+            && self.removed_asks.is_empty()
+    }
+
+    /// Whether the update reached the best bid or ask it was applied on top of, the only way
+    /// a depth update can move the top of book metrics
+    pub fn touches_top(
+        &self,
+        best_bid: Option<(Price, Size)>,
+        best_ask: Option<(Price, Size)>,
+    ) -> bool {
+        let bid_touched = |price: &Price| best_bid.is_none_or(|(best, _)| *price >= best);
+        let ask_touched = |price: &Price| best_ask.is_none_or(|(best, _)| *price <= best);
+        self.changed_bids
+            .iter()
+            .any(|(price, _)| bid_touched(price))
+            || self.removed_bids.iter().any(bid_touched)
+            || self
+                .changed_asks
+                .iter()
+                .any(|(price, _)| ask_touched(price))
+            || self.removed_asks.iter().any(ask_touched)
+    }
+}
+
+/// Outcome of applying a single level
+enum LevelChange {
+    Changed(Size),
+    Removed,
+    Unchanged,
+}
+
+/// Cumulative depth of both sides of the book, each ordered from the best price outward
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DepthChart {
+    pub bids: Vec<(Price, Size)>,
+    pub asks: Vec<(Price, Size)>,
+}
+
+/// Why buffered depth updates couldn't be joined onto a snapshot
+///
+/// Every variant means the snapshot should be refetched, keeping the buffered updates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BootstrapError {
+    /// The snapshot predates the first buffered update, so the updates in between were missed
+    SnapshotTooOld {
+        snapshot_update_id: u64,
+        first_buffered_id: u64,
+    },
+    /// A buffered update doesn't follow on from the one before it
+    Gap {
+        expected: u64,
+        first_update_id: u64,
+        final_update_id: u64,
+    },
+    /// A buffered update couldn't be applied
+    InvalidUpdate(String),
+}
+
+impl fmt::Display for BootstrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootstrapError::SnapshotTooOld {
+                snapshot_update_id,
+                first_buffered_id,
+            } => write!(
+                f,
+                "Snapshot {snapshot_update_id} is older than the first buffered update {first_buffered_id}"
+            ),
+            BootstrapError::Gap {
+                expected,
+                first_update_id,
+                final_update_id,
+            } => write!(
+                f,
+                "Buffered updates don't bridge the snapshot. Expected: {expected}, Update: [{first_update_id}, {final_update_id}]"
+            ),
+            BootstrapError::InvalidUpdate(e) => write!(f, "Failed to apply buffered update: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BootstrapError {}
+
+/// First level where the book differs from a reference snapshot, see
+/// [`OrderBookState::divergence_from`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelDivergence {
+    pub side: OrderSide,
+    /// Position from the best price, zero for the best level
+    pub level: usize,
+    pub expected: (Price, Size),
+    pub actual: Option<(Price, Size)>,
+}
+
+impl fmt::Display for LevelDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = match self.side {
+            OrderSide::Buy => "Bid",
+            OrderSide::Sell => "Ask",
+        };
+        write!(
+            f,
+            "{side} level {} diverges. Expected: {:?}, Actual: {:?}",
+            self.level, self.expected, self.actual
+        )
+    }
+}
+
+/// How the size of each level in a depth update is interpreted
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum UpdateSemantics {
+    /// The size is the new total at that price, zero removes the level (Binance)
+    #[default]
+    Absolute,
+    /// The size is a signed change to the existing level, which is removed at or below zero
+    Delta,
 }
 
 impl OrderBookState {
+    /// `last_update_id` of a book built by [`Self::from_levels`]
+    pub const SYNTHETIC_UPDATE_ID: u64 = 1;
+
+    pub fn with_update_semantics(update_semantics: UpdateSemantics) -> Self {
+        Self {
+            update_semantics,
+            ..Default::default()
+        }
+    }
+
+    /// A book holding `bids` and `asks` as `(price, size)` levels, e.g. for tests or
+    /// hypothetical books in analysis.
+    ///
+    /// The book is at [`Self::SYNTHETIC_UPDATE_ID`], so depth updates carry on from there, and
+    /// levels with no size are skipped as in a snapshot.
+    pub fn from_levels(bids: Vec<(Decimal, Decimal)>, asks: Vec<(Decimal, Decimal)>) -> Self {
+        let levels = |levels: Vec<(Decimal, Decimal)>| {
+            levels
+                .into_iter()
+                .map(|(price, size)| OfferData { price, size })
+                .collect()
+        };
+        let mut book = Self::default();
+        book.apply_snapshot(DepthSnapshot {
+            last_update_id: Self::SYNTHETIC_UPDATE_ID,
+            bids: levels(bids),
+            asks: levels(asks),
+        });
+        book
+    }
+
+    /// Sets how many mid/spread samples are kept in [`Self::history`]
+    pub fn with_history_len(mut self, len: usize) -> Self {
+        self.history.set_capacity(len);
+        self
+    }
+
+    /// Sets how many levels each side needs before the cached metrics are populated
+    pub fn with_min_levels(mut self, min_levels: usize) -> Self {
+        self.min_levels = min_levels;
+        self.refresh_metrics();
+        self
+    }
+
+    /// Only accepts depth updates for `symbol`
+    pub fn with_symbol(mut self, symbol: Symbol) -> Self {
+        self.symbol = Some(symbol);
+        self
+    }
+
+    pub fn symbol(&self) -> Option<&Symbol> {
+        self.symbol.as_ref()
+    }
+
+    /// Caps each side at `max_levels` levels, dropping those furthest from the touch after
+    /// every snapshot and update so memory stays bounded over long sessions.
+    pub fn with_max_levels_per_side(mut self, max_levels: usize) -> Self {
+        self.max_levels_per_side = Some(max_levels);
+        self.trim_levels();
+        self.refresh_metrics();
+        self
+    }
+
+    /// Raises the level cap to at least `depth`, so trimming never removes levels a depth
+    /// based metric reads
+    pub fn reserve_metric_depth(&mut self, depth: usize) {
+        if let Some(max_levels) = self.max_levels_per_side.as_mut()
+            && *max_levels < depth
+        {
+            debug!(
+                "Raising max levels per side from {} to {}",
+                max_levels, depth
+            );
+            *max_levels = depth;
+        }
+    }
+
+    pub fn max_levels_per_side(&self) -> Option<usize> {
+        self.max_levels_per_side
+    }
+
+    /// Sets how [`Self::quality_score`] weighs and scales its components
+    pub fn with_quality_weights(mut self, quality_weights: BookQualityWeights) -> Self {
+        self.quality_weights = quality_weights;
+        self
+    }
+
+    /// Takes the top of book from a bookTicker event that's ahead of the depth stream.
+    ///
+    /// The bookTicker stream usually reports top of book moves before the diff depth stream.
+    /// The best bid and ask and the top of book metrics follow the ticker until a depth update
+    /// catches up with its `update_id`, at which point the levels are authoritative again.
+    /// Returns whether the ticker was applied, stale or crossed tickers are ignored.
+    pub fn apply_book_ticker(&mut self, ticker: &BookTickerEvent) -> bool {
+        if self
+            .symbol
+            .as_ref()
+            .is_some_and(|symbol| symbol.as_str() != ticker.symbol)
+        {
+            warn!("Ignoring book ticker for {}", ticker.symbol);
+            return false;
+        }
+        if ticker.update_id <= self.last_update_id
+            || self
+                .ticker_top
+                .is_some_and(|top| ticker.update_id <= top.update_id)
+        {
+            return false;
+        }
+        if ticker.best_bid_price >= ticker.best_ask_price
+            || ticker.best_bid_qty <= Decimal::ZERO
+            || ticker.best_ask_qty <= Decimal::ZERO
+        {
+            debug!("Ignoring degenerate book ticker {}", ticker.update_id);
+            return false;
+        }
+
+        self.ticker_top = Some(TickerTop {
+            update_id: ticker.update_id,
+            bid: (ticker.best_bid_price, ticker.best_bid_qty),
+            ask: (ticker.best_ask_price, ticker.best_ask_qty),
+        });
+        self.refresh_metrics();
+        true
+    }
+
+    /// Overrides the top of book metrics with the bookTicker's, once the levels are behind it.
+    ///
+    /// Only the metrics read off the best bid and ask change, the depth weighted ones stay with
+    /// the levels. Our own bids come out of the ticker's bid size like they do from the levels',
+    /// and when they're all there is at the bid the levels' imbalance is kept.
+    fn apply_ticker_top(&mut self) {
+        let Some(top) = self.ticker_top else {
+            return;
+        };
+        if top.update_id <= self.last_update_id {
+            debug!("Depth stream caught up with book ticker {}", top.update_id);
+            self.ticker_top = None;
+            return;
+        }
+
+        let ((bid, bid_size), (ask, ask_size)) = (top.bid, top.ask);
+        self.best_bid = Some(top.bid);
+        self.best_ask = Some(top.ask);
+        if self.liquidity_ok() {
+            let mid_price = (bid + ask) / Decimal::TWO;
+            self.spread = Some(ask - bid);
+            self.mid_price = Some(mid_price);
+            self.relative_spread = Some((ask - bid) / mid_price);
+            self.spread_bps = self
+                .relative_spread
+                .map(|spread| spread * Decimal::from(10_000));
+            self.microprice = (bid * ask_size + ask * bid_size).checked_div(bid_size + ask_size);
+            let own = self.own_bids.get(&bid).copied().unwrap_or_default();
+            let external_bid_size = bid_size - own;
+            if external_bid_size > Decimal::ZERO {
+                self.imbalance =
+                    (external_bid_size - ask_size).checked_div(external_bid_size + ask_size);
+            }
+        }
+    }
+
+    /// Drops the levels beyond [`Self::max_levels_per_side`], the lowest bids and highest asks
+    fn trim_levels(&mut self) {
+        let Some(max_levels) = self.max_levels_per_side else {
+            return;
+        };
+        let mut trimmed = 0;
+        while self.bids.len() > max_levels {
+            self.bids.pop_first();
+            trimmed += 1;
+        }
+        while self.asks.len() > max_levels {
+            self.asks.pop_last();
+            trimmed += 1;
+        }
+        if trimmed > 0 {
+            debug!(
+                "Trimmed {} levels beyond the {} level cap",
+                trimmed, max_levels
+            );
+        }
+    }
+
+    /// Whether both sides hold at least the minimum number of levels and some size.
+    ///
+    /// A book with a single stale level on one side still yields a mid and spread, so the
+    /// cached metrics stay `None` until this holds, e.g. right after a thin snapshot.
+    pub fn liquidity_ok(&self) -> bool {
+        let has_liquidity = |levels: &BTreeMap<Price, Size>| {
+            levels.len() >= self.min_levels && levels.values().any(|size| *size > Decimal::ZERO)
+        };
+        has_liquidity(&self.bids) && has_liquidity(&self.asks)
+    }
+
     pub fn apply_snapshot(&mut self, snapshot: DepthSnapshot) {
         info!(
             "Applying snaphot with last_update_id: {}",
@@ -32,35 +494,96 @@ impl OrderBookState {
 
         self.bids.clear();
         self.asks.clear();
+        self.pending_trade_decay.clear();
 
         for OfferData { price, size } in snapshot.bids {
-            if size > Decimal::ZERO {
+            if Self::is_valid_level(price, size, "bid") && size > Decimal::ZERO {
                 self.bids.insert(price, size);
             }
         }
 
         for OfferData { price, size } in snapshot.asks {
-            if size > Decimal::ZERO {
+            if Self::is_valid_level(price, size, "ask") && size > Decimal::ZERO {
                 self.asks.insert(price, size);
             }
         }
 
         self.last_update_id = snapshot.last_update_id;
         self.last_update_time = Utc::now();
+        self.stamped_locally = true;
+        self.trim_levels();
+        self.refresh_metrics();
         info!(
             "Local orderbook state initialized with last_update_id: {}",
             self.last_update_id
         );
     }
 
-    pub fn process_update(&mut self, update: DepthUpdate) -> Result<()> {
+    /// Seeds or corrects the top of the book from a partial depth snapshot (`@depth<N>` stream).
+    ///
+    /// Levels within the snapshot's price range on each side are replaced by the snapshot's,
+    /// deeper levels are kept as they were. An empty book is seeded, and diff updates carry on
+    /// from the snapshot's `last_update_id`. Otherwise the deeper levels are still only as
+    /// recent as the book, so diff updates carry on from the book's `last_update_id` and bring
+    /// them up to date. That replays the updates the snapshot already covers over its levels,
+    /// which ends in the same sizes once the book reaches the snapshot. Returns false, leaving
+    /// the book untouched, for a snapshot no newer than the book or the last partial snapshot.
+    pub fn apply_partial_snapshot(&mut self, mut snapshot: DepthSnapshot) -> bool {
+        let latest = self.last_update_id.max(self.last_partial_update_id);
+        if snapshot.last_update_id <= latest {
+            debug!(
+                "Ignoring partial snapshot {}, book is at {}",
+                snapshot.last_update_id, latest
+            );
+            return false;
+        }
+        let seeding = self.bids.is_empty() && self.asks.is_empty();
+        self.restore_trade_decay();
+
+        // Malformed levels mustn't widen the range the snapshot replaces
+        snapshot
+            .bids
+            .retain(|level| Self::is_valid_level(level.price, level.size, "bid"));
+        snapshot
+            .asks
+            .retain(|level| Self::is_valid_level(level.price, level.size, "ask"));
+        if let Some(deepest_bid) = snapshot.bids.iter().map(|level| level.price).min() {
+            self.bids.retain(|&price, _| price < deepest_bid);
+        }
+        if let Some(deepest_ask) = snapshot.asks.iter().map(|level| level.price).max() {
+            self.asks.retain(|&price, _| price > deepest_ask);
+        }
+        for OfferData { price, size } in snapshot.bids {
+            if size > Decimal::ZERO {
+                self.bids.insert(price, size);
+            }
+        }
+        for OfferData { price, size } in snapshot.asks {
+            if size > Decimal::ZERO {
+                self.asks.insert(price, size);
+            }
+        }
+
+        if seeding {
+            self.last_update_id = snapshot.last_update_id;
+        }
+        self.last_partial_update_id = snapshot.last_update_id;
+        self.last_update_time = Utc::now();
+        self.stamped_locally = true;
+        self.trim_levels();
+        self.refresh_metrics();
+        true
+    }
+
+    /// Applies a live depth update, returning the levels it changed
+    pub fn process_update(&mut self, update: DepthUpdate) -> Result<BookDelta> {
         debug!(
             "Processing update: [{}-{}]",
             update.first_update_id, update.final_update_id
         );
         if update.final_update_id <= self.last_update_id {
             debug!("Ignoring old update");
-            return Ok(()); // Silently ignore old updates
+            return Ok(BookDelta::default()); // Silently ignore old updates
         }
         if update.first_update_id > self.last_update_id + 1 {
             return Err(anyhow::Error::msg(format!(
@@ -69,95 +592,178 @@ impl OrderBookState {
             )));
         }
 
-        self.apply_update_changes(update)
+        self.apply_update_changes(&update)
     }
 
-    pub fn process_buffer(&mut self, mut buffer: VecDeque<DepthUpdate>) -> Result<()> {
-        let buffer_size = buffer.len();
-        info!("Processing {} buffered updates", buffer_size);
+    /// Initialises the book from a REST snapshot and the depth updates buffered while it
+    /// was being fetched, following Binance's documented procedure.
+    ///
+    /// Buffered updates with `final_update_id <= snapshot.last_update_id` are dropped, the first
+    /// remaining update must straddle `last_update_id + 1` and every later one must follow on
+    /// from the previous. The whole buffer is checked before anything is applied, so on error
+    /// the book is untouched and the caller can fetch a newer snapshot and retry with the same
+    /// (by then longer) buffer.
+    pub fn bootstrap(
+        &mut self,
+        snapshot: DepthSnapshot,
+        buffered_updates: &[DepthUpdate],
+    ) -> Result<(), BootstrapError> {
+        let snapshot_update_id = snapshot.last_update_id;
+        if let Some(first) = buffered_updates.first()
+            && first.first_update_id > snapshot_update_id + 1
+        {
+            return Err(BootstrapError::SnapshotTooOld {
+                snapshot_update_id,
+                first_buffered_id: first.first_update_id,
+            });
+        }
+
+        let applicable = buffered_updates
+            .iter()
+            .skip_while(|update| update.final_update_id <= snapshot_update_id)
+            .collect::<Vec<_>>();
+
+        let mut expected = snapshot_update_id + 1;
+        for update in &applicable {
+            if update.first_update_id > expected {
+                return Err(BootstrapError::Gap {
+                    expected,
+                    first_update_id: update.first_update_id,
+                    final_update_id: update.final_update_id,
+                });
+            }
+            expected = update.final_update_id + 1;
+        }
+
+        info!(
+            "Bootstrapping from snapshot {} with {} of {} buffered updates",
+            snapshot_update_id,
+            applicable.len(),
+            buffered_updates.len()
+        );
+        self.apply_snapshot(snapshot);
+        for update in applicable {
+            // Only fails on malformed levels, which the checks above can't catch
+            self.apply_update_changes(update)
+                .map_err(|e| BootstrapError::InvalidUpdate(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Applies depth updates buffered while the book's snapshot was fetched.
+    ///
+    /// Updates entirely at or before the book's `last_update_id` are skipped, applying starts
+    /// at the first that straddles `last_update_id + 1` and every later one must follow on from
+    /// the previous. Errors with a [`BootstrapError`] if updates past the snapshot were
+    /// buffered but none straddles it, or on a gap after it. A buffer with nothing past the
+    /// snapshot leaves the book as it is.
+    pub fn process_buffer(&mut self, buffer: VecDeque<DepthUpdate>) -> Result<()> {
+        info!("Processing {} buffered updates", buffer.len());
+
+        let snapshot_update_id = self.last_update_id;
+        let mut expected = snapshot_update_id + 1;
+        let Some(start) = buffer.iter().position(|update| {
+            update.first_update_id <= expected && update.final_update_id >= expected
+        }) else {
+            return match buffer
+                .iter()
+                .find(|update| update.final_update_id > snapshot_update_id)
+            {
+                Some(first) => {
+                    warn!(
+                        "No buffered update straddles snapshot {}, first past it is [{}, {}]",
+                        snapshot_update_id, first.first_update_id, first.final_update_id
+                    );
+                    Err(BootstrapError::SnapshotTooOld {
+                        snapshot_update_id,
+                        first_buffered_id: first.first_update_id,
+                    }
+                    .into())
+                }
+                None => {
+                    debug!("No buffered updates past snapshot {}", snapshot_update_id);
+                    Ok(())
+                }
+            };
+        };
+        debug!(
+            "Skipping {} buffered updates before snapshot {}",
+            start, snapshot_update_id
+        );
 
-        while let Some(update) = buffer.pop_front() {
-            if update.final_update_id <= self.last_update_id {
+        for update in buffer.iter().skip(start) {
+            if update.final_update_id < expected {
                 debug!("Ignoring old update: {}", update.final_update_id);
                 continue;
             }
-            if update.first_update_id <= self.last_update_id + 1 {
-                self.apply_update_changes(update)?;
-            } else {
+            if update.first_update_id > expected {
                 warn!(
                     "Out of sequence update during initial buffering: {}",
                     update.final_update_id
                 );
-                return Err(anyhow::Error::msg(
-                    "Out of sequence update during initial buffering",
-                ));
+                return Err(BootstrapError::Gap {
+                    expected,
+                    first_update_id: update.first_update_id,
+                    final_update_id: update.final_update_id,
+                }
+                .into());
             }
+            self.apply_update_changes(update)?;
+            expected = update.final_update_id + 1;
         }
         Ok(())
     }
 
-    fn apply_update_changes(&mut self, update: DepthUpdate) -> Result<()> {
+    /// Applies the levels of `update`, returning which of them actually changed the book
+    pub fn apply_update_changes(&mut self, update: &DepthUpdate) -> Result<BookDelta> {
+        if let Some(symbol) = &self.symbol
+            && symbol.as_str() != update.symbol
+        {
+            return Err(anyhow::Error::msg(format!(
+                "Depth update for {} applied to the {} order book",
+                update.symbol, symbol
+            )));
+        }
+
+        // The update is authoritative, so any trade decay is undone before it's applied
+        let restored = self.restore_trade_decay();
+
+        // Sequence-only bumps leave the book, and so every derived metric, unchanged
+        if update.bids.is_empty() && update.asks.is_empty() {
+            debug!(
+                "Empty update, advancing last_update_id to {}",
+                update.final_update_id
+            );
+            self.last_update_id = update.final_update_id;
+            self.last_update_time = update.event_time;
+            self.stamped_locally = false;
+            if restored {
+                self.refresh_metrics();
+            }
+            return Ok(BookDelta::default());
+        }
+
+        // Top of book before the update, to tell whether the metrics need recomputing. The
+        // imbalance skips our own orders, so the best external bid is what the bids are
+        // compared against.
+        let was_liquid = self.liquidity_ok();
+        let (previous_best_bid, previous_best_ask) = (self.external_bids().next(), self.best_ask);
+
+        let semantics = self.update_semantics;
+        let mut delta = BookDelta::default();
         for &OfferData { price, size } in &update.bids {
-            if size > Decimal::ZERO {
-                match self.bids.insert(price, size) {
-                    Some(existing_size) => {
-                        if existing_size != size {
-                            debug!(
-                                "Updated bid price: {} from {} to {} diff: {}",
-                                price,
-                                existing_size,
-                                size,
-                                existing_size - size
-                            );
-                        } else {
-                            debug!("Bid price: {} size unchanged: {}", price, size);
-                        }
-                    }
-                    None => {
-                        debug!("New bid price: {} with size: {}", price, size);
-                    }
-                }
-            } else {
-                match self.bids.remove(&price) {
-                    Some(existing_size) => {
-                        debug!("Removed bid price: {} with size: {}", price, existing_size);
-                    }
-                    None => {
-                        debug!("Ignoring zero size bid price: {}", price);
-                    }
-                }
+            match Self::apply_level(&mut self.bids, price, size, semantics, "bid") {
+                LevelChange::Changed(size) => delta.changed_bids.push((price, size)),
+                LevelChange::Removed => delta.removed_bids.push(price),
+                LevelChange::Unchanged => {}
             }
         }
 
         for &OfferData { price, size } in &update.asks {
-            if size > Decimal::ZERO {
-                match self.asks.insert(price, size) {
-                    Some(existing_size) => {
-                        if existing_size != size {
-                            debug!(
-                                "Updated ask price: {} from {} to {} diff: {}",
-                                price,
-                                existing_size,
-                                size,
-                                existing_size - size
-                            );
-                        } else {
-                            debug!("Ask price: {} size unchanged: {}", price, size);
-                        }
-                    }
-                    None => {
-                        debug!("New ask price: {} with size: {}", price, size);
-                    }
-                }
-            } else {
-                match self.asks.remove(&price) {
-                    Some(existing_size) => {
-                        debug!("Removed ask price: {} with size: {}", price, existing_size);
-                    }
-                    None => {
-                        debug!("Ignoring zero size ask price: {}", price);
-                    }
-                }
+            match Self::apply_level(&mut self.asks, price, size, semantics, "ask") {
+                LevelChange::Changed(size) => delta.changed_asks.push((price, size)),
+                LevelChange::Removed => delta.removed_asks.push(price),
+                LevelChange::Unchanged => {}
             }
         }
 
@@ -167,15 +773,188 @@ impl OrderBookState {
         );
         self.last_update_id = update.final_update_id;
         self.last_update_time = update.event_time;
-        self.spread = self.spread();
-        self.relative_spread = self.relative_spread();
-        self.mid_price = self.mid_price();
-        self.imbalance = self.imbalance();
+        self.stamped_locally = false;
+        self.trim_levels();
+        // Most updates only move levels behind the touch, which leaves the top of book metrics
+        // as they were. Restored trade decay and a pending ticker top always need a full
+        // refresh. Levels behind the touch still weigh into the weighted imbalance.
+        if restored
+            || self.ticker_top.is_some()
+            || self.liquidity_ok() != was_liquid
+            || delta.touches_top(previous_best_bid, previous_best_ask)
+        {
+            self.refresh_metrics();
+        } else if self.liquidity_ok() && self.reaches_weighted_depth(&delta) {
+            self.weighted_imbalance = self.default_weighted_imbalance();
+        }
+
+        if let (Some(mid_price), Some(spread)) = (self.mid_price, self.spread) {
+            self.history.record(BookSample {
+                timestamp: self.last_update_time,
+                mid_price,
+                spread,
+            });
+        }
+
+        Ok(delta)
+    }
+
+    /// Provisionally removes the liquidity an aggressive trade printed through.
+    ///
+    /// A taking sell (`buyer_market_maker`) at `price` must have emptied every bid above it, a
+    /// taking buy every ask below it, so those levels go whole and `quantity`, the size printed
+    /// at `price`, comes off the level there. This only bridges the gap until the depth update
+    /// confirming the trade arrives: the next update undoes the decay before it's applied.
+    pub fn apply_trade_decay(&mut self, price: Price, quantity: Size, buyer_market_maker: bool) {
+        let (side, levels) = if buyer_market_maker {
+            (OrderSide::Buy, &mut self.bids)
+        } else {
+            (OrderSide::Sell, &mut self.asks)
+        };
+        let swept: Vec<Price> = match side {
+            OrderSide::Buy => levels
+                .range(price..)
+                .map(|(&p, _)| p)
+                .filter(|&p| p > price)
+                .collect(),
+            OrderSide::Sell => levels.range(..price).map(|(&p, _)| p).collect(),
+        };
+
+        for level in swept {
+            if let Some(size) = levels.remove(&level) {
+                debug!("Trade swept {:?} level {} of {}", side, level, size);
+                self.pending_trade_decay.push((side.clone(), level, size));
+            }
+        }
+        if let Some(size) = levels.get_mut(&price) {
+            let taken = quantity.min(*size);
+            *size -= taken;
+            if size.is_zero() {
+                levels.remove(&price);
+            }
+            debug!("Trade decayed {:?} level {} by {}", side, price, taken);
+            self.pending_trade_decay.push((side, price, taken));
+        }
+        self.refresh_metrics();
+    }
+
+    /// Puts back any size taken out by [`Self::apply_trade_decay`], returning whether there was any
+    fn restore_trade_decay(&mut self) -> bool {
+        if self.pending_trade_decay.is_empty() {
+            return false;
+        }
+        for (side, price, size) in self.pending_trade_decay.drain(..) {
+            let levels = match side {
+                OrderSide::Buy => &mut self.bids,
+                OrderSide::Sell => &mut self.asks,
+            };
+            *levels.entry(price).or_default() += size;
+        }
+        true
+    }
+
+    /// Recomputes the cached top of book metrics from the levels
+    ///
+    /// The metrics are cleared rather than computed from a degenerate book, see
+    /// [`Self::liquidity_ok`]. The best bid and ask are always kept.
+    fn refresh_metrics(&mut self) {
+        if self.liquidity_ok() {
+            self.spread = self.spread();
+            self.relative_spread = self.relative_spread();
+            self.spread_bps = self.relative_spread_bps();
+            self.mid_price = self.mid_price();
+            self.microprice = self.microprice();
+            self.imbalance = self.imbalance();
+            self.weighted_imbalance = self.default_weighted_imbalance();
+        } else {
+            debug!("Insufficient liquidity, clearing book metrics");
+            self.spread = None;
+            self.relative_spread = None;
+            self.spread_bps = None;
+            self.mid_price = None;
+            self.microprice = None;
+            self.imbalance = None;
+            self.weighted_imbalance = None;
+        }
 
         self.best_bid = self.bids.last_key_value().map(|(&k, &v)| (k, v));
         self.best_ask = self.asks.first_key_value().map(|(&k, &v)| (k, v));
+        self.apply_ticker_top();
+    }
 
-        Ok(())
+    /// Whether a level from the exchange can go into the book: a positive price and a size
+    /// that isn't negative, zero meaning the level is gone. Malformed levels are logged and
+    /// skipped rather than left to corrupt the best bid or ask.
+    fn is_valid_level(price: Price, size: Size, side: &str) -> bool {
+        if price <= Decimal::ZERO || size < Decimal::ZERO {
+            warn!(
+                "Skipping malformed {} level, price: {} size: {}",
+                side, price, size
+            );
+            return false;
+        }
+        true
+    }
+
+    fn apply_level(
+        levels: &mut BTreeMap<Price, Size>,
+        price: Price,
+        size: Size,
+        semantics: UpdateSemantics,
+        side: &str,
+    ) -> LevelChange {
+        // Delta sizes are negative when a level shrinks, absolute ones never are
+        let checked_size = match semantics {
+            UpdateSemantics::Absolute => size,
+            UpdateSemantics::Delta => Decimal::ZERO,
+        };
+        if !Self::is_valid_level(price, checked_size, side) {
+            return LevelChange::Unchanged;
+        }
+
+        let size = match semantics {
+            UpdateSemantics::Absolute => size,
+            UpdateSemantics::Delta => levels.get(&price).copied().unwrap_or_default() + size,
+        };
+
+        if size > Decimal::ZERO {
+            match levels.insert(price, size) {
+                Some(existing_size) => {
+                    if existing_size != size {
+                        debug!(
+                            "Updated {} price: {} from {} to {} diff: {}",
+                            side,
+                            price,
+                            existing_size,
+                            size,
+                            existing_size - size
+                        );
+                        LevelChange::Changed(size)
+                    } else {
+                        debug!("{} price: {} size unchanged: {}", side, price, size);
+                        LevelChange::Unchanged
+                    }
+                }
+                None => {
+                    debug!("New {} price: {} with size: {}", side, price, size);
+                    LevelChange::Changed(size)
+                }
+            }
+        } else {
+            match levels.remove(&price) {
+                Some(existing_size) => {
+                    debug!(
+                        "Removed {} price: {} with size: {}",
+                        side, price, existing_size
+                    );
+                    LevelChange::Removed
+                }
+                None => {
+                    debug!("Ignoring zero size {} price: {}", side, price);
+                    LevelChange::Unchanged
+                }
+            }
+        }
     }
 
     fn spread(&self) -> Option<Decimal> {
@@ -193,117 +972,487 @@ impl OrderBookState {
         Some((top_ask - top_bid) / mid_price)
     }
 
+    /// Spread in basis points of the mid price
+    pub fn relative_spread_bps(&self) -> Option<Decimal> {
+        Some(self.relative_spread()? * Decimal::from(10_000))
+    }
+
     pub fn mid_price(&self) -> Option<Decimal> {
         let top_bid = self.bids.last_key_value()?.0;
         let top_ask = self.asks.first_key_value()?.0;
         Some((top_bid + top_ask) / Decimal::from(2))
     }
 
+    /// Mid price weighted by the opposite side's top level size, leaning towards the side
+    /// more likely to be taken out next.
+    pub fn microprice(&self) -> Option<Decimal> {
+        let (&top_bid, &top_bid_size) = self.bids.last_key_value()?;
+        let (&top_ask, &top_ask_size) = self.asks.first_key_value()?;
+        top_bid
+            .checked_mul(top_ask_size)?
+            .checked_add(top_ask.checked_mul(top_bid_size)?)?
+            .checked_div(top_bid_size.checked_add(top_ask_size)?)
+    }
+
+    /// Event time of the last depth update applied, on the exchange's clock. Snapshots carry
+    /// no event time, so after one it's our clock's time the snapshot was applied.
+    pub fn last_update_time(&self) -> DateTime<Utc> {
+        self.last_update_time
+    }
+
+    /// Whether [`Self::last_update_time`] is our clock's, a snapshot having been applied since
+    /// the last depth update
+    pub fn is_stamped_locally(&self) -> bool {
+        self.stamped_locally
+    }
+
     /// Vbid−Vask/Vbid+Vask
     /// Positive values indicate a buy imbalance, while negative values indicate a sell imbalance.
+    ///
+    /// Our own resting orders (see [`Self::set_own_bids`]) are excluded from all imbalance metrics.
     pub fn imbalance(&self) -> Option<Decimal> {
-        let top_bid_volume = self.bids.last_key_value()?.1;
+        let (_, top_bid_volume) = self.external_bids().next()?;
         let top_ask_volume = self.asks.first_key_value()?.1;
 
-        Some((top_bid_volume - top_ask_volume) / (top_bid_volume + top_ask_volume))
+        (top_bid_volume - top_ask_volume).checked_div(top_bid_volume.checked_add(*top_ask_volume)?)
+    }
+
+    /// Top of book imbalance with the sign flipped, positive when asks outweigh bids.
+    ///
+    /// Reads more naturally than a negative [`Self::imbalance`] in strategies that act on
+    /// selling, e.g. stink bids getting more aggressive as sell pressure builds.
+    pub fn sell_pressure(&self) -> Option<Decimal> {
+        self.imbalance().map(|imbalance| -imbalance)
+    }
+
+    /// Registers our own resting bids so they can be excluded from the imbalance metrics.
+    ///
+    /// Replaces any previously registered orders. Only needed when our orders are actually
+    /// resting on the exchange and therefore show up in the depth stream.
+    pub fn set_own_bids(&mut self, orders: impl IntoIterator<Item = (Price, Size)>) {
+        self.own_bids.clear();
+        for (price, size) in orders {
+            *self.own_bids.entry(price).or_default() += size;
+        }
+        if self.liquidity_ok() {
+            self.imbalance = self.imbalance();
+            self.weighted_imbalance = self.default_weighted_imbalance();
+            self.apply_ticker_top();
+        }
+    }
+
+    fn default_weighted_imbalance(&self) -> Option<Decimal> {
+        self.weighted_relative_imbalance(WEIGHTED_IMBALANCE_DEPTH, DepthWeighting::default())
+    }
+
+    /// Whether `delta`, already applied, changed or removed a level within the top
+    /// [`WEIGHTED_IMBALANCE_DEPTH`] of its side. A removed level was within them when it's at
+    /// least as good as the deepest level they now reach.
+    fn reaches_weighted_depth(&self, delta: &BookDelta) -> bool {
+        let deepest_bid = self
+            .external_bids()
+            .nth(WEIGHTED_IMBALANCE_DEPTH - 1)
+            .map(|(price, _)| price);
+        let deepest_ask = self.asks.keys().nth(WEIGHTED_IMBALANCE_DEPTH - 1).copied();
+        let bid_reached = |price: &Price| deepest_bid.is_none_or(|deepest| *price >= deepest);
+        let ask_reached = |price: &Price| deepest_ask.is_none_or(|deepest| *price <= deepest);
+        delta
+            .changed_bids
+            .iter()
+            .any(|(price, _)| bid_reached(price))
+            || delta.removed_bids.iter().any(bid_reached)
+            || delta
+                .changed_asks
+                .iter()
+                .any(|(price, _)| ask_reached(price))
+            || delta.removed_asks.iter().any(ask_reached)
+    }
+
+    /// Bid levels net of our own orders, best (highest) first. Levels consisting only of our
+    /// own orders are skipped.
+    pub fn external_bids(&self) -> impl Iterator<Item = (Price, Size)> {
+        self.bids.iter().rev().filter_map(|(&price, &size)| {
+            let own = self.own_bids.get(&price).copied().unwrap_or_default();
+            let external = size - own;
+            (external > Decimal::ZERO).then_some((price, external))
+        })
+    }
+
+    /// Bid levels with prices in `low..=high`, best (highest) first.
+    pub fn bids_in_range(
+        &self,
+        low: Price,
+        high: Price,
+    ) -> impl DoubleEndedIterator<Item = (&Price, &Size)> {
+        (low <= high)
+            .then(|| self.bids.range(low..=high))
+            .into_iter()
+            .flatten()
+            .rev()
+    }
+
+    /// Ask levels with prices in `low..=high`, best (lowest) first.
+    pub fn asks_in_range(
+        &self,
+        low: Price,
+        high: Price,
+    ) -> impl DoubleEndedIterator<Item = (&Price, &Size)> {
+        (low <= high)
+            .then(|| self.asks.range(low..=high))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Total size resting between the best price on `side` and `price` inclusive.
+    ///
+    /// For `Buy` this walks the bids down from the best bid, for `Sell` the asks up from
+    /// the best ask. Returns zero if `price` is on the wrong side of the touch, `None` when
+    /// the total overflows.
+    pub fn cumulative_size_to_price(&self, side: OrderSide, price: Price) -> Option<Size> {
+        let size = |(_, &size): (&Price, &Size)| size;
+        match side {
+            OrderSide::Buy => self.best_bid().map_or(Some(Size::ZERO), |best_bid| {
+                Self::checked_sum(self.bids_in_range(price, best_bid).map(size))
+            }),
+            OrderSide::Sell => self.best_ask().map_or(Some(Size::ZERO), |best_ask| {
+                Self::checked_sum(self.asks_in_range(best_ask, price).map(size))
+            }),
+        }
+    }
+
+    /// First level that differs from `reference`, comparing as many levels per side as the
+    /// reference holds, since a REST snapshot is usually cut off at its depth limit.
+    ///
+    /// Prices must match exactly, sizes within `tolerance`.
+    pub fn divergence_from(
+        &self,
+        reference: &DepthSnapshot,
+        tolerance: Size,
+    ) -> Option<LevelDivergence> {
+        // Best first on both sides, whatever order the snapshot lists them in
+        let by_price = |levels: &[OfferData]| {
+            levels
+                .iter()
+                .map(|level| (level.price, level.size))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let sides = [
+            (
+                OrderSide::Buy,
+                by_price(&reference.bids)
+                    .into_iter()
+                    .rev()
+                    .collect::<Vec<_>>(),
+                self.bids
+                    .iter()
+                    .rev()
+                    .map(|(&p, &s)| (p, s))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                OrderSide::Sell,
+                by_price(&reference.asks).into_iter().collect(),
+                self.asks.iter().map(|(&p, &s)| (p, s)).collect(),
+            ),
+        ];
+        for (side, expected_levels, actual_levels) in sides {
+            for (level, &expected) in expected_levels.iter().enumerate() {
+                let actual = actual_levels.get(level).copied();
+                let matches = actual.is_some_and(|(price, size)| {
+                    price == expected.0 && (size - expected.1).abs() <= tolerance
+                });
+                if !matches {
+                    return Some(LevelDivergence {
+                        side,
+                        level,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Panics with the first divergent level unless the book matches `reference`, see
+    /// [`Self::divergence_from`]. For replaying recorded updates in regression tests.
+    #[track_caller]
+    pub fn assert_matches(&self, reference: &DepthSnapshot, tolerance: Size) {
+        if let Some(divergence) = self.divergence_from(reference, tolerance) {
+            panic!(
+                "Order book doesn't match snapshot {}: {}",
+                reference.last_update_id, divergence
+            );
+        }
+    }
+
+    /// Average price a market order of `size` on `side` would fill at, sweeping the book.
+    ///
+    /// A `Buy` takes the asks up from the best ask and a `Sell` the bids down from the best bid.
+    /// `None` for a non-positive size or when the side doesn't hold `size` in total.
+    pub fn vwap_for_size(&self, side: OrderSide, size: Size) -> Option<Price> {
+        if size <= Size::ZERO {
+            return None;
+        }
+        let levels: Box<dyn Iterator<Item = (&Price, &Size)>> = match side {
+            OrderSide::Buy => Box::new(self.asks.iter()),
+            OrderSide::Sell => Box::new(self.bids.iter().rev()),
+        };
+        let mut remaining = size;
+        let taken = levels.map_while(|(&price, &level_size)| {
+            let taken = remaining.min(level_size);
+            remaining -= taken;
+            (!taken.is_zero()).then_some((price, taken))
+        });
+        let vwap = Self::checked_vwap(taken)?;
+        remaining.is_zero().then_some(vwap)
+    }
+
+    /// Spread a taker pays to buy and sell `size`, the gap between the two sides' VWAPs.
+    ///
+    /// Unlike the quoted spread this grows with the size traded through thin levels.
+    pub fn effective_spread(&self, size: Size) -> Option<Decimal> {
+        Some(
+            self.vwap_for_size(OrderSide::Buy, size)?
+                - self.vwap_for_size(OrderSide::Sell, size)?,
+        )
+    }
+
+    /// [`Self::effective_spread`] as a fraction of the mid price
+    pub fn relative_effective_spread(&self, size: Size) -> Option<Decimal> {
+        self.effective_spread(size)?.checked_div(self.mid_price()?)
+    }
+
+    /// Cumulative size from the best price outward, one point per level.
+    ///
+    /// `Buy` walks the bids down from the best bid, `Sell` the asks up from the best ask.
+    pub fn depth_curve(&self, side: OrderSide) -> Vec<(Price, Size)> {
+        match side {
+            OrderSide::Buy => Self::cumulative_levels(self.bids.iter().rev(), self.bids.len()),
+            OrderSide::Sell => Self::cumulative_levels(self.asks.iter(), self.asks.len()),
+        }
+    }
+
+    /// Both sides' depth curves, ready to plot
+    pub fn depth_chart_data(&self) -> DepthChart {
+        DepthChart {
+            bids: self.depth_curve(OrderSide::Buy),
+            asks: self.depth_curve(OrderSide::Sell),
+        }
+    }
+
+    fn cumulative_levels<'a>(
+        levels: impl Iterator<Item = (&'a Price, &'a Size)>,
+        len: usize,
+    ) -> Vec<(Price, Size)> {
+        let mut curve = Vec::with_capacity(len);
+        let mut cumulative = Size::ZERO;
+        for (&price, &size) in levels {
+            cumulative += size;
+            curve.push((price, cumulative));
+        }
+        curve
+    }
+
+    /// Imbalance over all levels within `bps` basis points of the mid price.
+    ///
+    /// Unlike the level count based methods this adapts to the tick density of the book.
+    pub fn imbalance_within_bps(&self, bps: Decimal) -> Option<Decimal> {
+        let mid_price = self.mid_price()?;
+        let band = mid_price * bps / Decimal::from(10_000);
+
+        let bids = Self::checked_sum(
+            self.bids_in_range(mid_price - band, mid_price)
+                .map(|(_, &size)| size),
+        )?;
+        let asks = Self::checked_sum(
+            self.asks_in_range(mid_price, mid_price + band)
+                .map(|(_, &size)| size),
+        )?;
+
+        (bids - asks).checked_div(bids.checked_add(asks)?)
+    }
+
+    /// How favourable the book is for making markets, from 0 to 1.
+    ///
+    /// A weighted mean of the spread, the size within `depth_band_bps` of the mid and how
+    /// little `recent` trades moved over `stability_window`, see [`BookQualityWeights`].
+    /// `None` without a two sided book or any recent trades.
+    pub fn quality_score(&self, recent: &RecentTrades) -> Option<Decimal> {
+        let weights = &self.quality_weights;
+        let mid_price = self.mid_price()?;
+        let spread_bps = self.relative_spread_bps()?;
+        let movement =
+            recent.price_movement_over(TimeDelta::from_std(weights.stability_window).ok()?)?;
+
+        // Linear between the target and the max, flat outside them
+        let spread_score = ((weights.max_spread_bps - spread_bps)
+            .checked_div(weights.max_spread_bps - weights.target_spread_bps)?)
+        .clamp(Decimal::ZERO, Decimal::ONE);
+
+        let band = mid_price * weights.depth_band_bps / Decimal::from(10_000);
+        let depth = Self::checked_sum(
+            self.bids_in_range(mid_price - band, mid_price)
+                .chain(self.asks_in_range(mid_price, mid_price + band))
+                .map(|(_, &size)| size),
+        )?;
+        let depth_score = depth.checked_div(weights.target_depth)?.min(Decimal::ONE);
+
+        let move_bps = movement.pct.abs() * Decimal::ONE_HUNDRED;
+        let stability_score = Decimal::ONE
+            - move_bps
+                .checked_div(weights.max_move_bps)?
+                .min(Decimal::ONE);
+
+        (weights.spread * spread_score
+            + weights.depth * depth_score
+            + weights.stability * stability_score)
+            .checked_div(weights.spread + weights.depth + weights.stability)
     }
 
     pub fn imbalance_depth(&self, depth: impl Into<usize>) -> Option<Decimal> {
         let depth = depth.into();
+        // A one sided book has no imbalance, however lopsided the sums look
+        if self.external_bids().next().is_none() || self.asks.is_empty() {
+            return None;
+        }
 
-        let bids = self.bids.values().rev().take(depth).sum::<Decimal>();
-
-        let asks = self.asks.values().take(depth).sum::<Decimal>();
+        let bids = Self::checked_sum(self.external_bids().take(depth).map(|(_, size)| size))?;
+        let asks = Self::checked_sum(self.asks.values().take(depth).copied())?;
 
-        Some((bids - asks) / (bids + asks))
+        (bids - asks).checked_div(bids.checked_add(asks)?)
     }
     /// Calculates the weighted relative imbalance over the top `depth` levels of the order book.
     ///
-    /// Both buy and sell volumes are weighted so that orders nearer the top have a larger impact.
+    /// Both buy and sell volumes are weighted by `weighting` so that orders nearer the top have
+    /// a larger impact. [`DepthWeighting::Distance`] needs a mid price, the others don't.
     ///
     /// Returns a value in the range [-1, 1]. Positive values indicate a buy imbalance,
     /// while negative values indicate a sell imbalance.
-    pub fn weighted_relative_imbalance(&self, depth: impl Into<usize>) -> Option<Decimal> {
+    pub fn weighted_relative_imbalance(
+        &self,
+        depth: impl Into<usize>,
+        weighting: DepthWeighting,
+    ) -> Option<Decimal> {
         let depth: usize = depth.into();
-        if depth == 0 {
+        if depth == 0 || self.external_bids().next().is_none() || self.asks.is_empty() {
             return None;
         }
+        let mid_price = match weighting {
+            DepthWeighting::Distance { .. } => Some(self.mid_price()?),
+            _ => None,
+        };
+        let distance_bps = |price: Decimal| {
+            mid_price
+                .and_then(|mid| ((price - mid).abs() * Decimal::from(10_000)).checked_div(mid))
+                .unwrap_or_default()
+        };
 
         let mut weighted_bid = Decimal::ZERO;
         let mut weighted_ask = Decimal::ZERO;
 
         // For bids, iterate from best (last) to deeper levels.
-        for (i, volume) in self.bids.values().rev().take(depth).enumerate() {
-            // Example weighting: orders closer to the top (i==0) get weight 1,
-            // then weight decays as 1/(i+1)
-            let weight = Decimal::ONE / Decimal::from((i as u32) + 1);
-            weighted_bid += volume * weight;
+        for (i, (price, volume)) in self.external_bids().take(depth).enumerate() {
+            let weighted =
+                volume.checked_mul(weighting.weight(i, depth, distance_bps(price))?)?;
+            weighted_bid = weighted_bid.checked_add(weighted)?;
         }
 
         // For asks, iterate from best (first) to deeper levels.
-        for (i, volume) in self.asks.values().take(depth).enumerate() {
-            let weight = Decimal::ONE / Decimal::from((i as u32) + 1);
-            weighted_ask += volume * weight;
+        for (i, (&price, volume)) in self.asks.iter().take(depth).enumerate() {
+            let weighted =
+                volume.checked_mul(weighting.weight(i, depth, distance_bps(price))?)?;
+            weighted_ask = weighted_ask.checked_add(weighted)?;
         }
 
-        let total = weighted_bid + weighted_ask;
-        if total == Decimal::ZERO {
-            None
-        } else {
-            Some((weighted_bid - weighted_ask) / total)
-        }
+        (weighted_bid - weighted_ask).checked_div(weighted_bid.checked_add(weighted_ask)?)
     }
 
+    /// How far each side's VWAP over the top `depth` levels sits from its best price, relative
+    /// to the price range of those levels, bid minus ask.
+    ///
+    /// A fraction in [-1, 1]. Positive when the bid liquidity sits deeper in the book than the ask
+    /// liquidity does.
     pub fn relative_book_imbalance(&self, depth: impl Into<usize>) -> Option<Decimal> {
         let depth = depth.into();
+        if depth == 0 {
+            return None;
+        }
         let best_bid = self.best_bid()?;
         let worst_bid = self.bids.iter().rev().nth(depth - 1).map(|(&k, _)| k)?;
         let best_ask = self.best_ask()?;
         let worst_ask = self.asks.iter().nth(depth - 1).map(|(&k, _)| k)?;
         let (bid_vwap, ask_vwap) = self.relative_imbalance_vwap(depth)?;
 
-        let bid_weighted = (best_bid - bid_vwap) / (best_bid - worst_bid);
-        let ask_weighted = (best_ask - ask_vwap) / (best_ask - worst_ask);
+        // A single level has no price range to sit in
+        let bid_weighted = (best_bid - bid_vwap).checked_div(best_bid - worst_bid)?;
+        let ask_weighted = (best_ask - ask_vwap).checked_div(best_ask - worst_ask)?;
 
-        Some((bid_weighted - ask_weighted) * Decimal::ONE_HUNDRED)
+        Some(bid_weighted - ask_weighted)
     }
 
     /// Calculates the relative imbalance of the mid price over the top `depth` levels of the order book.
     ///
     /// Both buy and sell volumes are weighted so that orders nearer the top have a larger impact.
+    /// Returned as a fraction of the mid price.
     pub fn relative_mid_price_imbalance(&self, depth: impl Into<usize>) -> Option<Decimal> {
         let depth = depth.into();
         let mid_price = self.mid_price()?;
         let (bid_imbalance, ask_imbalance) = self.relative_imbalance_vwap(depth)?;
 
-        let bid_weighted = (mid_price - bid_imbalance) / (mid_price);
-        let ask_weighted = (mid_price - ask_imbalance) / (mid_price);
+        let bid_weighted = (mid_price - bid_imbalance).checked_div(mid_price)?;
+        let ask_weighted = (mid_price - ask_imbalance).checked_div(mid_price)?;
+
+        Some(bid_weighted - ask_weighted)
+    }
 
-        Some((bid_weighted - ask_weighted) * Decimal::ONE_HUNDRED)
+    /// Average of the bid and ask VWAPs over the top `depth` levels of each side.
+    ///
+    /// A deeper fair value than [`Self::microprice`], less prone to flicker when a single thin
+    /// top level changes. `None` if either side has fewer than `depth` levels.
+    pub fn weighted_mid(&self, depth: usize) -> Option<Decimal> {
+        if depth == 0 {
+            return None;
+        }
+        let (bid_vwap, ask_vwap) = self.relative_imbalance_vwap(depth)?;
+        Some((bid_vwap + ask_vwap) / Decimal::TWO)
     }
 
     fn relative_imbalance_vwap(&self, depth: usize) -> Option<(Decimal, Decimal)> {
+        if depth == 0 {
+            return None;
+        }
         if depth > self.bids.len().min(self.asks.len()) {
             info!("Relative imbalance depth is less than the order book depth");
             return None;
         }
-        let bids_iter = self.bids.iter().rev().take(depth);
-        let bid_vwap = bids_iter
-            .clone()
-            .map(|(&price, &size)| price * size)
-            .sum::<Decimal>()
-            / bids_iter.map(|(_, &size)| size).sum::<Decimal>();
-
-        let asks_iter = self.asks.iter().take(depth);
-        let ask_vwap = asks_iter
-            .clone()
-            .map(|(&price, &size)| price * size)
-            .sum::<Decimal>()
-            / asks_iter.map(|(_, &size)| size).sum::<Decimal>();
+        let level = |(&price, &size): (&Price, &Size)| (price, size);
+        let bid_vwap = Self::checked_vwap(self.bids.iter().rev().take(depth).map(level))?;
+        let ask_vwap = Self::checked_vwap(self.asks.iter().take(depth).map(level))?;
 
         Some((bid_vwap, ask_vwap))
     }
 
+    /// Size weighted average price of `levels`, `None` without any size or when the notional
+    /// overflows, as it can on an erroneous or adversarial book
+    fn checked_vwap(mut levels: impl Iterator<Item = (Price, Size)>) -> Option<Decimal> {
+        let (notional, size) = levels.try_fold(
+            (Decimal::ZERO, Decimal::ZERO),
+            |(notional, total), (price, size)| {
+                Some((
+                    notional.checked_add(price.checked_mul(size)?)?,
+                    total.checked_add(size)?,
+                ))
+            },
+        )?;
+        notional.checked_div(size)
+    }
+
+    /// Total of `sizes`, `None` when it overflows
+    fn checked_sum(mut sizes: impl Iterator<Item = Size>) -> Option<Size> {
+        sizes.try_fold(Size::ZERO, Size::checked_add)
+    }
+
     fn best_bid(&self) -> Option<Decimal> {
         self.bids.last_key_value().map(|(&k, _)| k)
     }
@@ -0,0 +1,156 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use rust_decimal::{Decimal, MathematicalOps};
+use std::collections::VecDeque;
+use tracing::debug;
+
+use crate::recent_trades::Trade;
+
+const MILLIS_PER_YEAR: i64 = 365 * 24 * 60 * 60 * 1_000;
+const SECONDS_PER_YEAR: i64 = MILLIS_PER_YEAR / 1_000;
+const MINUTES_PER_YEAR: i64 = SECONDS_PER_YEAR / 60;
+const DAYS_PER_YEAR: i64 = 365;
+
+/// Scales the volatility of returns over one period to a year of them,
+/// `per_period_vol * √periods_per_year`.
+///
+/// Assumes returns are independent and identically distributed across periods, so variance
+/// adds up linearly with time, and that the market trades around the clock all year, as
+/// crypto does. Neither holds exactly, treat the result as a like for like comparison rather
+/// than a forecast. A negative `periods_per_year` counts as zero.
+pub fn annualize(per_period_vol: Decimal, periods_per_year: Decimal) -> Decimal {
+    per_period_vol
+        * periods_per_year
+            .max(Decimal::ZERO)
+            .sqrt()
+            .unwrap_or_default()
+}
+
+/// Annualizes a per-trade return volatility such as
+/// [`RecentTrades::volatility`](crate::recent_trades::RecentTrades::volatility), assuming the
+/// current `trades_per_second` holds all year. Trade rates swing by orders of magnitude over
+/// a day, so measure the rate over the same window as the volatility.
+pub fn annualize_per_trade(per_trade_vol: Decimal, trades_per_second: Decimal) -> Decimal {
+    annualize(
+        per_trade_vol,
+        trades_per_second * Decimal::from(SECONDS_PER_YEAR),
+    )
+}
+
+/// Annualizes the volatility of one minute returns
+pub fn annualize_per_minute(per_minute_vol: Decimal) -> Decimal {
+    annualize(per_minute_vol, Decimal::from(MINUTES_PER_YEAR))
+}
+
+/// Annualizes the volatility of daily returns, over 365 days rather than 252 trading days
+pub fn annualize_per_day(per_day_vol: Decimal) -> Decimal {
+    annualize(per_day_vol, Decimal::from(DAYS_PER_YEAR))
+}
+
+/// Realized volatility over fixed time buckets, e.g. one minute RV.
+///
+/// Each bucket's RV is the square root of the sum of squared trade-to-trade returns within
+/// it, the return into a bucket's first trade counting towards that bucket. Unlike the tick
+/// window volatility of [`RecentTrades`](crate::recent_trades::RecentTrades) this is tied to
+/// wall time, so it's comparable across sessions and venues.
+#[derive(Debug, Clone)]
+pub struct RealizedVolatility {
+    bucket: TimeDelta,
+    history_len: usize,
+    /// Start of the bucket being filled and its sum of squared returns
+    current: Option<(DateTime<Utc>, Decimal)>,
+    last_price: Option<Decimal>,
+    /// RV of completed buckets, most recent last
+    completed: VecDeque<Decimal>,
+}
+
+impl Default for RealizedVolatility {
+    fn default() -> Self {
+        Self::new(TimeDelta::minutes(1), 60)
+    }
+}
+
+impl RealizedVolatility {
+    /// Buckets of length `bucket`, keeping `history_len` completed buckets for the average
+    pub fn new(bucket: TimeDelta, history_len: usize) -> Self {
+        Self {
+            bucket,
+            history_len,
+            current: None,
+            last_price: None,
+            completed: VecDeque::with_capacity(history_len),
+        }
+    }
+
+    pub fn update(&mut self, trade: &Trade) {
+        let Some(bucket_start) = self.bucket_start(trade.trade_time()) else {
+            return;
+        };
+        match self.current {
+            Some((current_start, _)) if bucket_start < current_start => {
+                debug!(
+                    "Ignoring trade at {} from before the current bucket",
+                    trade.trade_time()
+                );
+                return;
+            }
+            Some((current_start, sum_squares)) if bucket_start > current_start => {
+                self.complete(sum_squares);
+                // Buckets without trades saw no price change
+                let gap = (bucket_start - current_start).num_milliseconds()
+                    / self.bucket.num_milliseconds()
+                    - 1;
+                for _ in 0..gap.min(self.history_len as i64) {
+                    self.complete(Decimal::ZERO);
+                }
+                self.current = Some((bucket_start, Decimal::ZERO));
+            }
+            Some(_) => {}
+            None => self.current = Some((bucket_start, Decimal::ZERO)),
+        }
+
+        if let Some(last_price) = self.last_price
+            && let Some(ret) = (trade.price - last_price).checked_div(last_price)
+            && let Some((_, sum_squares)) = self.current.as_mut()
+        {
+            *sum_squares += ret * ret;
+        }
+        self.last_price = Some(trade.price);
+    }
+
+    /// RV of the most recent completed bucket
+    pub fn latest(&self) -> Option<Decimal> {
+        self.completed.back().copied()
+    }
+
+    /// Mean RV over the completed buckets held
+    pub fn rolling_average(&self) -> Option<Decimal> {
+        if self.completed.is_empty() {
+            return None;
+        }
+        Some(self.completed.iter().sum::<Decimal>() / Decimal::from(self.completed.len()))
+    }
+
+    /// [`Self::latest`] scaled to a year of buckets, trading around the clock
+    pub fn annualized(&self) -> Option<Decimal> {
+        let buckets_per_year = Decimal::from(MILLIS_PER_YEAR / self.bucket.num_milliseconds());
+        Some(annualize(self.latest()?, buckets_per_year))
+    }
+
+    fn bucket_start(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let bucket_ms = self.bucket.num_milliseconds();
+        if bucket_ms <= 0 {
+            return None;
+        }
+        let millis = time.timestamp_millis();
+        DateTime::from_timestamp_millis(millis - millis.rem_euclid(bucket_ms))
+    }
+
+    fn complete(&mut self, sum_squares: Decimal) {
+        if self.completed.len() >= self.history_len {
+            self.completed.pop_front();
+        }
+        if let Some(rv) = sum_squares.sqrt() {
+            self.completed.push_back(rv);
+        }
+    }
+}
@@ -1,17 +1,54 @@
 use std::collections::VecDeque;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use rust_decimal::{Decimal, MathematicalOps};
 use rust_decimal_macros::dec;
+use tracing::debug;
 
-use crate::binance::data::{AggregateTrade, TradeEventData};
+use crate::{
+    binance::data::{AggregateTrade, TradeEventData},
+    market_maker::OrderSide,
+    realized_volatility::annualize_per_trade,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RecentTrades {
     // Trades & returns
     trades: VecDeque<(Trade, Decimal)>,
     window_size: usize,
+    /// Standard deviation of trade-to-trade simple returns over the most recent trades.
+    ///
+    /// This is in per-trade return space: a dimensionless fraction (0.0001 = 1bp) per trade,
+    /// not a percentage, not annualised and not in price units. Multiply by a price to get
+    /// an approximate per-trade price move.
     pub volatility: Option<Decimal>,
+    /// Resizes the window with the trade rate, see [`RecentTrades::with_adaptive_window`]
+    adaptive_window: Option<AdaptiveWindow>,
+    /// How much each trade's return counts towards the volatility
+    weighting: VolatilityWeighting,
+    /// Highest exchange trade id taken in, anything at or below it is a duplicate
+    last_trade_id: Option<u64>,
+}
+
+/// How much each trade's return counts towards [`RecentTrades::volatility`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VolatilityWeighting {
+    /// Every print counts the same
+    #[default]
+    Unweighted,
+    /// Prints count by the number of exchange trades they aggregate
+    NumTrades,
+    /// Prints count by traded quantity
+    Quantity,
+}
+
+/// Sizes the trade window to cover a roughly constant stretch of time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveWindow {
+    /// Time the window should span at the current trade rate
+    pub horizon: TimeDelta,
+    pub min_trades: usize,
+    pub max_trades: usize,
 }
 
 impl Default for RecentTrades {
@@ -26,17 +63,145 @@ impl RecentTrades {
             trades: VecDeque::with_capacity(window_size),
             window_size,
             volatility: None,
+            adaptive_window: None,
+            weighting: VolatilityWeighting::default(),
+            last_trade_id: None,
         }
     }
 
-    pub fn update(&mut self, trade: impl Into<Trade>) {
+    /// Weights each return by the trade that ended it, so a 500 trade aggregate print says
+    /// more about volatility than a single trade
+    pub fn with_weighting(mut self, weighting: VolatilityWeighting) -> Self {
+        self.weighting = weighting;
+        self.volatility = self.calculate_volatility();
+        self
+    }
+
+    /// Resizes the window after every trade to hold about `horizon` worth of trades at the
+    /// current [`Self::trades_per_second`], within the min and max trade counts.
+    ///
+    /// The window starts at the max. Busy trading fits more trades into the horizon and quiet
+    /// trading fewer, so the volatility keeps the same time meaning across regimes. A growing
+    /// window fills up with new trades, it can't recover ones already dropped.
+    pub fn with_adaptive_window(mut self, adaptive_window: AdaptiveWindow) -> Self {
+        let min_trades = adaptive_window.min_trades.max(2);
+        let adaptive_window = AdaptiveWindow {
+            min_trades,
+            max_trades: adaptive_window.max_trades.max(min_trades),
+            ..adaptive_window
+        };
+        self.resize_window(adaptive_window.max_trades);
+        self.adaptive_window = Some(adaptive_window);
+        self
+    }
+
+    /// Drops all trade history, e.g. after a regime change.
+    ///
+    /// Trade ids already seen are still remembered, so replays after a reconnect stay ignored.
+    pub fn reset(&mut self) {
+        self.trades.clear();
+        self.volatility = None;
+    }
+
+    /// Changes the window length, keeping the most recent trades when shrinking
+    pub fn resize_window(&mut self, new_size: usize) {
+        self.trades.truncate(new_size);
+        self.window_size = new_size;
+        self.volatility = self.calculate_volatility();
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Prints per second between the oldest and latest trade held, `None` until they're apart
+    pub fn trades_per_second(&self) -> Option<Decimal> {
+        let (latest, _) = self.trades.front()?;
+        let (earliest, _) = self.trades.back()?;
+        let span_ms = (latest.trade_time - earliest.trade_time).num_milliseconds();
+        if span_ms <= 0 {
+            return None;
+        }
+        Some(Decimal::from(self.trades.len() - 1) * dec!(1000) / Decimal::from(span_ms))
+    }
+
+    /// [`Self::volatility`] annualized at the current trade rate, see
+    /// [`annualize_per_trade`](crate::realized_volatility::annualize_per_trade)
+    pub fn annualized_volatility(&self) -> Option<Decimal> {
+        Some(annualize_per_trade(
+            self.volatility?,
+            self.trades_per_second()?,
+        ))
+    }
+
+    /// Adds a trade to the window.
+    ///
+    /// Trades older than the most recent one held are ignored rather than reordered, since
+    /// returns are computed against the front trade and a stale print would corrupt them.
+    /// So are trades whose ids were already seen, as redelivered after a reconnect or by
+    /// overlapping streams, and only for those it returns false.
+    pub fn update(&mut self, trade: impl Into<Trade>) -> bool {
         let trade = trade.into();
+        if self
+            .last_trade_id
+            .is_some_and(|last| trade.last_trade_id <= last)
+        {
+            debug!(
+                "Ignoring duplicate trade {} (last seen: {:?})",
+                trade.last_trade_id, self.last_trade_id
+            );
+            return false;
+        }
+        if let Some((latest, _)) = self.trades.front()
+            && trade.trade_time < latest.trade_time
+        {
+            debug!(
+                "Ignoring out of order trade at {} (latest: {})",
+                trade.trade_time, latest.trade_time
+            );
+            return true;
+        }
+        self.last_trade_id = Some(trade.last_trade_id);
         let returns = self.calculate_returns(&trade);
-        if self.trades.len() == self.window_size {
+        if self.trades.len() >= self.window_size {
             self.trades.pop_back();
         }
         self.trades.push_front((trade, returns));
+        self.adapt_window();
         self.volatility = self.calculate_volatility();
+        true
+    }
+
+    /// Moves the window size towards the adaptive horizon at the current trade rate
+    fn adapt_window(&mut self) {
+        let Some(adaptive_window) = self.adaptive_window else {
+            return;
+        };
+        let Some(trades_per_second) = self.trades_per_second() else {
+            return;
+        };
+        let horizon_secs = Decimal::from(adaptive_window.horizon.num_milliseconds()) / dec!(1000);
+        let target: usize = (trades_per_second * horizon_secs)
+            .round()
+            .try_into()
+            .unwrap_or(adaptive_window.max_trades);
+        let target = target.clamp(adaptive_window.min_trades, adaptive_window.max_trades);
+        if target != self.window_size {
+            debug!(
+                "Trade window {} -> {} at {} trades/s",
+                self.window_size, target, trades_per_second
+            );
+            self.trades.truncate(target);
+            self.window_size = target;
+        }
     }
 
     pub fn update_many(&mut self, trades: impl Iterator<Item = impl Into<Trade>>) {
@@ -55,6 +220,14 @@ impl RecentTrades {
         }
     }
 
+    fn weight(&self, trade: &Trade) -> Decimal {
+        match self.weighting {
+            VolatilityWeighting::Unweighted => Decimal::ONE,
+            VolatilityWeighting::NumTrades => Decimal::from(trade.num_trades),
+            VolatilityWeighting::Quantity => trade.quantity,
+        }
+    }
+
     fn calculate_volatility(&self) -> Option<Decimal> {
         let total_trades = self.trades.len();
         if total_trades < 2 {
@@ -62,22 +235,41 @@ impl RecentTrades {
         }
         let total_trades = Decimal::from(total_trades);
 
-        let sum = self.trades.iter().map(|(_, ret)| ret).sum::<Decimal>();
-        let mean = sum / total_trades;
+        // Extreme prices can make for returns whose sums or squares overflow, which leaves
+        // the volatility unknown rather than panicking
+        let (weighted_sum, total_weight) = self.trades.iter().try_fold(
+            (Decimal::ZERO, Decimal::ZERO),
+            |(sum, total), (trade, ret)| {
+                let weight = self.weight(trade);
+                Some((
+                    sum.checked_add(weight.checked_mul(*ret)?)?,
+                    total.checked_add(weight)?,
+                ))
+            },
+        )?;
+        let mean = weighted_sum.checked_div(total_weight)?;
 
         // Use only the most recent subset (e.g., 30%) of trades for variance
         let window_size = Decimal::from(self.window_size);
         let recent_window = (window_size * dec!(0.3)).ceil();
         let recent_count = total_trades.min(recent_window);
 
-        let variance = self
+        let (weighted_squares, recent_weight) = self
             .trades
             .iter()
             .take(recent_count.try_into().unwrap_or(0))
-            .map(|(_, ret)| (*ret - mean).powi(2))
-            .sum::<Decimal>()
-            / recent_count;
-        variance.sqrt()
+            .try_fold(
+                (Decimal::ZERO, Decimal::ZERO),
+                |(sum, total), (trade, ret)| {
+                    let weight = self.weight(trade);
+                    let square = ret.checked_sub(mean)?.checked_powi(2)?;
+                    Some((
+                        sum.checked_add(weight.checked_mul(square)?)?,
+                        total.checked_add(weight)?,
+                    ))
+                },
+            )?;
+        weighted_squares.checked_div(recent_weight)?.sqrt()
     }
     fn calculate_ewma_volatility(&self, lambda: Decimal) -> Option<Decimal> {
         if self.trades.is_empty() {
@@ -87,37 +279,136 @@ impl RecentTrades {
         let mut ewma_var = Decimal::ZERO;
         let alpha = Decimal::ONE - lambda;
 
-        for (i, (_, returns)) in self.trades.iter().enumerate() {
+        for (i, (trade, returns)) in self.trades.iter().enumerate() {
             if i == 0 {
-                ewma_var = returns.powi(2);
+                ewma_var = returns.checked_powi(2)?;
+            } else if self.weighting == VolatilityWeighting::Unweighted {
+                ewma_var = lambda * ewma_var + alpha * returns.checked_powi(2)?;
             } else {
-                ewma_var = lambda * ewma_var + alpha * returns.powi(2);
+                // A weight of n decays the estimate as if the return was seen n times
+                let decay = lambda.powd(self.weight(trade));
+                ewma_var = decay * ewma_var + (Decimal::ONE - decay) * returns.checked_powi(2)?;
             }
         }
 
         ewma_var.sqrt()
     }
 
-    pub fn price_movement(&self, over_recent_trades: impl Into<usize>) -> Option<Decimal> {
-        let over_recent_trades = over_recent_trades.into();
+    /// (Vbuy−Vsell)/(Vbuy+Vsell) of taker volume over the window.
+    /// Positive values indicate aggressive buying, negative values aggressive selling.
+    pub fn trade_flow_imbalance(&self) -> Option<Decimal> {
+        let (buy_volume, sell_volume) =
+            self.trades
+                .iter()
+                .fold((Decimal::ZERO, Decimal::ZERO), |(buy, sell), (trade, _)| {
+                    if trade.buyer_market_maker {
+                        (buy, sell + trade.quantity)
+                    } else {
+                        (buy + trade.quantity, sell)
+                    }
+                });
+        (buy_volume - sell_volume).checked_div(buy_volume + sell_volume)
+    }
+
+    /// Price of the latest trade, `None` before any
+    pub fn last_price(&self) -> Option<Decimal> {
+        self.trades.front().map(|(trade, _)| trade.price)
+    }
 
-        if self.trades.len() < over_recent_trades {
+    /// Price movement from the trade `over_recent_trades` back to the latest one.
+    ///
+    /// If fewer trades are held, the movement is measured from the oldest available trade.
+    pub fn price_movement(&self, over_recent_trades: impl Into<usize>) -> Option<PriceMovement> {
+        let over_recent_trades = over_recent_trades.into().min(self.trades.len());
+        if over_recent_trades == 0 {
             return None;
         }
 
         let latest_price = self.trades.front()?.0.price;
-        let earlier_trade = self.trades.get(over_recent_trades - 1)?.0.price;
-        (latest_price - earlier_trade).checked_div(earlier_trade)
+        let earlier_price = self.trades.get(over_recent_trades - 1)?.0.price;
+        PriceMovement::between(earlier_price, latest_price)
+    }
+
+    /// Price movement over the trades within `window` of the latest trade time.
+    pub fn price_movement_over(&self, window: TimeDelta) -> Option<PriceMovement> {
+        let (latest, _) = self.trades.front()?;
+        let cutoff = latest.trade_time - window;
+        let (earliest, _) = self
+            .trades
+            .iter()
+            .take_while(|(trade, _)| trade.trade_time >= cutoff)
+            .last()?;
+        PriceMovement::between(earliest.price, latest.price)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Change in price between two trades
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceMovement {
+    /// Price difference, latest minus earlier
+    pub absolute: Decimal,
+    /// Price difference as a percentage of the earlier price
+    pub pct: Decimal,
+    /// `Buy` when the price rose or stayed flat, `Sell` when it fell
+    pub direction: OrderSide,
+}
+
+impl PriceMovement {
+    fn between(earlier_price: Decimal, latest_price: Decimal) -> Option<Self> {
+        let absolute = latest_price - earlier_price;
+        let pct = absolute.checked_div(earlier_price)? * Decimal::ONE_HUNDRED;
+        let direction = if absolute.is_sign_negative() {
+            OrderSide::Sell
+        } else {
+            OrderSide::Buy
+        };
+        Some(Self {
+            absolute,
+            pct,
+            direction,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Trade {
     pub price: Decimal,
     pub quantity: Decimal,
     trade_time: DateTime<Utc>,
     pub buyer_market_maker: bool,
     num_trades: u64,
+    /// Exchange id of the last trade this print covers
+    last_trade_id: u64,
+}
+
+impl Trade {
+    pub fn trade_time(&self) -> DateTime<Utc> {
+        self.trade_time
+    }
+
+    /// Number of exchange trades this print represents
+    pub fn num_trades(&self) -> u64 {
+        self.num_trades
+    }
+
+    /// Exchange id of the last trade this print covers
+    pub fn last_trade_id(&self) -> u64 {
+        self.last_trade_id
+    }
+
+    /// Whether `other` would have been part of the same aggregate trade: same taker side,
+    /// price and time.
+    pub(crate) fn aggregates_with(&self, other: &Trade) -> bool {
+        self.price == other.price
+            && self.buyer_market_maker == other.buyer_market_maker
+            && self.trade_time == other.trade_time
+    }
+
+    pub(crate) fn aggregate(&mut self, other: &Trade) {
+        self.quantity += other.quantity;
+        self.num_trades += other.num_trades;
+        self.last_trade_id = self.last_trade_id.max(other.last_trade_id);
+    }
 }
 
 impl From<TradeEventData> for Trade {
@@ -128,6 +419,7 @@ impl From<TradeEventData> for Trade {
             trade_time: event.trade_time,
             buyer_market_maker: event.buyer_market_maker,
             num_trades: 1,
+            last_trade_id: event.trade_id,
         }
     }
 }
@@ -140,6 +432,7 @@ impl From<AggregateTrade> for Trade {
             trade_time: event.trade_time,
             buyer_market_maker: event.buyer_market_maker,
             num_trades: event.last_trade_id - event.first_trade_id + 1,
+            last_trade_id: event.last_trade_id,
         }
     }
 }
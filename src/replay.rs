@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::debug;
+
+use crate::{binance::data::BinanceEvent, clock::MockClock};
+
+/// How fast recorded events are delivered during a replay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaySpeed {
+    /// Deliver every event immediately, for fast regression runs
+    #[default]
+    MaxSpeed,
+    /// Wait out the original gaps between events
+    Realtime,
+    /// Wait out the original gaps divided by this factor, `2.0` replays at twice the speed.
+    /// Non-positive factors behave like [`ReplaySpeed::MaxSpeed`].
+    Multiplier(f64),
+}
+
+/// Paces replayed events by their original `event_time` and moves a [`MockClock`] along with
+/// them.
+///
+/// The strategy should be given the pacer's clock, so time based logic (staleness, order
+/// TTLs, time windowed volatility) sees the recorded time whatever the replay speed. The
+/// speed only decides how long the pacer sleeps between events, which means a time correct
+/// strategy produces identical fills and PnL in max speed and realtime modes. Any difference
+/// between them points at logic reading the wall clock.
+#[derive(Debug, Clone)]
+pub struct ReplayPacer {
+    speed: ReplaySpeed,
+    clock: MockClock,
+    last_event_time: Option<DateTime<Utc>>,
+}
+
+impl ReplayPacer {
+    pub fn new(speed: ReplaySpeed, clock: MockClock) -> Self {
+        Self {
+            speed,
+            clock,
+            last_event_time: None,
+        }
+    }
+
+    /// The replay clock, shared with every clone
+    pub fn clock(&self) -> &MockClock {
+        &self.clock
+    }
+
+    /// How long to wait before delivering an event from `event_time`.
+    ///
+    /// Zero for the first event and for events older than the latest one delivered.
+    pub fn delay(&self, event_time: DateTime<Utc>) -> Duration {
+        let Some(gap) = self
+            .last_event_time
+            .and_then(|last| (event_time - last).to_std().ok())
+        else {
+            return Duration::ZERO;
+        };
+        match self.speed {
+            ReplaySpeed::MaxSpeed => Duration::ZERO,
+            ReplaySpeed::Realtime => gap,
+            ReplaySpeed::Multiplier(factor) if factor.is_finite() && factor > 0.0 => {
+                gap.div_f64(factor)
+            }
+            ReplaySpeed::Multiplier(_) => Duration::ZERO,
+        }
+    }
+
+    /// Waits until an event from `event_time` is due, then moves the clock to it.
+    ///
+    /// The clock never moves backwards, out of order events are delivered at the current time.
+    pub async fn pace(&mut self, event_time: DateTime<Utc>) {
+        let delay = self.delay(event_time);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        if self.last_event_time.is_some_and(|last| event_time < last) {
+            debug!(
+                "Replayed event from {} is older than {:?}, not moving the clock",
+                event_time, self.last_event_time
+            );
+            return;
+        }
+        self.clock.set(event_time);
+        self.last_event_time = Some(event_time);
+    }
+
+    /// [`Self::pace`] by the event's own timestamp, events without one are delivered at once
+    pub async fn pace_event(&mut self, event: &BinanceEvent) {
+        if let Some(event_time) = event.event_time() {
+            self.pace(event_time).await;
+        }
+    }
+}
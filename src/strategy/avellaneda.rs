@@ -0,0 +1,93 @@
+use rust_decimal::{Decimal, MathematicalOps};
+
+/// Parameters of the Avellaneda-Stoikov model, with a hard inventory flatten schedule
+/// towards the end of the horizon.
+///
+/// Time is in whatever unit `volatility` is quoted per square root of, e.g. a horizon of 1
+/// with a per session volatility.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvellanedaParams {
+    /// Risk aversion γ, higher skews harder against inventory and quotes wider
+    pub gamma: Decimal,
+    /// Decay κ of the order arrival intensity with distance from the mid, see
+    /// [`ArrivalIntensity`](crate::market_maker::ArrivalIntensity)
+    pub kappa: Decimal,
+    /// Length of the trading horizon `T`
+    pub horizon: Decimal,
+    /// Fraction of the horizon after which the flatten schedule starts, 1 disables it
+    pub flatten_after: Decimal,
+    /// Flatten skew at `T`, as a fraction of the half spread
+    pub max_flatten_skew: Decimal,
+}
+
+/// Bid and ask around the inventory adjusted reservation price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quotes {
+    pub reservation_price: Decimal,
+    pub bid: Decimal,
+    pub ask: Decimal,
+    /// How far the flatten schedule moved both quotes towards offloading the inventory
+    pub flatten_skew: Decimal,
+}
+
+impl AvellanedaParams {
+    /// Quotes for a `mid` price and `inventory` (positive when long) with `time_remaining`
+    /// until the end of the horizon.
+    ///
+    /// The reservation price is `mid - q·γ·σ²·(T - t)` and the spread
+    /// `γ·σ²·(T - t) + (2/γ)·ln(1 + γ/κ)`. Past `flatten_after`, the quote that would grow the
+    /// position moves away by the flatten skew, ramping linearly up to `max_flatten_skew` at
+    /// `T`, and the offloading quote moves in by as much, never past the reservation price.
+    ///
+    /// `None` for non-positive `gamma`, `kappa` or `horizon`.
+    pub fn quotes(
+        &self,
+        mid: Decimal,
+        inventory: Decimal,
+        volatility: Decimal,
+        time_remaining: Decimal,
+    ) -> Option<Quotes> {
+        if self.gamma <= Decimal::ZERO
+            || self.kappa <= Decimal::ZERO
+            || self.horizon <= Decimal::ZERO
+        {
+            return None;
+        }
+        let time_remaining = time_remaining.clamp(Decimal::ZERO, self.horizon);
+        let elapsed = Decimal::ONE - time_remaining / self.horizon;
+
+        let variance_term = self.gamma * volatility * volatility * time_remaining;
+        let reservation_price = mid - inventory * variance_term;
+        let half_spread = (variance_term
+            + Decimal::TWO / self.gamma * (Decimal::ONE + self.gamma / self.kappa).checked_ln()?)
+            / Decimal::TWO;
+
+        let flatten_skew = if inventory.is_zero() || self.flatten_after >= Decimal::ONE {
+            Decimal::ZERO
+        } else {
+            let progress = ((elapsed - self.flatten_after) / (Decimal::ONE - self.flatten_after))
+                .clamp(Decimal::ZERO, Decimal::ONE);
+            progress * self.max_flatten_skew * half_spread
+        };
+        let offload_skew = flatten_skew.min(half_spread);
+
+        let (bid, ask) = if inventory.is_sign_positive() {
+            // Long: buy less eagerly, sell more eagerly
+            (
+                reservation_price - half_spread - flatten_skew,
+                reservation_price + half_spread - offload_skew,
+            )
+        } else {
+            (
+                reservation_price - half_spread + offload_skew,
+                reservation_price + half_spread + flatten_skew,
+            )
+        };
+        Some(Quotes {
+            reservation_price,
+            bid,
+            ask,
+            flatten_skew,
+        })
+    }
+}
@@ -0,0 +1,3 @@
+//! Quoting models beyond the stink bid strategy of [`MarketMaker`](crate::market_maker::MarketMaker)
+
+pub mod avellaneda;
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// A validated exchange symbol such as `BTCUSDT`
+///
+/// Always uppercase ASCII alphanumerics, so a symbol parsed from config compares equal to the
+/// `s` field Binance puts on every event.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Symbol(String);
+
+/// Why a string isn't a valid [`Symbol`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolError {
+    Empty,
+    InvalidChar { symbol: String, invalid: char },
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolError::Empty => write!(f, "Symbol is empty"),
+            SymbolError::InvalidChar { symbol, invalid } => write!(
+                f,
+                "Symbol {symbol:?} contains {invalid:?}, only ASCII letters and digits are allowed"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+impl Symbol {
+    /// Validates `symbol`, trimming whitespace and uppercasing it
+    pub fn new(symbol: &str) -> Result<Self, SymbolError> {
+        let symbol = symbol.trim();
+        if symbol.is_empty() {
+            return Err(SymbolError::Empty);
+        }
+        if let Some(invalid) = symbol.chars().find(|c| !c.is_ascii_alphanumeric()) {
+            return Err(SymbolError::InvalidChar {
+                symbol: symbol.to_string(),
+                invalid,
+            });
+        }
+        Ok(Self(symbol.to_ascii_uppercase()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = SymbolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for Symbol {
+    type Error = SymbolError;
+
+    fn try_from(symbol: String) -> Result<Self, Self::Error> {
+        Self::new(&symbol)
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
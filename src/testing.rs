@@ -0,0 +1,95 @@
+//! Builders for the raw JSON Binance sends on combined streams, for parser and pipeline tests.
+//!
+//! Every message is wrapped as `{"stream": ..., "data": ...}` with the exchange's single letter
+//! field names, decimals as strings and all timestamps at [`FIXTURE_TIME_MS`].
+
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+
+/// Event and trade time of every fixture, in milliseconds since the epoch
+pub const FIXTURE_TIME_MS: i64 = 1_700_000_000_000;
+
+/// Wraps `data` as a message from the named combined stream
+pub fn wrapped(stream: &str, data: Value) -> String {
+    json!({ "stream": stream, "data": data }).to_string()
+}
+
+/// A `depthUpdate` diff covering update ids `first_update_id..=final_update_id`
+pub fn wrapped_depth(
+    symbol: &str,
+    bids: &[(Decimal, Decimal)],
+    asks: &[(Decimal, Decimal)],
+    first_update_id: u64,
+    final_update_id: u64,
+) -> String {
+    wrapped(
+        &format!("{}@depth@100ms", symbol.to_lowercase()),
+        json!({
+            "e": "depthUpdate",
+            "E": FIXTURE_TIME_MS,
+            "s": symbol.to_uppercase(),
+            "U": first_update_id,
+            "u": final_update_id,
+            "b": levels(bids),
+            "a": levels(asks),
+        }),
+    )
+}
+
+/// An `aggTrade` covering the single exchange trade `aggregate_trade_id`
+pub fn wrapped_agg_trade(
+    symbol: &str,
+    aggregate_trade_id: u64,
+    price: Decimal,
+    quantity: Decimal,
+    buyer_market_maker: bool,
+) -> String {
+    wrapped(
+        &format!("{}@aggTrade", symbol.to_lowercase()),
+        json!({
+            "e": "aggTrade",
+            "E": FIXTURE_TIME_MS,
+            "s": symbol.to_uppercase(),
+            "a": aggregate_trade_id,
+            "p": price.to_string(),
+            "q": quantity.to_string(),
+            "f": aggregate_trade_id,
+            "l": aggregate_trade_id,
+            "T": FIXTURE_TIME_MS,
+            "m": buyer_market_maker,
+            "M": true,
+        }),
+    )
+}
+
+/// A raw `trade`
+pub fn wrapped_trade(
+    symbol: &str,
+    trade_id: u64,
+    price: Decimal,
+    quantity: Decimal,
+    buyer_market_maker: bool,
+) -> String {
+    wrapped(
+        &format!("{}@trade", symbol.to_lowercase()),
+        json!({
+            "e": "trade",
+            "E": FIXTURE_TIME_MS,
+            "s": symbol.to_uppercase(),
+            "t": trade_id,
+            "p": price.to_string(),
+            "q": quantity.to_string(),
+            "T": FIXTURE_TIME_MS,
+            "m": buyer_market_maker,
+            "M": true,
+        }),
+    )
+}
+
+/// Levels as Binance sends them, `["price", "size"]` string pairs
+fn levels(levels: &[(Decimal, Decimal)]) -> Vec<[String; 2]> {
+    levels
+        .iter()
+        .map(|(price, size)| [price.to_string(), size.to_string()])
+        .collect()
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    binance::data::{AggregateTrade, TradeEventData},
+    recent_trades::Trade,
+};
+
+/// Which trade stream drives the strategy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSource {
+    /// Aggregate trades only, raw trades are ignored
+    #[default]
+    AggTrade,
+    /// Raw trades, aggregated locally, aggregate trades are ignored
+    Trade,
+    /// Whichever stream reports a trade id first, the other's copy is dropped
+    Both,
+}
+
+/// Merges the `aggTrade` and `trade` streams into a single feed without double counting.
+///
+/// Every exchange trade id is only ever let through once: aggregate trades cover the
+/// `first_trade_id..=last_trade_id` range and raw trades their own `trade_id`. An aggregate
+/// trade whose range the raw trades already reached into is dropped in favour of them. Raw
+/// trades are combined into aggregate-equivalent prints (same side, price and time) before
+/// being emitted, so call [`TradeFeed::flush`] on shutdown for the last one.
+#[derive(Debug, Clone, Default)]
+pub struct TradeFeed {
+    source: TradeSource,
+    last_trade_id: Option<u64>,
+    pending: Option<Trade>,
+}
+
+impl TradeFeed {
+    pub fn new(source: TradeSource) -> Self {
+        Self {
+            source,
+            last_trade_id: None,
+            pending: None,
+        }
+    }
+
+    /// Returns the trades to process for a new aggregate trade
+    pub fn on_agg_trade(&mut self, trade: AggregateTrade) -> Vec<Trade> {
+        if self.source == TradeSource::Trade {
+            return Vec::new();
+        }
+        if self.is_covered(trade.last_trade_id) {
+            debug!(
+                "Ignoring already seen aggregate trade {} [{}-{}]",
+                trade.aggregate_trade_id, trade.first_trade_id, trade.last_trade_id
+            );
+            return Vec::new();
+        }
+        // The raw stream got part way through this print first. Its per-trade sizes aren't
+        // known, so leave the rest of the range to the raw stream rather than count the
+        // overlap twice.
+        if self.is_covered(trade.first_trade_id) {
+            debug!(
+                "Leaving aggregate trade {} [{}-{}] to the raw trades, seen up to {:?}",
+                trade.aggregate_trade_id,
+                trade.first_trade_id,
+                trade.last_trade_id,
+                self.last_trade_id
+            );
+            return Vec::new();
+        }
+        self.last_trade_id = Some(trade.last_trade_id);
+
+        // Anything still being aggregated from raw trades happened before this print
+        let mut trades = self.flush().into_iter().collect::<Vec<_>>();
+        trades.push(trade.into());
+        trades
+    }
+
+    /// Returns any aggregate completed by a new raw trade
+    pub fn on_trade(&mut self, trade: TradeEventData) -> Option<Trade> {
+        if self.source == TradeSource::AggTrade {
+            return None;
+        }
+        if self.is_covered(trade.trade_id) {
+            debug!("Ignoring already seen trade {}", trade.trade_id);
+            return None;
+        }
+        self.last_trade_id = Some(trade.trade_id);
+
+        let trade = Trade::from(trade);
+        match &mut self.pending {
+            Some(pending) if pending.aggregates_with(&trade) => {
+                pending.aggregate(&trade);
+                None
+            }
+            _ => self.pending.replace(trade),
+        }
+    }
+
+    /// Emits the raw trade aggregate currently being built, if any
+    pub fn flush(&mut self) -> Option<Trade> {
+        self.pending.take()
+    }
+
+    fn is_covered(&self, trade_id: u64) -> bool {
+        self.last_trade_id.is_some_and(|last| trade_id <= last)
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{TimeDelta, Utc};
+use marketmakerlib::market_maker::ArrivalIntensity;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn assert_close(actual: Decimal, expected: Decimal) {
+    assert!(
+        (actual - expected).abs() < dec!(0.0001),
+        "{actual} is not close to {expected}"
+    );
+}
+
+#[test]
+fn fill_rate_halving_every_bucket_fits_exactly() {
+    let start = Utc::now();
+    let mut intensity = ArrivalIntensity::new(dec!(10), start);
+    for distance in [
+        dec!(1),
+        dec!(2),
+        dec!(5),
+        dec!(9),
+        dec!(12),
+        dec!(18),
+        dec!(25),
+    ] {
+        intensity.record_fill(distance);
+    }
+    assert_eq!(intensity.fill_count(), 7);
+
+    // 4, 2 and 1 fills at 5, 15 and 25 from the mid over 10 seconds
+    let estimate = intensity.estimate(start + TimeDelta::seconds(10)).unwrap();
+    assert_close(estimate.kappa, dec!(0.0693147));
+    assert_close(estimate.a, dec!(0.5656854));
+
+    // Twice the time at the same fills halves the rate, the decay is unchanged
+    let later = intensity.estimate(start + TimeDelta::seconds(20)).unwrap();
+    assert_close(later.kappa, estimate.kappa);
+    assert_close(later.a, estimate.a / Decimal::TWO);
+}
+
+#[test]
+fn no_estimate_until_fills_span_two_buckets() {
+    let start = Utc::now();
+    let mut intensity = ArrivalIntensity::new(dec!(10), start);
+    let now = start + TimeDelta::seconds(10);
+    assert_eq!(intensity.estimate(now), None);
+
+    intensity.record_fill(dec!(3));
+    intensity.record_fill(dec!(4));
+    assert_eq!(intensity.estimate(now), None);
+
+    intensity.record_fill(dec!(13));
+    assert!(intensity.estimate(now).is_some());
+}
@@ -0,0 +1,87 @@
+use marketmakerlib::strategy::avellaneda::{AvellanedaParams, Quotes};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn params() -> AvellanedaParams {
+    AvellanedaParams {
+        gamma: dec!(0.1),
+        kappa: dec!(1.5),
+        horizon: dec!(1),
+        flatten_after: dec!(0.25),
+        max_flatten_skew: dec!(1),
+    }
+}
+
+/// How far the centre of the quotes sits below the reservation price
+fn skew(quotes: &Quotes) -> Decimal {
+    quotes.reservation_price - (quotes.bid + quotes.ask) / Decimal::TWO
+}
+
+#[test]
+fn long_inventory_quotes_skew_harder_towards_the_end_of_the_horizon() {
+    let params = params();
+    let at = |time_remaining| {
+        params
+            .quotes(dec!(100), dec!(3), dec!(2), time_remaining)
+            .unwrap()
+    };
+    let start = at(dec!(1));
+    let middle = at(dec!(0.5));
+    let near_end = at(dec!(0.05));
+
+    // Reservation price below the mid while long, converging on it as T approaches
+    assert!(start.reservation_price < middle.reservation_price);
+    assert!(middle.reservation_price < near_end.reservation_price);
+    assert!(near_end.reservation_price < dec!(100));
+
+    // No flatten skew before a quarter of the horizon, then ramping up
+    assert_eq!(start.flatten_skew, Decimal::ZERO);
+    assert_eq!(skew(&start), Decimal::ZERO);
+    assert!(skew(&middle) > Decimal::ZERO);
+    assert!(skew(&near_end) > skew(&middle));
+
+    // The bid backs off while the ask leans in, never through the reservation price
+    for quotes in [start, middle, near_end] {
+        assert!(quotes.reservation_price - quotes.bid >= quotes.ask - quotes.reservation_price);
+        assert!(quotes.ask >= quotes.reservation_price);
+        assert!(quotes.bid < quotes.ask);
+    }
+}
+
+#[test]
+fn short_inventory_skews_the_other_way() {
+    let quotes = params()
+        .quotes(dec!(100), dec!(-3), dec!(2), dec!(0.05))
+        .unwrap();
+    assert!(quotes.reservation_price > dec!(100));
+    assert!(skew(&quotes) < Decimal::ZERO);
+    assert!(quotes.bid <= quotes.reservation_price);
+}
+
+#[test]
+fn flat_inventory_or_disabled_schedule_has_no_flatten_skew() {
+    let params = params();
+    let flat = params
+        .quotes(dec!(100), Decimal::ZERO, dec!(2), dec!(0.05))
+        .unwrap();
+    assert_eq!(flat.flatten_skew, Decimal::ZERO);
+    assert_eq!(flat.reservation_price, dec!(100));
+
+    let disabled = AvellanedaParams {
+        flatten_after: Decimal::ONE,
+        ..params
+    };
+    let quotes = disabled
+        .quotes(dec!(100), dec!(3), dec!(2), dec!(0.05))
+        .unwrap();
+    assert_eq!(skew(&quotes), Decimal::ZERO);
+}
+
+#[test]
+fn invalid_parameters_give_no_quotes() {
+    let params = AvellanedaParams {
+        horizon: Decimal::ZERO,
+        ..params()
+    };
+    assert_eq!(params.quotes(dec!(100), dec!(1), dec!(2), dec!(0)), None);
+}
@@ -0,0 +1,91 @@
+use marketmakerlib::binance::{
+    BinanceMessage, StreamError,
+    data::{AggregateTrade, BinanceEvent, DepthSnapshot, DepthUpdate, TradeEventData},
+};
+use rust_decimal_macros::dec;
+
+#[test]
+fn error_message_is_surfaced_as_stream_error() {
+    let data = r#"{"error":{"code":2,"msg":"Invalid request: unknown symbol"},"id":1}"#;
+    match BinanceMessage::from_str_into_market_data(data) {
+        Ok(BinanceEvent::StreamError(error)) => assert_eq!(
+            error,
+            StreamError {
+                code: 2,
+                msg: "Invalid request: unknown symbol".to_string(),
+                id: Some(1),
+            }
+        ),
+        other => panic!("expected a stream error, got {other:?}"),
+    }
+}
+
+#[test]
+fn subscription_ack_is_an_event_and_heartbeat_is_not() {
+    assert!(matches!(
+        BinanceMessage::from_str_into_market_data(r#"{"result":null,"id":1}"#),
+        Ok(BinanceEvent::RequestAck(1))
+    ));
+    assert!(matches!(
+        BinanceMessage::from_str_into_market_data("1700000000000"),
+        Err(None)
+    ));
+}
+
+#[test]
+fn malformed_level_is_skipped_without_losing_the_update() {
+    let data = r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1700000000000,"s":"BTCUSDT","U":10,"u":12,"b":[["100.5","1"],["not-a-price","2"],["99.5","3"]],"a":[["101","0.5"]]}}"#;
+    match BinanceMessage::from_str_into_market_data(data) {
+        Ok(BinanceEvent::DepthUpdate(update)) => {
+            assert_eq!((update.first_update_id, update.final_update_id), (10, 12));
+            let bids: Vec<_> = update.bids.iter().map(|level| level.price).collect();
+            assert_eq!(bids, [dec!(100.5), dec!(99.5)]);
+            assert_eq!(update.asks.len(), 1);
+        }
+        other => panic!("expected a depth update, got {other:?}"),
+    }
+}
+
+#[test]
+fn partial_depth_stream_is_parsed_as_a_snapshot() {
+    let payload = r#"{"lastUpdateId":160,"bids":[["100","1"],["99.5","2"]],"asks":[["101","3"]]}"#;
+    let wrapped = format!(r#"{{"stream":"btcusdt@depth20@100ms","data":{payload}}}"#);
+    for data in [wrapped.as_str(), payload] {
+        match BinanceMessage::from_str_into_market_data(data) {
+            Ok(BinanceEvent::PartialDepth(snapshot)) => {
+                assert_eq!(snapshot.last_update_id, 160);
+                assert_eq!(snapshot.bids.len(), 2);
+                assert_eq!(snapshot.asks[0].price, dec!(101));
+            }
+            other => panic!("expected a partial depth snapshot, got {other:?}"),
+        }
+    }
+}
+
+/// Deserializes `raw`, serializes the result and checks it reads back as the same JSON
+fn assert_round_trips<T: serde::Serialize + serde::de::DeserializeOwned>(raw: &str) {
+    let event: T = serde_json::from_str(raw).unwrap();
+    let serialized = serde_json::to_value(&event).unwrap();
+    assert_eq!(
+        serialized,
+        serde_json::from_str::<serde_json::Value>(raw).unwrap()
+    );
+    let reparsed: T = serde_json::from_value(serialized.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&reparsed).unwrap(), serialized);
+}
+
+#[test]
+fn data_events_serialize_back_to_the_binance_format() {
+    assert_round_trips::<TradeEventData>(
+        r#"{"E":1700000000000,"s":"BTCUSDT","t":12345,"p":"100.50","q":"0.001","T":1699999999999,"m":true}"#,
+    );
+    assert_round_trips::<AggregateTrade>(
+        r#"{"E":1700000000000,"s":"BTCUSDT","a":26129,"p":"0.01633102","q":"4.70443515","f":27781,"l":27781,"T":1699999999999,"m":false}"#,
+    );
+    assert_round_trips::<DepthUpdate>(
+        r#"{"E":1700000000000,"s":"BTCUSDT","U":157,"u":160,"b":[["0.0024","10"]],"a":[["0.0026","100"],["0.0027","0"]]}"#,
+    );
+    assert_round_trips::<DepthSnapshot>(
+        r#"{"lastUpdateId":1027024,"bids":[["4.00000000","431.00000000"]],"asks":[["4.00000200","12.00000000"]]}"#,
+    );
+}
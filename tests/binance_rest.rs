@@ -0,0 +1,20 @@
+use marketmakerlib::binance::rest::RestError;
+use std::{collections::HashMap, time::Duration};
+
+#[test]
+fn rate_limit_statuses_carry_the_retry_after() {
+    let headers = HashMap::from([("Retry-After".to_string(), "30".to_string())]);
+    let limited = RestError::from_status(429, &headers).unwrap();
+    assert_eq!(
+        limited,
+        RestError::RateLimited {
+            retry_after: Some(Duration::from_secs(30))
+        }
+    );
+    assert_eq!(limited.retry_after(), Some(Duration::from_secs(30)));
+
+    let banned = RestError::from_status(418, &HashMap::new()).unwrap();
+    assert_eq!(banned, RestError::Banned { retry_after: None });
+
+    assert_eq!(RestError::from_status(400, &headers), None);
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, TimeDelta};
+use marketmakerlib::book_history::{BookHistory, BookSample};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn sample(seconds: i64, mid_price: Decimal, spread: Decimal) -> BookSample {
+    BookSample {
+        timestamp: DateTime::from_timestamp_millis(1_700_000_000_000).unwrap()
+            + TimeDelta::seconds(seconds),
+        mid_price,
+        spread,
+    }
+}
+
+#[test]
+fn history_is_bounded_and_keeps_latest_samples() {
+    let mut history = BookHistory::new(3);
+    for i in 0..5 {
+        history.record(sample(i, Decimal::from(100 + i), dec!(1)));
+    }
+    assert_eq!(history.len(), 3);
+    assert_eq!(history.samples().next().unwrap().mid_price, dec!(102));
+    assert_eq!(history.latest().unwrap().mid_price, dec!(104));
+
+    history.set_capacity(1);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history.latest().unwrap().mid_price, dec!(104));
+}
+
+#[test]
+fn mid_velocity_and_spread_widening() {
+    let mut history = BookHistory::new(10);
+    assert_eq!(history.mid_velocity(5), None);
+
+    history.record(sample(0, dec!(100), dec!(1)));
+    history.record(sample(2, dec!(101), dec!(1)));
+    history.record(sample(4, dec!(99), dec!(4)));
+
+    // Over all samples: -1 over 4 seconds
+    assert_eq!(history.mid_velocity(10), Some(dec!(-0.25)));
+    // Over the last two: -2 over 2 seconds
+    assert_eq!(history.mid_velocity(2), Some(dec!(-1)));
+
+    assert_eq!(history.average_spread(3), Some(dec!(2)));
+    assert_eq!(history.spread_ratio(3), Some(dec!(2)));
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, TimeDelta};
+use marketmakerlib::clock::ClockSkew;
+
+#[test]
+fn clock_skew_is_the_median_offset() {
+    let local = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut skew = ClockSkew::new(5);
+    assert_eq!(skew.offset(), TimeDelta::zero());
+
+    // Our clock runs about 2s ahead, one message was held up for another 10s
+    for (i, lag_ms) in [2_000, 2_010, 12_000, 1_990, 2_005].into_iter().enumerate() {
+        let now = local + TimeDelta::seconds(i as i64);
+        skew.record(now, now - TimeDelta::milliseconds(lag_ms));
+    }
+    assert_eq!(skew.offset(), TimeDelta::milliseconds(2_005));
+    assert_eq!(
+        skew.to_exchange_time(local),
+        local - TimeDelta::milliseconds(2_005)
+    );
+    assert_eq!(skew.to_local_time(skew.to_exchange_time(local)), local);
+
+    // Only the last five samples count
+    for i in 0..3 {
+        let now = local + TimeDelta::seconds(10 + i);
+        skew.record(now, now + TimeDelta::milliseconds(500));
+    }
+    assert_eq!(skew.offset(), TimeDelta::milliseconds(-500));
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use marketmakerlib::{
+    binance::data::TradeEventData,
+    flow_spike::{FlowSpikeConfig, FlowSpikeDetector},
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Trades with increasing ids, repeated ids are ignored as duplicates
+fn trade(time: DateTime<Utc>, buyer_market_maker: bool) -> TradeEventData {
+    static NEXT_TRADE_ID: AtomicU64 = AtomicU64::new(1);
+    TradeEventData {
+        event_time: time,
+        symbol: "BTCUSDT".to_string(),
+        trade_id: NEXT_TRADE_ID.fetch_add(1, Ordering::Relaxed),
+        price: dec!(100),
+        quantity: dec!(1),
+        trade_time: time,
+        buyer_market_maker,
+    }
+}
+
+/// A trade a second for a bit over the default baseline, alternating sides
+fn calm_detector(start: DateTime<Utc>) -> FlowSpikeDetector {
+    let mut detector = FlowSpikeDetector::new(FlowSpikeConfig::default());
+    for second in 0..=65 {
+        let spike = detector.update(trade(start + TimeDelta::seconds(second), second % 2 == 0));
+        assert_eq!(spike, None);
+    }
+    detector
+}
+
+#[test]
+fn one_sided_burst_is_reported_once() {
+    let start = Utc::now();
+    let mut detector = calm_detector(start);
+
+    // Twenty taking sells within a second
+    let burst_start = start + TimeDelta::seconds(66);
+    let spikes: Vec<_> = (0..20)
+        .filter_map(|i| detector.update(trade(burst_start + TimeDelta::milliseconds(i * 50), true)))
+        .collect();
+
+    assert_eq!(spikes.len(), 1);
+    let spike = spikes[0];
+    assert!(spike.imbalance <= dec!(-0.6));
+    assert!(spike.trades_per_second >= spike.baseline_trades_per_second * Decimal::from(3));
+    assert!(detector.is_spiking());
+
+    // Back to the usual pace and mix, the window drains and the flow calms down
+    let calm_start = burst_start + TimeDelta::seconds(1);
+    for second in 0..10 {
+        detector.update(trade(
+            calm_start + TimeDelta::seconds(second),
+            second % 2 == 0,
+        ));
+    }
+    assert!(!detector.is_spiking());
+}
+
+#[test]
+fn fast_but_balanced_flow_is_not_a_spike() {
+    let start = Utc::now();
+    let mut detector = calm_detector(start);
+
+    let burst_start = start + TimeDelta::seconds(66);
+    for i in 0..20 {
+        let spike = detector.update(trade(
+            burst_start + TimeDelta::milliseconds(i * 50),
+            i % 2 == 0,
+        ));
+        assert_eq!(spike, None);
+    }
+    assert!(!detector.is_spiking());
+}
+
+#[test]
+fn nothing_is_reported_before_the_baseline_fills() {
+    let mut detector = FlowSpikeDetector::new(FlowSpikeConfig::default());
+    let start = Utc::now();
+    for i in 0..50 {
+        let spike = detector.update(trade(start + TimeDelta::milliseconds(i * 10), true));
+        assert_eq!(spike, None);
+    }
+}
@@ -0,0 +1,76 @@
+use marketmakerlib::{binance::data::KlineEventData, kline_store::KlineStore};
+use rust_decimal_macros::dec;
+
+const MINUTE_MS: i64 = 60_000;
+const START_MS: i64 = 1_700_000_000_000;
+
+fn kline(
+    interval: &str,
+    index: i64,
+    high: &str,
+    low: &str,
+    close: &str,
+    closed: bool,
+) -> KlineEventData {
+    let start = START_MS + index * MINUTE_MS;
+    serde_json::from_value(serde_json::json!({
+        "E": start + MINUTE_MS,
+        "s": "BTCUSDT",
+        "k": {
+            "t": start,
+            "T": start + MINUTE_MS - 1,
+            "s": "BTCUSDT",
+            "i": interval,
+            "f": 1,
+            "L": 2,
+            "o": close,
+            "c": close,
+            "h": high,
+            "l": low,
+            "v": "1.5",
+            "n": 2,
+            "x": closed,
+            "q": "150",
+            "V": "0.5",
+            "Q": "50",
+            "B": "0"
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn only_closed_candles_are_kept_per_interval() {
+    let mut store = KlineStore::new(2);
+    store.update(&kline("1m", 0, "101", "99", "100", true));
+    store.update(&kline("1m", 1, "102", "100", "101", false));
+    store.update(&kline("5m", 0, "105", "95", "104", true));
+
+    assert_eq!(store.latest("1m").unwrap().close, dec!(100));
+    assert_eq!(store.forming("1m").unwrap().close, dec!(101));
+    assert_eq!(store.latest("5m").unwrap().close, dec!(104));
+
+    store.update(&kline("1m", 1, "102", "100", "102", true));
+    store.update(&kline("1m", 2, "103", "101", "103", true));
+    assert!(store.forming("1m").is_none());
+    // Capacity of two drops the first candle
+    assert_eq!(store.candles("1m").count(), 2);
+    assert_eq!(store.sma("1m", 2), Some(dec!(102.5)));
+    assert_eq!(store.sma("1m", 3), None);
+}
+
+#[test]
+fn atr_uses_true_range_against_previous_close() {
+    let mut store = KlineStore::new(10);
+    store.update(&kline("1m", 0, "101", "99", "100", true));
+    store.update(&kline("1m", 1, "103", "100", "102", true));
+    assert_eq!(store.atr("1m", 2), None);
+
+    // Gap down: the range against the previous close exceeds high - low
+    store.update(&kline("1m", 2, "101", "99", "99", true));
+    // True ranges: max(3, 3, 0) = 3 and max(2, 1, 3) = 3
+    assert_eq!(store.atr("1m", 2), Some(dec!(3)));
+    assert_eq!(store.atr("1m", 1), Some(dec!(3)));
+    assert_eq!(store.atr("1m", 3), None);
+    assert_eq!(store.atr("5m", 1), None);
+}
@@ -0,0 +1,50 @@
+use futures_util::stream;
+use marketmakerlib::{
+    binance::data::BinanceEvent,
+    market_data::{BinanceStreamSource, FileReplaySource, MarketDataSource},
+};
+
+const DEPTH: &str = r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1700000000000,"s":"BTCUSDT","U":1,"u":2,"b":[["100","1"]],"a":[["101","1"]]}}"#;
+const AGG_TRADE: &str = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1700000000001,"s":"BTCUSDT","a":7,"p":"100.5","q":"0.1","f":7,"l":7,"T":1700000000001,"m":true,"M":true}}"#;
+
+/// Drains a source into its events, with errors as `None`
+async fn drain(mut source: impl MarketDataSource) -> Vec<Option<BinanceEvent>> {
+    let mut events = Vec::new();
+    while let Some(event) = source.next_event().await {
+        events.push(event.ok());
+    }
+    events
+}
+
+#[tokio::test]
+async fn stream_source_skips_heartbeats_and_reports_bad_frames() {
+    let frames = vec![
+        "1700000000000".to_string(),
+        r#"{"result":null,"id":1}"#.to_string(),
+        DEPTH.to_string(),
+        "not json".to_string(),
+        AGG_TRADE.to_string(),
+    ];
+
+    let events = drain(BinanceStreamSource::new(stream::iter(frames))).await;
+
+    assert_eq!(events.len(), 4);
+    assert!(matches!(events[0], Some(BinanceEvent::RequestAck(1))));
+    assert!(matches!(events[1], Some(BinanceEvent::DepthUpdate(_))));
+    assert!(events[2].is_none());
+    assert!(matches!(events[3], Some(BinanceEvent::AggTrade(_))));
+}
+
+#[tokio::test]
+async fn file_replay_yields_the_same_events_as_the_live_source() {
+    let path =
+        std::env::temp_dir().join(format!("market_data_replay_{}.jsonl", std::process::id()));
+    std::fs::write(&path, format!("{DEPTH}\n\n{AGG_TRADE}\n")).unwrap();
+
+    let replayed = drain(FileReplaySource::open(&path).await.unwrap()).await;
+    let live = drain(BinanceStreamSource::new(stream::iter([DEPTH, AGG_TRADE]))).await;
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(replayed.len(), 2);
+    assert_eq!(format!("{replayed:?}"), format!("{live:?}"));
+}
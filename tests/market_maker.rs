@@ -0,0 +1,1884 @@
+use chrono::{DateTime, NaiveTime, TimeDelta, Utc};
+use marketmakerlib::{
+    binance::data::{DepthSnapshot, DepthUpdate, OfferData, TradeEventData, WindowTickerData},
+    clock::{Clock, MockClock, SystemClock},
+    flow_spike::FlowSpikeConfig,
+    market_maker::{
+        Action, AdaptiveState, CancelReason, FeatureCsvWriter, FeeModel, FillQuality, FillSource,
+        ForwardHorizon, HaltReason, ImbalanceBand, ImbalanceNormalization, KFactorPolicy, KOutcome,
+        MarketMaker, MarketMakerConfig, MidSource, MultiplicativePolicy, Order, OrderSide,
+        OrderStatus, OrderThrottle, QuoteMode, ReconnectPolicy, SuppressionWindow, SymbolPrecision,
+        ValueAreaBids, distance_in_sigmas, format_to_increment, inventory_skew,
+    },
+    order_book_state::OrderBookState,
+    recent_trades::RecentTrades,
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::{sync::Arc, time::Duration};
+
+fn levels(levels: &[(Decimal, Decimal)]) -> Vec<OfferData> {
+    levels
+        .iter()
+        .map(|&(price, size)| OfferData { price, size })
+        .collect()
+}
+
+fn maker(config: MarketMakerConfig) -> MarketMaker {
+    let book = OrderBookState::from_levels(
+        vec![(dec!(100), dec!(1)), (dec!(99), dec!(1))],
+        vec![(dec!(100.1), dec!(1)), (dec!(100.2), dec!(1))],
+    );
+    MarketMaker::new(config, book, RecentTrades::default())
+}
+
+fn depth_update(update_id: u64) -> DepthUpdate {
+    DepthUpdate {
+        event_time: Utc::now(),
+        symbol: "BTCUSDT".to_string(),
+        first_update_id: update_id,
+        final_update_id: update_id,
+        bids: Vec::new(),
+        asks: Vec::new(),
+    }
+}
+
+fn resting_bid(id: &str, price: Decimal, k_factor_used: Decimal) -> Order {
+    Order {
+        id: id.to_string(),
+        price,
+        size: dec!(0.01),
+        side: OrderSide::Buy,
+        status: OrderStatus::Placed,
+        created_at: Utc::now(),
+        filled_at: None,
+        reference_mid: dec!(100.05),
+        reference_best_bid: dec!(100),
+        k_factor_used,
+        imbalance_at_placement: Decimal::ZERO,
+        distance_sigmas: None,
+        queue_ahead: Decimal::ZERO,
+        cancel_reason: None,
+        fill_quality: None,
+        fill_source: None,
+    }
+}
+
+fn cancel_reason(maker: &MarketMaker, id: &str) -> Option<CancelReason> {
+    maker
+        .cancelled_orders
+        .iter()
+        .find(|order| order.id == id)
+        .and_then(|order| order.cancel_reason)
+}
+
+#[test]
+fn cancel_band_uses_percentage_distance_from_best_bid() {
+    // k = 1: too far beyond 5%, too close within 0.025% of the best bid of 100
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .cancel_band(dec!(5), dec!(0.5))
+            .min_distance_pct(dec!(0.05))
+            .build()
+            .unwrap(),
+    );
+    maker.active_orders = vec![
+        resting_bid("inside", dec!(97), dec!(1)),
+        resting_bid("far", dec!(94), dec!(1)),
+        resting_bid("near", dec!(99.99), dec!(1)),
+        resting_bid("near-edge", dec!(99.97), dec!(1)),
+        resting_bid("far-edge", dec!(95), dec!(1)),
+    ];
+
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    let active = maker
+        .active_orders
+        .iter()
+        .map(|order| order.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(active, ["inside", "near-edge", "far-edge"]);
+    assert_eq!(cancel_reason(&maker, "far"), Some(CancelReason::TooFar));
+    assert_eq!(cancel_reason(&maker, "near"), Some(CancelReason::TooClose));
+}
+
+#[test]
+fn cancel_band_scales_with_k_factor() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .cancel_band(dec!(5), dec!(0.5))
+            .build()
+            .unwrap(),
+    );
+    // 3% below best bid: inside the band for k = 1 (5%), outside for k = 0.5 (2.5%)
+    maker.active_orders = vec![
+        resting_bid("k1", dec!(97), dec!(1)),
+        resting_bid("k0.5", dec!(97), dec!(0.5)),
+    ];
+
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    assert_eq!(maker.active_orders.len(), 1);
+    assert_eq!(maker.active_orders[0].id, "k1");
+    assert_eq!(cancel_reason(&maker, "k0.5"), Some(CancelReason::TooFar));
+}
+
+#[test]
+fn prices_and_sizes_are_formatted_to_symbol_precision() {
+    let precision = SymbolPrecision::default();
+    assert_eq!(precision.format_price(dec!(95123.45000000)), "95123.45");
+    assert_eq!(precision.format_price(dec!(95123.456)), "95123.46");
+    assert_eq!(precision.format_price(dec!(95123)), "95123.00");
+    assert_eq!(precision.format_size(dec!(0.01000000)), "0.01000");
+    assert_eq!(format_to_increment(dec!(1.2345), dec!(1)), "1");
+}
+
+#[test]
+fn hard_imbalance_flip_cancels_orders_from_the_prior_regime() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .regime_shift_threshold(Some(dec!(0.5)))
+            .build()
+            .unwrap(),
+    );
+    let mut sell_regime = resting_bid("sell-regime", dec!(97), dec!(1));
+    sell_regime.imbalance_at_placement = dec!(-0.8);
+    let mut buy_regime = resting_bid("buy-regime", dec!(97), dec!(1));
+    buy_regime.imbalance_at_placement = dec!(0.6);
+    maker.active_orders = vec![sell_regime, buy_regime];
+    // A balanced first reading
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    assert_eq!(maker.active_orders.len(), 2);
+
+    // Top of book imbalance jumps from 0 to 0.9
+    let mut update = depth_update(3);
+    update.bids = levels(&[(dec!(100), dec!(19))]);
+    maker.handle_depth_update(update).unwrap();
+
+    assert_eq!(
+        cancel_reason(&maker, "sell-regime"),
+        Some(CancelReason::RegimeShift)
+    );
+    assert!(maker.active_orders.iter().any(|o| o.id == "buy-regime"));
+}
+
+#[test]
+fn first_imbalance_reading_is_not_a_regime_shift() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .regime_shift_threshold(Some(dec!(0.5)))
+            .build()
+            .unwrap(),
+    );
+    let mut restored = resting_bid("restored", dec!(97), dec!(1));
+    restored.imbalance_at_placement = dec!(-0.8);
+    maker.active_orders = vec![restored];
+
+    // Already 0.9 on the first update, with nothing before it to have jumped from
+    let mut update = depth_update(2);
+    update.bids = levels(&[(dec!(100), dec!(19))]);
+    maker.handle_depth_update(update).unwrap();
+
+    assert_eq!(cancel_reason(&maker, "restored"), None);
+    assert!(maker.active_orders.iter().any(|o| o.id == "restored"));
+}
+
+#[test]
+fn sell_pressure_after_placement_cancels_the_order() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .adverse_imbalance_delta(Some(dec!(0.5)))
+            .build()
+            .unwrap(),
+    );
+    // Placed on the balanced book, inside the cancel band
+    maker.active_orders = vec![resting_bid("neutral", dec!(99.5), dec!(1))];
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    assert!(cancel_reason(&maker, "neutral").is_none());
+
+    // Asks pile up at the touch, taking the imbalance to about -0.8
+    let mut update = depth_update(3);
+    update.asks = levels(&[(dec!(100.1), dec!(10))]);
+    maker.handle_depth_update(update).unwrap();
+    assert_eq!(
+        cancel_reason(&maker, "neutral"),
+        Some(CancelReason::AdverseImbalance)
+    );
+}
+
+#[test]
+fn mock_clock_drives_staleness_and_timestamps() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let clock = MockClock::new(start);
+    let book =
+        OrderBookState::from_levels(vec![(dec!(100), dec!(1))], vec![(dec!(100.1), dec!(1))]);
+    let mut maker = MarketMaker::with_clock(
+        MarketMakerConfig::default(),
+        book,
+        RecentTrades::default(),
+        Arc::new(clock.clone()),
+    );
+    let mut update = depth_update(2);
+    update.event_time = start;
+    maker.handle_depth_update(update).unwrap();
+    maker.active_orders = vec![resting_bid("resting", dec!(97), dec!(1))];
+
+    let trade = |trade_id| TradeEventData {
+        event_time: start,
+        symbol: "BTCUSDT".to_string(),
+        trade_id,
+        price: dec!(100.1),
+        quantity: dec!(0.1),
+        trade_time: start,
+        buyer_market_maker: false,
+    };
+
+    // Within the staleness limit nothing happens...
+    clock.advance(TimeDelta::seconds(1));
+    maker.handle_trade(trade(1)).unwrap();
+    assert_eq!(maker.active_orders.len(), 1);
+
+    // ...but once the depth stream has been quiet for too long the order is pulled
+    clock.advance(TimeDelta::seconds(10));
+    maker.handle_trade(trade(2)).unwrap();
+    assert_eq!(
+        cancel_reason(&maker, "resting"),
+        Some(CancelReason::StaleBook)
+    );
+    let record = maker.audit_log.records().last().unwrap();
+    assert_eq!(record.timestamp, start + TimeDelta::seconds(11));
+}
+
+#[test]
+fn staleness_is_measured_on_the_exchange_clock() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let clock = MockClock::new(start);
+    let book =
+        OrderBookState::from_levels(vec![(dec!(100), dec!(1))], vec![(dec!(100.1), dec!(1))]);
+    let config = MarketMakerConfig::builder()
+        .max_book_staleness(Duration::from_secs(1))
+        .build()
+        .unwrap();
+    let mut maker = MarketMaker::with_clock(
+        config,
+        book,
+        RecentTrades::default(),
+        Arc::new(clock.clone()),
+    );
+    // The exchange's clock runs 3s behind ours
+    let mut update = depth_update(2);
+    update.event_time = start - TimeDelta::seconds(3);
+    maker.handle_depth_update(update).unwrap();
+
+    assert_eq!(maker.clock_skew().offset(), TimeDelta::seconds(3));
+    assert_eq!(maker.exchange_now(), start - TimeDelta::seconds(3));
+    assert!(!maker.is_book_stale(start + TimeDelta::milliseconds(500)));
+    assert!(maker.is_book_stale(start + TimeDelta::seconds(2)));
+}
+
+#[test]
+fn snapshot_age_is_measured_on_our_clock() {
+    let start = Utc::now();
+    let clock = MockClock::new(start);
+    let book =
+        OrderBookState::from_levels(vec![(dec!(100), dec!(1))], vec![(dec!(100.1), dec!(1))]);
+    let config = MarketMakerConfig::builder()
+        .max_book_staleness(Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let mut maker = MarketMaker::with_clock(
+        config,
+        book,
+        RecentTrades::default(),
+        Arc::new(clock.clone()),
+    );
+    // The exchange's clock runs 30s ahead of ours
+    let mut update = depth_update(2);
+    update.event_time = start + TimeDelta::seconds(30);
+    maker.handle_depth_update(update).unwrap();
+    assert!(!maker.is_book_stale(start));
+
+    maker
+        .handle_partial_depth(DepthSnapshot {
+            last_update_id: 3,
+            bids: levels(&[(dec!(100), dec!(2))]),
+            asks: levels(&[(dec!(100.1), dec!(2))]),
+        })
+        .unwrap();
+    assert!(maker.order_book.is_stamped_locally());
+    assert!(!maker.is_book_stale(start));
+}
+
+#[test]
+fn active_orders_sort_best_price_first_per_side() {
+    let mut maker = maker(MarketMakerConfig::default());
+    let ask = |id: &str, price| Order {
+        side: OrderSide::Sell,
+        ..resting_bid(id, price, dec!(1))
+    };
+    maker.active_orders = vec![
+        resting_bid("mid", dec!(98), dec!(1)),
+        ask("far ask", dec!(103)),
+        resting_bid("deep", dec!(97), dec!(1)),
+        resting_bid("near", dec!(99), dec!(1)),
+        ask("near ask", dec!(101)),
+    ];
+
+    let ids = |orders: Vec<&Order>| {
+        orders
+            .iter()
+            .map(|order| order.id.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        ids(maker.active_orders_by_price(OrderSide::Buy)),
+        ["near", "mid", "deep"]
+    );
+    assert_eq!(
+        ids(maker.active_orders_by_price(OrderSide::Sell)),
+        ["near ask", "far ask"]
+    );
+    assert_eq!(maker.worst_order(OrderSide::Buy).unwrap().id, "deep");
+    assert_eq!(maker.worst_order(OrderSide::Sell).unwrap().id, "far ask");
+    assert_eq!(maker.total_active_size(OrderSide::Buy), dec!(0.03));
+    assert_eq!(maker.total_active_size(OrderSide::Sell), dec!(0.02));
+
+    maker.active_orders.clear();
+    assert!(maker.worst_order(OrderSide::Buy).is_none());
+    assert_eq!(maker.total_active_size(OrderSide::Buy), Decimal::ZERO);
+}
+
+#[test]
+fn maintenance_expires_orders_without_waiting_for_the_book() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let clock = MockClock::new(start);
+    let book =
+        OrderBookState::from_levels(vec![(dec!(100), dec!(1))], vec![(dec!(100.1), dec!(1))]);
+    let config = MarketMakerConfig::builder()
+        .max_book_staleness(Duration::from_secs(60))
+        .order_ttl(Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let mut maker = MarketMaker::with_clock(
+        config,
+        book,
+        RecentTrades::default(),
+        Arc::new(clock.clone()),
+    );
+    let mut update = depth_update(2);
+    update.event_time = start;
+    maker.handle_depth_update(update).unwrap();
+
+    let resting_at = |id: &str, created_at| {
+        let mut order = resting_bid(id, dec!(99), dec!(1));
+        order.created_at = created_at;
+        order
+    };
+    maker.active_orders.push(resting_at("old", start));
+    maker
+        .active_orders
+        .push(resting_at("new", start + TimeDelta::seconds(5)));
+
+    maker.maintenance(start + TimeDelta::seconds(9));
+    assert_eq!(maker.active_orders.len(), 2);
+
+    maker.maintenance(start + TimeDelta::seconds(10));
+    assert_eq!(cancel_reason(&maker, "old"), Some(CancelReason::Expired));
+    assert_eq!(maker.active_orders.len(), 1);
+
+    // With no book updates for a minute everything goes, expired or not
+    maker
+        .active_orders
+        .push(resting_at("fresh", start + TimeDelta::seconds(60)));
+    maker.maintenance(start + TimeDelta::seconds(61));
+    assert_eq!(cancel_reason(&maker, "new"), Some(CancelReason::StaleBook));
+    assert_eq!(
+        cancel_reason(&maker, "fresh"),
+        Some(CancelReason::StaleBook)
+    );
+    assert!(maker.active_orders.is_empty());
+}
+
+#[test]
+fn throttle_enforces_both_rate_limits() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut throttle = OrderThrottle::new(2, 3);
+
+    assert!(throttle.try_acquire(start));
+    assert!(throttle.try_acquire(start));
+    assert!(!throttle.try_acquire(start));
+
+    // Half a second refills one per-second token
+    let later = start + TimeDelta::milliseconds(500);
+    assert!(throttle.try_acquire(later));
+    // The per-minute budget is now spent even though a second has passed
+    assert!(!throttle.try_acquire(start + TimeDelta::seconds(2)));
+    assert!(throttle.try_acquire(start + TimeDelta::seconds(22)));
+}
+
+#[test]
+fn throttle_takes_grouped_actions_all_or_nothing() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut throttle = OrderThrottle::new(3, 100);
+
+    assert!(throttle.try_acquire_many(start, 2));
+    assert!(!throttle.try_acquire_many(start, 2));
+    // The failed pair took nothing
+    assert!(throttle.try_acquire(start));
+}
+
+#[test]
+fn throttled_cancels_are_deferred() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .order_rate_limits(1, 100)
+            .build()
+            .unwrap(),
+    );
+    maker.active_orders = vec![
+        resting_bid("far-1", dec!(90), dec!(1)),
+        resting_bid("far-2", dec!(91), dec!(1)),
+    ];
+
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    assert_eq!(maker.cancelled_orders.len(), 1);
+    assert_eq!(maker.active_orders.len(), 1);
+    assert_eq!(maker.throttle_hits(), 1);
+}
+
+#[test]
+fn inventory_skew_leans_against_the_position() {
+    let (factor, max_inventory, price_volatility) = (dec!(2), dec!(1), dec!(10));
+
+    assert_eq!(
+        inventory_skew(factor, Decimal::ZERO, max_inventory, price_volatility),
+        Decimal::ZERO
+    );
+    // Max long: bid 20 lower
+    assert_eq!(
+        inventory_skew(factor, dec!(1), max_inventory, price_volatility),
+        dec!(20)
+    );
+    // Max short: bid 20 higher
+    assert_eq!(
+        inventory_skew(factor, dec!(-1), max_inventory, price_volatility),
+        dec!(-20)
+    );
+    // Beyond the max the skew stays at full strength
+    assert_eq!(
+        inventory_skew(factor, dec!(3), max_inventory, price_volatility),
+        dec!(20)
+    );
+}
+
+#[test]
+fn stink_bid_that_would_cross_the_ask_is_not_placed() {
+    // A crossed book, as seen briefly during a fast move
+    let book = OrderBookState::from_levels(
+        vec![(dec!(101), dec!(1)), (dec!(100.9), dec!(1))],
+        vec![(dec!(100), dec!(1)), (dec!(100.1), dec!(1))],
+    );
+    let config = MarketMakerConfig::builder()
+        .min_distance_pct(dec!(0.7))
+        .build()
+        .unwrap();
+    let clock = Arc::new(MockClock::new(
+        DateTime::from_timestamp_millis(1_700_000_000_000).unwrap(),
+    ));
+    let start = clock.now();
+    let mut maker = MarketMaker::with_clock(config, book, RecentTrades::default(), clock);
+
+    for (i, price) in [dec!(100), dec!(100.001), dec!(100), dec!(100.001)]
+        .into_iter()
+        .enumerate()
+    {
+        let time = start + TimeDelta::milliseconds(i as i64);
+        maker
+            .handle_trade(TradeEventData {
+                event_time: time,
+                symbol: "BTCUSDT".to_string(),
+                trade_id: i as u64,
+                price,
+                quantity: dec!(0.1),
+                trade_time: time,
+                buyer_market_maker: false,
+            })
+            .unwrap();
+    }
+
+    // Priced at 101 - 0.707 = 100.293, a sensible discount to the 100.5 mid but through the ask
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    assert!(maker.active_orders.is_empty());
+    assert!(maker.audit_log.records().is_empty());
+}
+
+#[test]
+fn statistics_round_metrics_to_display_precision() {
+    let config = MarketMakerConfig::builder()
+        .base_k(dec!(0.123456))
+        .display_precision(2)
+        .build()
+        .unwrap();
+    assert_eq!(config.for_display(dec!(0.123456)), dec!(0.12));
+
+    let statistics = maker(config).get_statistics();
+    assert!(statistics.contains("Current K-Factor: 0.12\n"));
+}
+
+#[test]
+fn fill_into_a_falling_market_is_classified_adverse() {
+    let mut maker = maker(MarketMakerConfig::default());
+    maker.active_orders = vec![resting_bid("knife", dec!(100), dec!(1))];
+
+    let start = Utc::now();
+    for (i, price) in [dec!(100.5), dec!(100.3), dec!(100.1), dec!(99.9)]
+        .into_iter()
+        .enumerate()
+    {
+        let time = start + TimeDelta::milliseconds(i as i64);
+        maker
+            .handle_trade(TradeEventData {
+                event_time: time,
+                symbol: "BTCUSDT".to_string(),
+                trade_id: i as u64,
+                price,
+                quantity: dec!(0.1),
+                trade_time: time,
+                buyer_market_maker: true,
+            })
+            .unwrap();
+    }
+
+    assert!(maker.is_adverse_selection_likely());
+    assert_eq!(maker.filled_orders.len(), 1);
+    assert_eq!(
+        maker.filled_orders[0].fill_quality,
+        Some(FillQuality::Adverse)
+    );
+    assert_eq!(maker.adverse_fill_rate(), dec!(100));
+}
+
+#[test]
+fn trades_at_the_bid_price_work_through_the_queue_ahead_first() {
+    let mut maker = maker(MarketMakerConfig::default());
+    let mut bid = resting_bid("queued", dec!(99.9), dec!(1));
+    bid.queue_ahead = dec!(0.02);
+    maker.active_orders = vec![bid];
+
+    let now = Utc::now();
+    let sell_at = |trade_id, price| TradeEventData {
+        event_time: now,
+        symbol: "BTCUSDT".to_string(),
+        trade_id,
+        price,
+        quantity: dec!(0.015),
+        trade_time: now,
+        buyer_market_maker: true,
+    };
+
+    // Only eats into the 0.02 resting ahead of us
+    maker.handle_trade(sell_at(1, dec!(99.9))).unwrap();
+    assert!(maker.filled_orders.is_empty());
+    assert_eq!(maker.active_orders[0].queue_ahead, dec!(0.005));
+
+    // Clears the rest of the queue with 0.01 left over for our bid
+    maker.handle_trade(sell_at(2, dec!(99.9))).unwrap();
+    assert_eq!(maker.filled_orders.len(), 1);
+    assert_eq!(maker.filled_orders[0].id, "queued");
+}
+
+#[test]
+fn redelivered_trades_do_not_work_through_the_queue_again() {
+    let mut maker = maker(MarketMakerConfig::default());
+    let mut bid = resting_bid("queued", dec!(99.9), dec!(1));
+    bid.queue_ahead = dec!(0.02);
+    maker.active_orders = vec![bid];
+
+    let now = Utc::now();
+    let sell = || TradeEventData {
+        event_time: now,
+        symbol: "BTCUSDT".to_string(),
+        trade_id: 1,
+        price: dec!(99.9),
+        quantity: dec!(0.015),
+        trade_time: now,
+        buyer_market_maker: true,
+    };
+    maker.handle_trade(sell()).unwrap();
+    // The same print again after a reconnect
+    maker.handle_trade(sell()).unwrap();
+
+    assert!(maker.filled_orders.is_empty());
+    assert_eq!(maker.active_orders[0].queue_ahead, dec!(0.005));
+}
+
+#[test]
+fn trade_through_the_bid_price_skips_the_queue_ahead() {
+    let mut maker = maker(MarketMakerConfig::default());
+    let mut bid = resting_bid("queued", dec!(99.9), dec!(1));
+    bid.queue_ahead = dec!(5);
+    maker.active_orders = vec![bid];
+
+    let now = Utc::now();
+    maker
+        .handle_trade(TradeEventData {
+            event_time: now,
+            symbol: "BTCUSDT".to_string(),
+            trade_id: 1,
+            price: dec!(99.8),
+            quantity: dec!(0.01),
+            trade_time: now,
+            buyer_market_maker: true,
+        })
+        .unwrap();
+
+    assert_eq!(maker.filled_orders.len(), 1);
+    assert!(maker.active_orders.is_empty());
+}
+
+#[test]
+fn inferred_fills_are_capped_by_the_traded_volume() {
+    let mut maker = maker(MarketMakerConfig::default());
+    maker.active_orders = vec![
+        resting_bid("deeper", dec!(99.8), dec!(1)),
+        resting_bid("best", dec!(99.9), dec!(1)),
+    ];
+
+    // Enough volume through both prices for only one of the 0.01 bids
+    let now = Utc::now();
+    maker
+        .handle_trade(TradeEventData {
+            event_time: now,
+            symbol: "BTCUSDT".to_string(),
+            trade_id: 1,
+            price: dec!(99.7),
+            quantity: dec!(0.015),
+            trade_time: now,
+            buyer_market_maker: true,
+        })
+        .unwrap();
+
+    assert_eq!(maker.filled_orders.len(), 1);
+    assert_eq!(maker.filled_orders[0].id, "best");
+    assert_eq!(maker.active_orders[0].id, "deeper");
+    assert_eq!(maker.fill_divergences(), 1);
+    assert!(maker.get_statistics().contains("Fill Divergences: 1\n"));
+}
+
+#[test]
+fn deeper_bids_do_not_fill_ahead_of_a_bid_too_large_to_fill() {
+    let mut maker = maker(MarketMakerConfig::default());
+    let mut best = resting_bid("best", dec!(99.9), dec!(1));
+    best.size = dec!(0.05);
+    maker.active_orders = vec![resting_bid("deeper", dec!(99.8), dec!(1)), best];
+
+    // Prints through both bids, enough for the small one but not the large one above it
+    let now = Utc::now();
+    maker
+        .handle_trade(TradeEventData {
+            event_time: now,
+            symbol: "BTCUSDT".to_string(),
+            trade_id: 1,
+            price: dec!(99.7),
+            quantity: dec!(0.02),
+            trade_time: now,
+            buyer_market_maker: true,
+        })
+        .unwrap();
+
+    assert!(maker.filled_orders.is_empty());
+    assert_eq!(maker.active_orders.len(), 2);
+    assert_eq!(maker.net_inventory(), Decimal::ZERO);
+    assert_eq!(maker.fill_divergences(), 2);
+}
+
+#[test]
+fn small_sweep_partially_fills_stacked_bids_best_price_first() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .partial_fills(true)
+            .build()
+            .unwrap(),
+    );
+    maker.active_orders = vec![
+        resting_bid("deepest", dec!(99.7), dec!(1)),
+        resting_bid("best", dec!(99.9), dec!(1)),
+        resting_bid("middle", dec!(99.8), dec!(1)),
+    ];
+
+    // Prints below all three bids, with only 0.015 to go round the 0.01 each wants
+    let now = Utc::now();
+    maker
+        .handle_trade(TradeEventData {
+            event_time: now,
+            symbol: "BTCUSDT".to_string(),
+            trade_id: 1,
+            price: dec!(99.6),
+            quantity: dec!(0.015),
+            trade_time: now,
+            buyer_market_maker: true,
+        })
+        .unwrap();
+
+    let filled = maker
+        .filled_orders
+        .iter()
+        .map(|order| (order.id.as_str(), order.size, &order.status))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        filled,
+        [
+            ("middle-fill-1", dec!(0.005), &OrderStatus::PartiallyFilled),
+            ("best", dec!(0.01), &OrderStatus::Filled)
+        ]
+    );
+    let resting = maker
+        .active_orders
+        .iter()
+        .map(|order| (order.id.as_str(), order.size))
+        .collect::<Vec<_>>();
+    assert_eq!(resting, [("deepest", dec!(0.01)), ("middle", dec!(0.005))]);
+    assert_eq!(maker.net_inventory(), dec!(0.015));
+    // The deepest bid got nothing of a trade that printed through it
+    assert_eq!(maker.fill_divergences(), 1);
+    // Only the bid that filled whole counts as a fill
+    assert_eq!(maker.adaptive_state().successful_fill_count, 1);
+}
+
+#[test]
+fn partial_fills_leave_k_alone_until_the_order_completes() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .partial_fills(true)
+            .build()
+            .unwrap(),
+    );
+    maker.active_orders = vec![resting_bid("bid", dec!(99.9), dec!(1))];
+    let k = maker.adaptive_state().current_k;
+
+    let now = Utc::now();
+    let sell = |trade_id, quantity| TradeEventData {
+        event_time: now,
+        symbol: "BTCUSDT".to_string(),
+        trade_id,
+        price: dec!(99.8),
+        quantity,
+        trade_time: now,
+        buyer_market_maker: true,
+    };
+    maker.handle_trade(sell(1, dec!(0.004))).unwrap();
+
+    let state = maker.adaptive_state();
+    assert_eq!((state.current_k, state.successful_fill_count), (k, 0));
+    assert_eq!(maker.active_orders[0].id, "bid");
+    assert_eq!(maker.active_orders[0].status, OrderStatus::Placed);
+    assert_ne!(maker.filled_orders[0].id, "bid");
+
+    maker.handle_trade(sell(2, dec!(0.006))).unwrap();
+
+    let state = maker.adaptive_state();
+    assert_ne!(state.current_k, k);
+    assert_eq!(state.successful_fill_count, 1);
+    assert!(maker.active_orders.is_empty());
+    assert_eq!(maker.filled_orders[1].id, "bid");
+    assert_eq!(maker.filled_orders[1].status, OrderStatus::Filled);
+    assert_eq!(maker.net_inventory(), dec!(0.01));
+}
+
+#[test]
+fn value_area_bids_snap_down_to_the_point_of_control() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .min_distance_pct(dec!(2))
+            .value_area_bids(ValueAreaBids::Snap, dec!(1))
+            .build()
+            .unwrap(),
+    );
+
+    let start = Utc::now();
+    for (i, (price, quantity)) in [
+        (dec!(97.1), dec!(2)),
+        (dec!(97.3), dec!(2)),
+        (dec!(97.5), dec!(1)),
+        (dec!(96.5), dec!(2)),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let time = start + TimeDelta::milliseconds(i as i64);
+        maker
+            .handle_trade(TradeEventData {
+                event_time: time,
+                symbol: "BTCUSDT".to_string(),
+                trade_id: i as u64,
+                price,
+                quantity,
+                trade_time: time,
+                buyer_market_maker: false,
+            })
+            .unwrap();
+    }
+    assert_eq!(maker.volume_profile.point_of_control(), Some(dec!(97)));
+
+    // Volatility alone would bid at the 98 minimum distance, above the POC
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    assert_eq!(maker.active_orders.len(), 1);
+    assert_eq!(maker.active_orders[0].price, dec!(97));
+}
+
+#[test]
+fn adaptive_state_survives_a_restart_until_stale() {
+    let path = std::env::temp_dir().join(format!("adaptive_state_{}.json", std::process::id()));
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let clock = MockClock::new(start);
+    let config = MarketMakerConfig::builder()
+        .base_k(dec!(0.5))
+        .state_path(&path, Duration::from_secs(60 * 60))
+        .build()
+        .unwrap();
+    let restart = || {
+        MarketMaker::with_clock(
+            config.clone(),
+            OrderBookState::default(),
+            RecentTrades::default(),
+            Arc::new(clock.clone()),
+        )
+    };
+    AdaptiveState {
+        saved_at: start - TimeDelta::minutes(10),
+        current_k: dec!(0.8),
+        successful_fill_count: 3,
+        attempt_count: 12,
+    }
+    .save(&path)
+    .unwrap();
+
+    let mut maker = restart();
+    let restored = maker.adaptive_state();
+    assert_eq!(restored.current_k, dec!(0.8));
+    assert_eq!(
+        (restored.successful_fill_count, restored.attempt_count),
+        (3, 12)
+    );
+
+    // Nothing learned, nothing written
+    clock.advance(TimeDelta::minutes(30));
+    maker.maintenance(clock.now());
+    assert_eq!(
+        AdaptiveState::load(&path).unwrap().saved_at,
+        start - TimeDelta::minutes(10)
+    );
+
+    // A fill moves k, the next maintenance tick saves it
+    maker.active_orders = vec![resting_bid("filled", dec!(99.9), dec!(0.8))];
+    assert!(maker.handle_execution_fill("filled", dec!(99.9)));
+    maker.maintenance(clock.now());
+    let saved = AdaptiveState::load(&path).unwrap();
+    assert_eq!(saved, maker.adaptive_state());
+    assert_eq!(saved.successful_fill_count, 4);
+    assert_eq!(restart().adaptive_state(), saved);
+
+    // Down for longer than the maximum age
+    clock.advance(TimeDelta::hours(2));
+    let fresh = restart().adaptive_state();
+    assert_eq!(fresh.current_k, dec!(0.5));
+    assert_eq!((fresh.successful_fill_count, fresh.attempt_count), (0, 0));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn signal_research_joins_imbalance_with_the_forward_return() {
+    let mut maker = maker(MarketMakerConfig::default());
+    let (outcome_tx, mut outcome_rx) = tokio::sync::mpsc::unbounded_channel();
+    maker.set_signal_research(ForwardHorizon::Trades(1), outcome_tx);
+
+    // Bids thin out, the imbalance turns negative
+    let mut update = depth_update(2);
+    update.bids = levels(&[(dec!(100), dec!(0.5))]);
+    maker.handle_depth_update(update).unwrap();
+    let signal = maker.current_imbalance().unwrap();
+    let mid = maker.order_book.mid_price.unwrap();
+    assert!(outcome_rx.try_recv().is_err());
+
+    // The mid drops and a trade prints
+    let mut update = depth_update(3);
+    update.bids = levels(&[(dec!(100), dec!(0)), (dec!(99.9), dec!(1))]);
+    maker.handle_depth_update(update).unwrap();
+    let now = Utc::now();
+    maker
+        .handle_trade(TradeEventData {
+            event_time: now,
+            symbol: "BTCUSDT".to_string(),
+            trade_id: 1,
+            price: dec!(99.9),
+            quantity: dec!(0.1),
+            trade_time: now,
+            buyer_market_maker: true,
+        })
+        .unwrap();
+
+    let outcome = outcome_rx.try_recv().unwrap();
+    assert_eq!(outcome.imbalance, signal);
+    assert_eq!(outcome.mid_price, mid);
+    assert_eq!(outcome.forward_mid_price, dec!(100));
+    assert!(outcome.forward_return < Decimal::ZERO);
+}
+
+#[test]
+fn session_export_writes_orders_and_pnl() {
+    let mut maker = maker(MarketMakerConfig::default());
+    let mut filled = resting_bid("filled", dec!(99.5), dec!(0.5));
+    filled.status = OrderStatus::Filled;
+    filled.filled_at = Some(Utc::now());
+    filled.fill_quality = Some(FillQuality::Benign);
+    let mut cancelled = resting_bid("cancelled", dec!(99), dec!(0.5));
+    cancelled.status = OrderStatus::Cancelled;
+    cancelled.cancel_reason = Some(CancelReason::TooFar);
+    maker.filled_orders.push(filled);
+    maker.cancelled_orders.push(cancelled);
+
+    let dir = std::env::temp_dir();
+    let json_path = dir.join(format!("session_export_{}.json", std::process::id()));
+    maker.export_session(&json_path).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+    std::fs::remove_file(&json_path).unwrap();
+    assert_eq!(json["filled_orders"][0]["id"], "filled");
+    assert_eq!(json["filled_orders"][0]["k_factor_used"], "0.5");
+    assert_eq!(json["cancelled_orders"][0]["cancel_reason"], "TooFar");
+    assert_eq!(json["net_pnl"], maker.net_pnl().to_string());
+
+    let csv_path = dir.join(format!("session_export_{}.csv", std::process::id()));
+    maker.export_session(&csv_path).unwrap();
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    std::fs::remove_file(&csv_path).unwrap();
+    let rows: Vec<_> = csv.lines().filter(|line| !line.starts_with('#')).collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].starts_with("status,id,price"));
+    assert!(rows[1].starts_with("Filled,filled,99.5,"));
+    assert!(rows[2].starts_with("Cancelled,cancelled,99,"));
+    assert!(rows[2].ends_with(",TooFar,,"));
+}
+
+/// A maker whose book is dominated by asks, so a small k is halved again by the imbalance
+fn sell_pressure_maker(min_distance_pct: Decimal) -> MarketMaker {
+    sell_pressure_maker_with(
+        MarketMakerConfig::builder()
+            .base_k(dec!(0.01))
+            .min_distance_pct(min_distance_pct)
+            .build()
+            .unwrap(),
+        Arc::new(SystemClock),
+    )
+}
+
+fn sell_pressure_maker_with(config: MarketMakerConfig, clock: Arc<dyn Clock>) -> MarketMaker {
+    let book =
+        OrderBookState::from_levels(vec![(dec!(100), dec!(0.1))], vec![(dec!(100.1), dec!(5))]);
+    traded_maker(config, book, clock)
+}
+
+/// A maker on `book` that has seen four small trades, enough for a volatility estimate
+fn traded_maker(
+    config: MarketMakerConfig,
+    book: OrderBookState,
+    clock: Arc<dyn Clock>,
+) -> MarketMaker {
+    let start = clock.now();
+    let mut maker = MarketMaker::with_clock(config, book, RecentTrades::default(), clock);
+    for (i, price) in [dec!(100), dec!(100.001), dec!(100), dec!(100.001)]
+        .into_iter()
+        .enumerate()
+    {
+        let time = start + TimeDelta::milliseconds(i as i64);
+        maker
+            .handle_trade(TradeEventData {
+                event_time: time,
+                symbol: "BTCUSDT".to_string(),
+                trade_id: i as u64,
+                price,
+                quantity: dec!(0.1),
+                trade_time: time,
+                buyer_market_maker: false,
+            })
+            .unwrap();
+    }
+    maker
+}
+
+#[test]
+fn join_spread_quotes_a_tick_inside_a_wide_spread() {
+    let config = MarketMakerConfig::builder()
+        .quote_mode(QuoteMode::JoinSpread {
+            min_spread_bps: dec!(10),
+            max_volatility: dec!(0.005),
+        })
+        .order_rate_limits(100, 1000)
+        .build()
+        .unwrap();
+    // 20bps wide
+    let book = OrderBookState::from_levels(
+        vec![(dec!(100), dec!(1)), (dec!(99.9), dec!(1))],
+        vec![(dec!(100.2), dec!(1)), (dec!(100.3), dec!(1))],
+    );
+    let mut maker = traded_maker(config, book, Arc::new(SystemClock));
+    let quotes = |maker: &MarketMaker| {
+        maker
+            .active_orders
+            .iter()
+            .map(|order| (order.side.clone(), order.price))
+            .collect::<Vec<_>>()
+    };
+
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    assert_eq!(
+        quotes(&maker),
+        [
+            (OrderSide::Buy, dec!(100.01)),
+            (OrderSide::Sell, dec!(100.19))
+        ]
+    );
+
+    // A taking buy lifts our ask
+    let now = Utc::now();
+    maker
+        .handle_trade(TradeEventData {
+            event_time: now,
+            symbol: "BTCUSDT".to_string(),
+            trade_id: 10,
+            price: dec!(100.19),
+            quantity: dec!(0.01),
+            trade_time: now,
+            buyer_market_maker: false,
+        })
+        .unwrap();
+    assert_eq!(maker.net_inventory(), dec!(-0.01));
+    assert_eq!(maker.gross_pnl(), dec!(0.0009));
+
+    // Someone steps ahead of our bid, so it moves up and the ask is quoted again
+    let mut update = depth_update(3);
+    update.bids = levels(&[(dec!(100.05), dec!(2))]);
+    maker.handle_depth_update(update).unwrap();
+    assert_eq!(
+        maker.cancelled_orders.last().unwrap().cancel_reason,
+        Some(CancelReason::Requote)
+    );
+    assert_eq!(
+        quotes(&maker),
+        [
+            (OrderSide::Buy, dec!(100.06)),
+            (OrderSide::Sell, dec!(100.19))
+        ]
+    );
+
+    // Too tight to be worth it any more
+    let mut update = depth_update(4);
+    update.asks = levels(&[(dec!(100.08), dec!(1))]);
+    maker.handle_depth_update(update).unwrap();
+    assert!(maker.active_orders.is_empty());
+}
+
+#[test]
+fn drawdown_past_the_maximum_halts_until_equity_recovers() {
+    let mut maker = sell_pressure_maker_with(
+        MarketMakerConfig::builder()
+            .base_k(dec!(0.01))
+            .order_size(dec!(1))
+            .fee_model(FeeModel {
+                maker_bps: Decimal::ZERO,
+                taker_bps: Decimal::ZERO,
+            })
+            .max_drawdown(Some(dec!(2)), Some(dec!(0.5)))
+            .build()
+            .unwrap(),
+        Arc::new(SystemClock),
+    );
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    let filled = maker.active_orders[0].id.clone();
+    // Bought at 99.95 against a mid of 100.05
+    assert!(maker.handle_execution_fill(&filled, dec!(99.95)));
+    assert_eq!(maker.equity(), dec!(0.1));
+    maker.handle_depth_update(depth_update(3)).unwrap();
+    assert!(!maker.active_orders.is_empty());
+
+    // The mid drops to 97.55
+    let mut crash = depth_update(4);
+    crash.bids = levels(&[(dec!(100), dec!(0)), (dec!(97.5), dec!(1))]);
+    crash.asks = levels(&[(dec!(100.1), dec!(0)), (dec!(97.6), dec!(5))]);
+    maker.handle_depth_update(crash).unwrap();
+
+    assert!(maker.is_halted());
+    assert_eq!(
+        maker.halt_reason(),
+        Some(HaltReason::MaxDrawdown {
+            peak_equity: dec!(0.1),
+            equity: dec!(-2.4),
+        })
+    );
+    assert!(maker.active_orders.is_empty());
+    assert_eq!(
+        maker.cancelled_orders.last().unwrap().cancel_reason,
+        Some(CancelReason::Halted)
+    );
+    maker.handle_depth_update(depth_update(5)).unwrap();
+    assert!(maker.active_orders.is_empty());
+
+    // Back to a mid of 99.55, half a unit below the peak
+    let mut recovery = depth_update(6);
+    recovery.bids = levels(&[(dec!(97.5), dec!(0)), (dec!(99.5), dec!(1))]);
+    recovery.asks = levels(&[(dec!(97.6), dec!(0)), (dec!(99.6), dec!(5))]);
+    maker.handle_depth_update(recovery).unwrap();
+    assert_eq!(maker.drawdown(), dec!(0.5));
+    assert_eq!(maker.halt_reason(), None);
+}
+
+#[test]
+fn halt_without_a_resume_level_waits_for_a_reset() {
+    let mut maker = sell_pressure_maker_with(
+        MarketMakerConfig::builder()
+            .base_k(dec!(0.01))
+            .max_drawdown(Some(dec!(0.0001)), None)
+            .build()
+            .unwrap(),
+        Arc::new(SystemClock),
+    );
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    let filled = maker.active_orders[0].id.clone();
+    // Fees on a fill with no edge are a loss from the zero equity peak
+    assert!(maker.handle_execution_fill(&filled, dec!(100.05)));
+    assert!(maker.is_halted());
+
+    maker.handle_depth_update(depth_update(3)).unwrap();
+    assert!(maker.is_halted());
+    assert!(maker.active_orders.is_empty());
+
+    maker.reset_halt();
+    assert!(!maker.is_halted());
+    assert_eq!(maker.drawdown(), Decimal::ZERO);
+    maker.handle_depth_update(depth_update(4)).unwrap();
+    assert_eq!(maker.active_orders.len(), 1);
+}
+
+#[test]
+fn drawdown_resume_level_needs_a_larger_maximum() {
+    let config = |max_drawdown, resume| {
+        MarketMakerConfig::builder()
+            .max_drawdown(max_drawdown, resume)
+            .build()
+    };
+    assert!(config(Some(dec!(10)), Some(dec!(5))).is_ok());
+    assert!(config(Some(dec!(10)), Some(dec!(10))).is_err());
+    assert!(config(None, Some(dec!(5))).is_err());
+    assert!(config(Some(Decimal::ZERO), None).is_err());
+}
+
+#[test]
+fn flatten_cost_walks_the_opposite_side_of_the_book() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .fee_model(FeeModel {
+                maker_bps: Decimal::ZERO,
+                taker_bps: dec!(10),
+            })
+            .build()
+            .unwrap(),
+    );
+    assert_eq!(maker.flatten_cost(), Some(Decimal::ZERO));
+
+    // Long 2 sells 1 at 100 and 1 at 99 against a mid of 100.05, plus 0.1% of 199
+    maker.set_net_inventory(dec!(2));
+    assert_eq!(maker.flatten_cost(), Some(dec!(1.1) + dec!(0.199)));
+
+    // Short 1 buys at 100.1
+    maker.set_net_inventory(dec!(-1));
+    assert_eq!(maker.flatten_cost(), Some(dec!(0.05) + dec!(0.1001)));
+
+    // More than both levels hold
+    maker.set_net_inventory(dec!(3));
+    assert_eq!(maker.flatten_cost(), None);
+}
+
+#[test]
+fn cloned_makers_diverge_independently() {
+    let maker = sell_pressure_maker(dec!(0.05));
+
+    let mut quiet = maker.clone();
+    quiet.handle_depth_update(depth_update(2)).unwrap();
+    let mut thinned = maker.clone();
+    let mut update = depth_update(2);
+    update.asks = levels(&[(dec!(100.1), dec!(0.1))]);
+    thinned.handle_depth_update(update).unwrap();
+
+    // With the sell pressure gone k is raised rather than halved
+    assert_eq!(quiet.active_orders[0].k_factor_used, dec!(0.005));
+    assert_eq!(thinned.active_orders[0].k_factor_used, dec!(0.015));
+    assert_ne!(quiet.order_book, thinned.order_book);
+    // The original saw neither update
+    assert!(maker.active_orders.is_empty());
+    assert_eq!(maker.recent_trades, quiet.recent_trades);
+    assert_eq!(maker.volume_profile, quiet.volume_profile);
+}
+
+#[test]
+fn stink_bid_near_the_best_bid_rests_at_least_a_tick_below_it() {
+    // The raw price sits above the best bid of 100, with no minimum distance to fall back on
+    let mut maker = sell_pressure_maker(Decimal::ZERO);
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    assert_eq!(maker.active_orders.len(), 1);
+    assert_eq!(maker.active_orders[0].price, dec!(99.99));
+}
+
+#[test]
+fn ask_heavy_book_halves_k() {
+    let mut maker = sell_pressure_maker(dec!(0.05));
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    assert!(maker.order_book.sell_pressure().unwrap() > dec!(0.7));
+    assert!(maker.current_imbalance().unwrap() < dec!(-0.7));
+    assert_eq!(maker.active_orders[0].k_factor_used, dec!(0.005));
+}
+
+#[test]
+fn tanh_normalization_tames_a_nearly_empty_ask_side() {
+    let imbalance = |normalization| {
+        let book = OrderBookState::from_levels(
+            vec![(dec!(100), dec!(10))],
+            vec![(dec!(100.1), dec!(0.0001))],
+        );
+        let config = MarketMakerConfig::builder()
+            .imbalance_normalization(normalization)
+            .build()
+            .unwrap();
+        MarketMaker::new(config, book, RecentTrades::default())
+            .current_imbalance()
+            .unwrap()
+    };
+
+    assert!(imbalance(ImbalanceNormalization::Raw) > dec!(0.9999));
+    let squashed = imbalance(ImbalanceNormalization::Tanh { scale: dec!(1) });
+    // tanh(1)
+    assert!(squashed > dec!(0.76) && squashed < dec!(0.77));
+
+    let clip = ImbalanceNormalization::Clip;
+    assert_eq!(clip.apply(dec!(1.5)), Decimal::ONE);
+    assert_eq!(clip.apply(dec!(-30)), Decimal::NEGATIVE_ONE);
+    let tanh = ImbalanceNormalization::Tanh { scale: dec!(2) };
+    assert_eq!(tanh.apply(Decimal::ZERO), Decimal::ZERO);
+    assert_eq!(tanh.apply(dec!(-0.4)), -tanh.apply(dec!(0.4)));
+    assert!(tanh.apply(dec!(100)) <= Decimal::ONE);
+}
+
+#[test]
+fn min_distance_is_a_percentage_of_the_best_bid() {
+    let mut maker = sell_pressure_maker(dec!(0.05));
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    assert_eq!(maker.active_orders.len(), 1);
+    assert_eq!(maker.active_orders[0].price, dec!(99.95));
+}
+
+#[test]
+fn preview_reports_actions_without_taking_them() {
+    let mut maker = sell_pressure_maker(dec!(0.05));
+    maker
+        .active_orders
+        .push(resting_bid("deep", dec!(90), dec!(0.01)));
+
+    let actions = maker.preview();
+    assert_eq!(actions.len(), 2);
+    let Action::Cancel(cancel) = &actions[0] else {
+        panic!("expected a cancel first, got {actions:?}");
+    };
+    assert_eq!(cancel.order_id, "deep");
+    assert_eq!(cancel.reason, CancelReason::TooFar);
+    let Action::Place(place) = &actions[1] else {
+        panic!("expected a placement, got {actions:?}");
+    };
+    assert_eq!(place.price, dec!(99.95));
+    assert_eq!(maker.active_orders.len(), 1);
+    assert!(maker.cancelled_orders.is_empty());
+
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    assert_eq!(cancel_reason(&maker, "deep"), Some(CancelReason::TooFar));
+    assert_eq!(maker.active_orders.len(), 1);
+    assert_eq!(maker.active_orders[0].price, place.price);
+}
+
+#[test]
+fn full_slots_go_to_the_order_with_the_higher_expected_edge() {
+    // The stink bid would rest at 99.95, 3bps deeper than the resting order at 99.98
+    let maker_with_threshold = |threshold_bps: Decimal, max_orders_per_second: u32| {
+        let config = MarketMakerConfig::builder()
+            .order_rate_limits(max_orders_per_second, 100)
+            .base_k(dec!(0.01))
+            .min_distance_pct(dec!(0.05))
+            .max_active_orders(1)
+            .cancel_band(dec!(5), Decimal::ZERO)
+            .replace_edge_threshold_bps(Some(threshold_bps))
+            .build()
+            .unwrap();
+        let mut maker = sell_pressure_maker_with(config, Arc::new(SystemClock));
+        maker
+            .active_orders
+            .push(resting_bid("shallow", dec!(99.98), dec!(0.01)));
+        maker
+    };
+
+    let mut maker = maker_with_threshold(dec!(2), 5);
+    let actions = maker.preview();
+    let [Action::Cancel(cancel), Action::Place(place)] = actions.as_slice() else {
+        panic!("expected a replacement, got {actions:?}");
+    };
+    assert_eq!(cancel.order_id, "shallow");
+    assert_eq!(cancel.reason, CancelReason::Replaced);
+    assert_eq!(place.price, dec!(99.95));
+
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    assert_eq!(
+        cancel_reason(&maker, "shallow"),
+        Some(CancelReason::Replaced)
+    );
+    assert_eq!(maker.active_orders.len(), 1);
+    assert_eq!(maker.active_orders[0].price, dec!(99.95));
+
+    // Budget for the cancel but not the placement leaves the slot alone
+    let mut maker = maker_with_threshold(dec!(2), 1);
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    assert_eq!(maker.active_orders[0].id, "shallow");
+    assert!(maker.cancelled_orders.is_empty());
+    assert_eq!(maker.throttle_hits(), 1);
+
+    // Not enough better to be worth the churn
+    let mut maker = maker_with_threshold(dec!(5), 5);
+    assert!(maker.preview().is_empty());
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    assert_eq!(maker.active_orders[0].id, "shallow");
+    assert!(maker.cancelled_orders.is_empty());
+}
+
+#[test]
+fn fair_value_follows_the_configured_source() {
+    let fair_value = |source: MidSource| {
+        let config = MarketMakerConfig::builder()
+            .mid_source(source, 1)
+            .build()
+            .unwrap();
+        let book =
+            OrderBookState::from_levels(vec![(dec!(100), dec!(3))], vec![(dec!(100.1), dec!(1))]);
+        let mut maker = MarketMaker::new(config, book, RecentTrades::default());
+        let before_trades = maker.fair_value();
+        let now = Utc::now();
+        maker
+            .handle_trade(TradeEventData {
+                event_time: now,
+                symbol: "BTCUSDT".to_string(),
+                trade_id: 1,
+                price: dec!(99.9),
+                quantity: dec!(0.1),
+                trade_time: now,
+                buyer_market_maker: true,
+            })
+            .unwrap();
+        (before_trades, maker.fair_value())
+    };
+
+    assert_eq!(fair_value(MidSource::Mid).1, Some(dec!(100.05)));
+    // Pulled towards the thinner ask
+    assert_eq!(fair_value(MidSource::Microprice).1, Some(dec!(100.075)));
+    assert_eq!(fair_value(MidSource::WeightedMid).1, Some(dec!(100.05)));
+    assert_eq!(fair_value(MidSource::LastTrade), (None, Some(dec!(99.9))));
+}
+
+#[test]
+fn distance_in_sigmas_scales_by_price_volatility() {
+    assert_eq!(
+        distance_in_sigmas(dec!(99), dec!(100), dec!(0.5)),
+        Some(dec!(2))
+    );
+    assert_eq!(distance_in_sigmas(dec!(99), dec!(100), Decimal::ZERO), None);
+}
+
+#[test]
+fn orders_shallower_than_the_sigma_minimum_are_skipped_and_cancelled() {
+    let config = MarketMakerConfig::builder()
+        .base_k(dec!(0.01))
+        .min_distance_pct(dec!(0.05))
+        .cancel_band(dec!(100), dec!(0.5))
+        .min_distance_sigmas(Some(Decimal::ZERO))
+        .build()
+        .unwrap();
+    let mut maker = sell_pressure_maker_with(config, Arc::new(SystemClock));
+    let actions = maker.preview();
+    let [Action::Place(place)] = actions.as_slice() else {
+        panic!("expected a placement, got {actions:?}");
+    };
+    assert_eq!(
+        place.distance_sigmas,
+        distance_in_sigmas(
+            place.price,
+            place.mid_price,
+            place.volatility * place.mid_price
+        )
+    );
+
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    assert_eq!(
+        maker.active_orders[0].distance_sigmas,
+        place.distance_sigmas
+    );
+
+    // The same order is too shallow once the minimum is above its depth
+    maker.config.min_distance_sigmas = place.distance_sigmas.map(|sigmas| sigmas * dec!(2));
+    let actions = maker.preview();
+    let [Action::Cancel(cancel)] = actions.as_slice() else {
+        panic!("expected only a cancel, got {actions:?}");
+    };
+    assert_eq!(cancel.reason, CancelReason::TooClose);
+}
+
+#[test]
+fn imbalance_hovering_at_a_threshold_does_not_flip_the_band() {
+    let bands = |hysteresis: Decimal| {
+        let mut maker = maker(
+            MarketMakerConfig::builder()
+                .imbalance_hysteresis(hysteresis)
+                .build()
+                .unwrap(),
+        );
+        // Against 10 on the ask, bids of 6 and 5 sit either side of the -0.3 threshold
+        [6, 5, 6, 5, 4, 6, 5, 7]
+            .into_iter()
+            .enumerate()
+            .map(|(i, bid_size)| {
+                let mut update = depth_update(i as u64 + 2);
+                update.bids = levels(&[(dec!(100), Decimal::from(bid_size))]);
+                update.asks = levels(&[(dec!(100.1), dec!(10))]);
+                maker.handle_depth_update(update).unwrap();
+                maker.imbalance_band()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let (balanced, sell) = (ImbalanceBand::Balanced, ImbalanceBand::SellPressure);
+    assert_eq!(
+        bands(Decimal::ZERO),
+        [
+            balanced, sell, balanced, sell, sell, balanced, sell, balanced
+        ]
+    );
+    // Only -0.43 (bid of 4) gets past -0.4 and only -0.18 (bid of 7) back past -0.2
+    assert_eq!(
+        bands(dec!(0.1)),
+        [
+            balanced, balanced, balanced, balanced, sell, sell, sell, balanced
+        ]
+    );
+}
+
+#[test]
+fn placement_needs_an_edge_over_round_trip_fees() {
+    // The bid rests at 99.95 against a mid of 100.05, a 9.995bps discount
+    let placed = |maker_bps: Decimal, buffer_bps: Decimal| {
+        let config = MarketMakerConfig::builder()
+            .base_k(dec!(0.01))
+            .min_distance_pct(dec!(0.05))
+            .fee_model(FeeModel {
+                maker_bps,
+                taker_bps: maker_bps,
+            })
+            .min_edge_buffer_bps(buffer_bps)
+            .build()
+            .unwrap();
+        let mut maker = sell_pressure_maker_with(config, Arc::new(SystemClock));
+        maker.handle_depth_update(depth_update(2)).unwrap();
+        maker.active_orders.len()
+    };
+
+    assert_eq!(placed(dec!(4.9975), Decimal::ZERO), 1);
+    assert_eq!(placed(dec!(5), Decimal::ZERO), 0);
+    assert_eq!(placed(dec!(4), dec!(2)), 0);
+    assert_eq!(placed(dec!(4), dec!(1.995)), 1);
+}
+
+#[test]
+fn each_depth_decision_emits_a_feature_snapshot() {
+    let (feature_tx, mut feature_rx) = tokio::sync::mpsc::unbounded_channel();
+    // No trades yet, so no volatility to price a stink bid from
+    let mut cold = maker(MarketMakerConfig::default());
+    cold.set_feature_sender(feature_tx.clone());
+    cold.handle_depth_update(depth_update(2)).unwrap();
+    let mut warm = sell_pressure_maker(dec!(0.05));
+    warm.set_feature_sender(feature_tx);
+    warm.handle_depth_update(depth_update(2)).unwrap();
+
+    let held = feature_rx.try_recv().unwrap();
+    assert!(!held.order_placed);
+    assert_eq!(held.volatility, None);
+    assert_eq!(held.depth_imbalance, Some(Decimal::ZERO));
+    let placed = feature_rx.try_recv().unwrap();
+    assert!(placed.order_placed);
+    assert_eq!(placed.mid_price, Some(dec!(100.05)));
+    assert_eq!(placed.spread, Some(dec!(0.1)));
+    assert_eq!(placed.volatility, warm.recent_trades.volatility);
+    assert!(feature_rx.try_recv().is_err());
+
+    let mut csv = Vec::new();
+    let mut writer = FeatureCsvWriter::new(&mut csv);
+    writer.write(&held).unwrap();
+    writer.write(&placed).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("timestamp,mid_price,"));
+    assert!(lines[1].ends_with(",,false"));
+    assert!(lines[2].ends_with(",true"));
+}
+
+#[test]
+fn orders_placed_in_the_same_millisecond_get_distinct_ids() {
+    let clock = MockClock::new(DateTime::from_timestamp_millis(1_700_000_000_000).unwrap());
+    let config = MarketMakerConfig::builder()
+        .base_k(dec!(0.01))
+        .min_distance_pct(dec!(0.05))
+        .build()
+        .unwrap();
+    let mut maker = sell_pressure_maker_with(config, Arc::new(clock));
+    for update_id in 2..5 {
+        maker.handle_depth_update(depth_update(update_id)).unwrap();
+    }
+
+    let mut ids = maker
+        .active_orders
+        .iter()
+        .chain(&maker.cancelled_orders)
+        .map(|order| order.id.clone())
+        .collect::<Vec<_>>();
+    assert!(ids.len() >= 2);
+    let placed = ids.len();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), placed);
+}
+
+#[test]
+fn no_stink_bids_until_warmed_up() {
+    let start = Utc::now();
+    let clock = MockClock::new(start);
+    let config = MarketMakerConfig::builder()
+        .base_k(dec!(0.01))
+        .min_distance_pct(dec!(0.05))
+        .warmup(4, 2, Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let mut maker = sell_pressure_maker_with(config, Arc::new(clock.clone()));
+
+    // Enough trades, but only one book update and no time has passed
+    let mut update = depth_update(2);
+    update.event_time = start;
+    maker.handle_depth_update(update).unwrap();
+    assert!(!maker.is_warmed_up());
+    assert!(maker.active_orders.is_empty());
+    assert!(maker.preview().is_empty());
+
+    clock.advance(TimeDelta::seconds(10));
+    let mut update = depth_update(3);
+    update.event_time = start + TimeDelta::seconds(10);
+    maker.handle_depth_update(update).unwrap();
+    assert!(maker.is_warmed_up());
+    assert_eq!(maker.active_orders.len(), 1);
+}
+
+#[test]
+fn multiplicative_policy_moves_k_within_its_bounds() {
+    let policy = MultiplicativePolicy::new(dec!(0.1));
+    let fill = |adverse| KOutcome::Filled {
+        distance_pct: dec!(0.5),
+        adverse,
+        time_to_fill: TimeDelta::seconds(30),
+    };
+
+    assert_eq!(policy.next_k(dec!(1), &fill(false)), dec!(0.9));
+    assert_eq!(policy.next_k(dec!(1), &fill(true)), dec!(1.1));
+    assert_eq!(policy.next_k(dec!(1), &KOutcome::Cancelled), dec!(1.1));
+    assert_eq!(policy.next_k(dec!(0.1), &fill(false)), dec!(0.1));
+    assert_eq!(policy.next_k(dec!(3), &KOutcome::Cancelled), dec!(3));
+}
+
+/// Jumps straight to a fixed k after any outcome
+#[derive(Debug)]
+struct FixedK(Decimal);
+
+impl KFactorPolicy for FixedK {
+    fn next_k(&self, _current_k: Decimal, _outcome: &KOutcome) -> Decimal {
+        self.0
+    }
+}
+
+#[test]
+fn k_policy_is_pluggable() {
+    let mut maker = sell_pressure_maker(dec!(0.05)).with_k_policy(FixedK(dec!(0.02)));
+    maker
+        .active_orders
+        .push(resting_bid("deep", dec!(90), dec!(0.01)));
+    maker.handle_depth_update(depth_update(2)).unwrap();
+
+    // The cancel set k to 0.02, halved by the sell pressure
+    assert_eq!(cancel_reason(&maker, "deep"), Some(CancelReason::TooFar));
+    assert_eq!(maker.active_orders[0].k_factor_used, dec!(0.01));
+}
+
+#[test]
+fn execution_report_fills_replace_trade_inference() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .fill_source(FillSource::ExecutionReport)
+            .build()
+            .unwrap(),
+    );
+    maker.active_orders = vec![resting_bid("live", dec!(99.5), dec!(1))];
+
+    // The tape trading through the bid doesn't mean it filled
+    let time = Utc::now();
+    maker
+        .handle_trade(TradeEventData {
+            event_time: time,
+            symbol: "BTCUSDT".to_string(),
+            trade_id: 1,
+            price: dec!(99),
+            quantity: dec!(1),
+            trade_time: time,
+            buyer_market_maker: true,
+        })
+        .unwrap();
+    assert_eq!(maker.active_orders.len(), 1);
+
+    assert!(!maker.handle_execution_fill("unknown", dec!(99.5)));
+    assert!(maker.handle_execution_fill("live", dec!(99.5)));
+    assert!(maker.active_orders.is_empty());
+    assert_eq!(
+        maker.filled_orders[0].fill_source,
+        Some(FillSource::ExecutionReport)
+    );
+    assert_eq!(maker.fill_count_by_source(FillSource::ExecutionReport), 1);
+    assert_eq!(maker.fill_count_by_source(FillSource::TradeInference), 0);
+    assert!(
+        maker
+            .get_statistics()
+            .contains("Total Filled Orders: 1 (inferred 0, reported 1)")
+    );
+    assert_eq!(maker.adaptive_state().successful_fill_count, 1);
+}
+
+#[test]
+fn flatten_cancels_every_order_even_when_throttled() {
+    let mut maker = maker(
+        MarketMakerConfig::builder()
+            .order_rate_limits(1, 1)
+            .build()
+            .unwrap(),
+    );
+    maker.active_orders = vec![
+        resting_bid("a", dec!(99), dec!(1)),
+        resting_bid("b", dec!(98), dec!(1)),
+    ];
+
+    maker.flatten();
+    maker.flatten();
+
+    assert!(maker.active_orders.is_empty());
+    assert_eq!(maker.cancelled_orders.len(), 2);
+    assert_eq!(cancel_reason(&maker, "b"), Some(CancelReason::Shutdown));
+}
+
+fn window_ticker(price_change_percent: Decimal) -> WindowTickerData {
+    WindowTickerData {
+        event_type: "1hTicker".to_string(),
+        event_time: 0,
+        symbol: "BTCUSDT".to_string(),
+        price_change: Decimal::ZERO,
+        price_change_percent,
+        open_price: dec!(100),
+        high_price: dec!(100),
+        low_price: dec!(100),
+        close_price: dec!(100),
+        weighted_avg_price: dec!(100),
+        volume: Decimal::ZERO,
+        quote_volume: Decimal::ZERO,
+        open_time: 0,
+        close_time: 0,
+        first_trade_id: 0,
+        last_trade_id: 0,
+        trade_count: 0,
+    }
+}
+
+#[test]
+fn rolling_window_trend_biases_k_within_the_cap() {
+    let mut trending = maker(
+        MarketMakerConfig::builder()
+            .trend_filter(dec!(0.1), dec!(0.3))
+            .build()
+            .unwrap(),
+    );
+    assert_eq!(trending.trend_bias(), Decimal::ZERO);
+
+    trending.handle_window_ticker(&window_ticker(dec!(2)));
+    assert_eq!(trending.trend_bias(), dec!(0.2));
+
+    trending.handle_window_ticker(&window_ticker(dec!(-5)));
+    assert_eq!(trending.trend_bias(), dec!(-0.3));
+
+    // Off by default
+    let mut untrended = maker(MarketMakerConfig::default());
+    untrended.handle_window_ticker(&window_ticker(dec!(5)));
+    assert_eq!(untrended.trend_bias(), Decimal::ZERO);
+}
+
+#[test]
+fn orders_missing_from_the_exchange_are_unconfirmed() {
+    let mut maker = maker(MarketMakerConfig::default());
+    maker.active_orders = vec![
+        resting_bid("still-open", dec!(99), dec!(1)),
+        resting_bid("gone", dec!(98), dec!(1)),
+    ];
+
+    maker.reconcile_orders(&["still-open".to_string(), "unknown".to_string()]);
+
+    assert_eq!(maker.active_orders.len(), 1);
+    assert_eq!(maker.active_orders[0].id, "still-open");
+    assert_eq!(
+        cancel_reason(&maker, "gone"),
+        Some(CancelReason::Unconfirmed)
+    );
+}
+
+#[test]
+fn reconnect_policy_decides_whether_orders_survive() {
+    let mut trusting = maker(
+        MarketMakerConfig::builder()
+            .reconnect_policy(ReconnectPolicy::Trust)
+            .build()
+            .unwrap(),
+    );
+    trusting.active_orders = vec![resting_bid("a", dec!(99), dec!(1))];
+    trusting.handle_reconnect();
+    assert_eq!(trusting.active_orders.len(), 1);
+
+    let mut cautious = maker(MarketMakerConfig::default());
+    cautious.active_orders = vec![resting_bid("a", dec!(99), dec!(1))];
+    cautious.handle_reconnect();
+    assert!(cautious.active_orders.is_empty());
+    assert_eq!(
+        cancel_reason(&cautious, "a"),
+        Some(CancelReason::Unconfirmed)
+    );
+}
+
+#[test]
+fn suppression_window_pulls_orders_in_maintenance() {
+    let now = Utc::now();
+    let mut maker = sell_pressure_maker_with(
+        MarketMakerConfig::builder()
+            .base_k(dec!(0.01))
+            .max_book_staleness(Duration::from_secs(24 * 60 * 60))
+            .suppression_windows(vec![SuppressionWindow::Once {
+                start: now + TimeDelta::hours(1),
+                end: now + TimeDelta::hours(2),
+            }])
+            .build()
+            .unwrap(),
+        Arc::new(SystemClock),
+    );
+
+    assert!(maker.is_suppressed(now + TimeDelta::hours(1)));
+    assert!(!maker.is_suppressed(now + TimeDelta::hours(2)));
+
+    maker.handle_depth_update(depth_update(2)).unwrap();
+    let placed = maker.active_orders[0].id.clone();
+    maker.maintenance(now + TimeDelta::minutes(90));
+
+    assert!(maker.active_orders.is_empty());
+    assert_eq!(
+        cancel_reason(&maker, &placed),
+        Some(CancelReason::Suppressed)
+    );
+}
+
+#[test]
+fn flow_spike_pulls_quotes_for_its_window() {
+    let mut maker = maker(MarketMakerConfig::default()).with_flow_spike_detector(FlowSpikeConfig {
+        window: TimeDelta::seconds(1),
+        baseline: TimeDelta::seconds(10),
+        min_trades: 5,
+        ..FlowSpikeConfig::default()
+    });
+    maker.active_orders = vec![resting_bid("resting", dec!(97), dec!(1))];
+
+    let start = Utc::now() - TimeDelta::seconds(12);
+    let mut trade_id = 0;
+    let mut trade = |time: DateTime<Utc>, buyer_market_maker: bool| {
+        trade_id += 1;
+        TradeEventData {
+            event_time: time,
+            symbol: "BTCUSDT".to_string(),
+            trade_id,
+            price: dec!(100),
+            quantity: dec!(0.1),
+            trade_time: time,
+            buyer_market_maker,
+        }
+    };
+    // A calm trade a second, alternating sides
+    for second in 0..=11 {
+        let calm = trade(start + TimeDelta::seconds(second), second % 2 == 0);
+        maker.handle_trade(calm).unwrap();
+    }
+    assert!(!maker.is_suppressed(Utc::now()));
+
+    // Then a burst of taking sells
+    let burst_start = start + TimeDelta::seconds(11);
+    for i in 1..=8 {
+        let sell = trade(burst_start + TimeDelta::milliseconds(i * 100), true);
+        maker.handle_trade(sell).unwrap();
+    }
+    assert_eq!(
+        cancel_reason(&maker, "resting"),
+        Some(CancelReason::Suppressed)
+    );
+    assert!(maker.is_suppressed(Utc::now()));
+    assert!(!maker.is_suppressed(Utc::now() + TimeDelta::seconds(2)));
+}
+
+#[test]
+fn empty_suppression_windows_are_rejected() {
+    let now = Utc::now();
+    assert!(
+        MarketMakerConfig::builder()
+            .suppression_windows(vec![SuppressionWindow::Once {
+                start: now,
+                end: now,
+            }])
+            .build()
+            .is_err()
+    );
+}
+
+#[test]
+fn daily_suppression_windows_wrap_past_midnight() {
+    let at = |time: &str| DateTime::parse_from_rfc3339(time).unwrap().to_utc();
+    let window = SuppressionWindow::Daily {
+        start: NaiveTime::from_hms_opt(23, 30, 0).unwrap(),
+        duration: Duration::from_secs(60 * 60),
+    };
+
+    assert!(window.contains(at("2024-03-01T23:30:00Z")));
+    assert!(window.contains(at("2024-03-02T00:15:00Z")));
+    assert!(!window.contains(at("2024-03-02T00:30:00Z")));
+    assert!(!window.contains(at("2024-03-01T23:29:59Z")));
+    assert!(!window.contains(at("2024-03-01T12:00:00Z")));
+}
@@ -0,0 +1,873 @@
+use chrono::{TimeDelta, Utc};
+use marketmakerlib::{
+    binance::data::{BookTickerEvent, DepthSnapshot, DepthUpdate, OfferData, TradeEventData},
+    market_maker::OrderSide,
+    order_book_state::{
+        BookDelta, BootstrapError, DepthWeighting, LevelDivergence, OrderBookState,
+        UpdateSemantics, WEIGHTED_IMBALANCE_DEPTH,
+    },
+    recent_trades::RecentTrades,
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn levels(levels: &[(Decimal, Decimal)]) -> Vec<OfferData> {
+    levels
+        .iter()
+        .map(|&(price, size)| OfferData { price, size })
+        .collect()
+}
+
+fn book(bids: &[(Decimal, Decimal)], asks: &[(Decimal, Decimal)]) -> OrderBookState {
+    OrderBookState::from_levels(bids.to_vec(), asks.to_vec())
+}
+
+#[test]
+fn own_bids_are_excluded_from_imbalance() {
+    let asks = [(dec!(101), dec!(2)), (dec!(102), dec!(3))];
+    let without_own = book(&[(dec!(100), dec!(1)), (dec!(99), dec!(4))], &asks);
+
+    // Our large bid joins the best level
+    let mut with_own = book(&[(dec!(100), dec!(51)), (dec!(99), dec!(4))], &asks);
+    with_own.set_own_bids([(dec!(100), dec!(50))]);
+
+    assert_eq!(with_own.imbalance(), without_own.imbalance());
+    assert_eq!(
+        with_own.imbalance_depth(2usize),
+        without_own.imbalance_depth(2usize)
+    );
+    assert_eq!(
+        with_own.weighted_relative_imbalance(2usize, DepthWeighting::Harmonic),
+        without_own.weighted_relative_imbalance(2usize, DepthWeighting::Harmonic)
+    );
+    // The cached metrics are refreshed too
+    assert_eq!(with_own.imbalance, without_own.imbalance);
+    assert!(without_own.weighted_imbalance.is_some());
+    assert_eq!(with_own.weighted_imbalance, without_own.weighted_imbalance);
+}
+
+#[test]
+fn level_of_only_own_bids_is_skipped() {
+    let asks = [(dec!(101), dec!(2))];
+    let without_own = book(&[(dec!(99), dec!(4))], &asks);
+
+    let mut with_own = book(&[(dec!(100), dec!(50)), (dec!(99), dec!(4))], &asks);
+    with_own.set_own_bids([(dec!(100), dec!(50))]);
+
+    assert_eq!(with_own.imbalance(), without_own.imbalance());
+}
+
+fn update(first_update_id: u64, final_update_id: u64, bid: (Decimal, Decimal)) -> DepthUpdate {
+    DepthUpdate {
+        event_time: Utc::now(),
+        symbol: "BTCUSDT".to_string(),
+        first_update_id,
+        final_update_id,
+        bids: levels(&[bid]),
+        asks: Vec::new(),
+    }
+}
+
+fn snapshot(last_update_id: u64) -> DepthSnapshot {
+    DepthSnapshot {
+        last_update_id,
+        bids: levels(&[(dec!(100), dec!(1))]),
+        asks: levels(&[(dec!(101), dec!(1))]),
+    }
+}
+
+#[test]
+fn bootstrap_skips_stale_updates_and_applies_the_rest() {
+    let buffered = [
+        update(1, 5, (dec!(100), dec!(9))),
+        update(6, 12, (dec!(100), dec!(2))),
+        update(13, 14, (dec!(99), dec!(3))),
+    ];
+    let mut book = OrderBookState::default();
+    book.bootstrap(snapshot(10), &buffered).unwrap();
+
+    assert_eq!(book.bids.get(&dec!(100)), Some(&dec!(2)));
+    assert_eq!(book.bids.get(&dec!(99)), Some(&dec!(3)));
+}
+
+#[test]
+fn bootstrap_rejects_buffers_that_dont_bridge_the_snapshot() {
+    let mut book = OrderBookState::default();
+
+    let too_old = book.bootstrap(snapshot(10), &[update(15, 20, (dec!(100), dec!(2)))]);
+    assert_eq!(
+        too_old,
+        Err(BootstrapError::SnapshotTooOld {
+            snapshot_update_id: 10,
+            first_buffered_id: 15,
+        })
+    );
+
+    let gap = book.bootstrap(
+        snapshot(10),
+        &[
+            update(8, 11, (dec!(100), dec!(2))),
+            update(14, 15, (dec!(100), dec!(3))),
+        ],
+    );
+    assert_eq!(
+        gap,
+        Err(BootstrapError::Gap {
+            expected: 12,
+            first_update_id: 14,
+            final_update_id: 15,
+        })
+    );
+    // Nothing was applied
+    assert!(book.bids.is_empty());
+}
+
+#[test]
+fn process_buffer_starts_at_the_update_straddling_the_snapshot() {
+    let processed = |buffered: Vec<DepthUpdate>| {
+        let mut book = OrderBookState::default();
+        book.apply_snapshot(snapshot(10));
+        book.process_buffer(buffered.into()).map(|()| book)
+    };
+
+    // Starting before the snapshot, the stale updates are skipped
+    let book = processed(vec![
+        update(1, 5, (dec!(100), dec!(9))),
+        update(6, 9, (dec!(100), dec!(8))),
+        update(9, 12, (dec!(100), dec!(2))),
+        update(13, 14, (dec!(99), dec!(3))),
+    ])
+    .unwrap();
+    assert_eq!(book.bids.get(&dec!(100)), Some(&dec!(2)));
+    assert_eq!(book.bids.get(&dec!(99)), Some(&dec!(3)));
+
+    // Starting right after the snapshot
+    let book = processed(vec![
+        update(11, 11, (dec!(100), dec!(4))),
+        update(12, 13, (dec!(99), dec!(1))),
+    ])
+    .unwrap();
+    assert_eq!(book.bids.get(&dec!(100)), Some(&dec!(4)));
+    assert_eq!(book.bids.get(&dec!(99)), Some(&dec!(1)));
+
+    // Starting after the snapshot, the updates in between were missed
+    let error = processed(vec![update(15, 20, (dec!(100), dec!(2)))]).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<BootstrapError>(),
+        Some(&BootstrapError::SnapshotTooOld {
+            snapshot_update_id: 10,
+            first_buffered_id: 15,
+        })
+    );
+
+    // Nothing past the snapshot yet
+    let book = processed(vec![update(1, 10, (dec!(100), dec!(9)))]).unwrap();
+    assert_eq!(book.bids.get(&dec!(100)), Some(&dec!(1)));
+}
+
+#[test]
+fn depth_curve_accumulates_from_the_touch_outward() {
+    let book = book(
+        &[
+            (dec!(99), dec!(2)),
+            (dec!(100), dec!(1)),
+            (dec!(98), dec!(3)),
+        ],
+        &[(dec!(101), dec!(1)), (dec!(102), dec!(4))],
+    );
+    let chart = book.depth_chart_data();
+    assert_eq!(
+        chart.bids,
+        vec![
+            (dec!(100), dec!(1)),
+            (dec!(99), dec!(3)),
+            (dec!(98), dec!(6))
+        ]
+    );
+    assert_eq!(chart.asks, book.depth_curve(OrderSide::Sell));
+    assert_eq!(chart.asks, vec![(dec!(101), dec!(1)), (dec!(102), dec!(5))]);
+}
+
+#[test]
+fn empty_update_only_advances_the_sequence() {
+    let mut book = book(&[(dec!(100), dec!(3))], &[(dec!(101), dec!(1))]);
+    let imbalance = book.imbalance;
+    let mid_price = book.mid_price;
+    assert!(imbalance.is_some() && mid_price.is_some());
+
+    let event_time = Utc::now();
+    book.process_update(DepthUpdate {
+        event_time,
+        symbol: "BTCUSDT".to_string(),
+        first_update_id: 2,
+        final_update_id: 3,
+        bids: Vec::new(),
+        asks: Vec::new(),
+    })
+    .unwrap();
+
+    assert_eq!(book.last_update_time(), event_time);
+    assert_eq!(book.imbalance, imbalance);
+    assert_eq!(book.mid_price, mid_price);
+    assert_eq!(book.best_bid, Some((dec!(100), dec!(3))));
+    // The next update must follow on from the bumped sequence
+    assert!(
+        book.process_update(DepthUpdate {
+            event_time,
+            symbol: "BTCUSDT".to_string(),
+            first_update_id: 5,
+            final_update_id: 5,
+            bids: Vec::new(),
+            asks: Vec::new(),
+        })
+        .is_err()
+    );
+}
+
+#[test]
+fn delta_updates_add_to_the_resting_size() {
+    let mut book = OrderBookState::with_update_semantics(UpdateSemantics::Delta);
+    book.apply_snapshot(snapshot(10));
+
+    book.process_update(DepthUpdate {
+        asks: levels(&[(dec!(101), dec!(-0.4))]),
+        ..update(11, 11, (dec!(100), dec!(0.5)))
+    })
+    .unwrap();
+    assert_eq!(book.bids.get(&dec!(100)), Some(&dec!(1.5)));
+    assert_eq!(book.asks.get(&dec!(101)), Some(&dec!(0.6)));
+
+    // A new level starts from nothing, and a level taken to or past zero is removed
+    book.process_update(update(12, 12, (dec!(99), dec!(0.3))))
+        .unwrap();
+    book.process_update(update(13, 13, (dec!(100), dec!(-2))))
+        .unwrap();
+    assert_eq!(book.bids.get(&dec!(99)), Some(&dec!(0.3)));
+    assert_eq!(book.bids.get(&dec!(100)), None);
+    assert_eq!(book.best_bid, Some((dec!(99), dec!(0.3))));
+}
+
+#[test]
+fn absolute_updates_replace_the_resting_size() {
+    let mut book = OrderBookState::default();
+    assert_eq!(book.update_semantics, UpdateSemantics::Absolute);
+    book.apply_snapshot(snapshot(10));
+
+    book.process_update(update(11, 11, (dec!(100), dec!(0.5))))
+        .unwrap();
+    assert_eq!(book.bids.get(&dec!(100)), Some(&dec!(0.5)));
+}
+
+#[test]
+fn malformed_levels_are_skipped() {
+    let mut book = book(
+        &[(dec!(-1), dec!(5)), (dec!(100), dec!(1))],
+        &[(dec!(101), dec!(2)), (dec!(102), dec!(-3))],
+    );
+    assert_eq!(book.bids.len(), 1);
+    assert_eq!(book.asks.len(), 1);
+
+    let delta = book
+        .process_update(update(2, 2, (dec!(-1), dec!(2))))
+        .unwrap();
+    assert_eq!(delta, BookDelta::default());
+    let mut negative_size = update_empty(3);
+    negative_size.asks = levels(&[(dec!(101), dec!(-1))]);
+    book.process_update(negative_size).unwrap();
+
+    assert_eq!(book.best_bid, Some((dec!(100), dec!(1))));
+    assert_eq!(book.best_ask, Some((dec!(101), dec!(2))));
+    assert_eq!(book.mid_price, Some(dec!(100.5)));
+}
+
+#[test]
+fn process_update_reports_changed_and_removed_levels() {
+    let mut book = book(
+        &[(dec!(100), dec!(1)), (dec!(99), dec!(2))],
+        &[(dec!(101), dec!(1))],
+    );
+    let mut update = update(2, 2, (dec!(100), dec!(5)));
+    update.bids.extend(levels(&[
+        (dec!(99), dec!(0)),
+        (dec!(98), dec!(0)),
+        (dec!(97), dec!(4)),
+    ]));
+    update.asks = levels(&[(dec!(101), dec!(1)), (dec!(102), dec!(3))]);
+
+    let delta = book.process_update(update).unwrap();
+    assert_eq!(
+        delta,
+        BookDelta {
+            changed_bids: vec![(dec!(100), dec!(5)), (dec!(97), dec!(4))],
+            changed_asks: vec![(dec!(102), dec!(3))],
+            removed_bids: vec![dec!(99)],
+            removed_asks: Vec::new(),
+        }
+    );
+    assert!(book.process_update(update_empty(3)).unwrap().is_empty());
+}
+
+fn update_empty(update_id: u64) -> DepthUpdate {
+    let mut update = update(update_id, update_id, (dec!(100), dec!(5)));
+    update.bids.clear();
+    update
+}
+
+#[test]
+fn weighted_mid_averages_bid_and_ask_vwaps() {
+    let book = book(
+        &[(dec!(100), dec!(1)), (dec!(99), dec!(3))],
+        &[(dec!(101), dec!(2)), (dec!(102), dec!(2))],
+    );
+    // Bid VWAP 99.25, ask VWAP 101.5
+    assert_eq!(book.weighted_mid(2), Some(dec!(100.375)));
+    assert_eq!(book.weighted_mid(1), book.mid_price);
+    assert_eq!(book.weighted_mid(3), None);
+    assert_eq!(book.weighted_mid(0), None);
+}
+
+#[test]
+fn metrics_wait_for_minimum_liquidity() {
+    let mut book = OrderBookState::default().with_min_levels(2);
+    book.apply_snapshot(DepthSnapshot {
+        last_update_id: 1,
+        bids: levels(&[(dec!(100), dec!(1)), (dec!(99), dec!(2))]),
+        asks: levels(&[(dec!(101), dec!(1))]),
+    });
+
+    assert!(!book.liquidity_ok());
+    assert_eq!(book.mid_price, None);
+    assert_eq!(book.spread, None);
+    assert_eq!(book.imbalance, None);
+    assert_eq!(book.best_ask, Some((dec!(101), dec!(1))));
+
+    let mut update = update(2, 2, (dec!(100), dec!(1)));
+    update.asks = levels(&[(dec!(102), dec!(3))]);
+    book.process_update(update).unwrap();
+
+    assert!(book.liquidity_ok());
+    assert_eq!(book.mid_price, Some(dec!(100.5)));
+    assert_eq!(book.spread, Some(dec!(1)));
+}
+
+#[test]
+fn spread_bps_is_cached_alongside_the_fractional_spread() {
+    let book = book(&[(dec!(99.99), dec!(1))], &[(dec!(100.01), dec!(1))]);
+
+    assert_eq!(book.relative_spread, Some(dec!(0.0002)));
+    assert_eq!(book.spread_bps, Some(dec!(2)));
+    assert_eq!(book.relative_spread_bps(), book.spread_bps);
+}
+
+#[test]
+fn trade_decay_is_undone_by_the_next_depth_update() {
+    let mut book = book(
+        &[(dec!(100), dec!(1)), (dec!(99), dec!(2))],
+        &[(dec!(101), dec!(1))],
+    );
+
+    // A market sell printing 1 lot at 99 swept the best bid and took half the next level
+    book.apply_trade_decay(dec!(99), dec!(1), true);
+    assert_eq!(book.best_bid, Some((dec!(99), dec!(1))));
+    assert_eq!(book.bids.len(), 1);
+
+    // The confirming update only removes the best bid, the rest of the decay was wrong
+    book.process_update(update(2, 2, (dec!(100), dec!(0))))
+        .unwrap();
+    assert_eq!(book.best_bid, Some((dec!(99), dec!(2))));
+    assert_eq!(book.asks.get(&dec!(101)), Some(&dec!(1)));
+}
+
+#[test]
+fn trade_decay_empties_every_level_better_than_the_print() {
+    let mut book = book(
+        &[(dec!(100), dec!(1))],
+        &[
+            (dec!(101), dec!(3)),
+            (dec!(102), dec!(5)),
+            (dec!(103), dec!(1)),
+        ],
+    );
+
+    // The aggregate only carries the size printed at 102, the 101 level still went
+    book.apply_trade_decay(dec!(102), dec!(1), false);
+    assert_eq!(book.best_ask, Some((dec!(102), dec!(4))));
+    assert_eq!(book.asks.get(&dec!(103)), Some(&dec!(1)));
+}
+
+#[test]
+fn levels_beyond_the_cap_are_trimmed_furthest_first() {
+    let mut book = OrderBookState::default().with_max_levels_per_side(2);
+    book.apply_snapshot(DepthSnapshot {
+        last_update_id: 1,
+        bids: levels(&[
+            (dec!(100), dec!(1)),
+            (dec!(99), dec!(1)),
+            (dec!(98), dec!(1)),
+        ]),
+        asks: levels(&[
+            (dec!(101), dec!(1)),
+            (dec!(102), dec!(1)),
+            (dec!(103), dec!(1)),
+        ]),
+    });
+    assert_eq!(
+        book.bids.keys().collect::<Vec<_>>(),
+        [&dec!(99), &dec!(100)]
+    );
+    assert_eq!(
+        book.asks.keys().collect::<Vec<_>>(),
+        [&dec!(101), &dec!(102)]
+    );
+
+    // A new best bid pushes the lowest one out
+    book.process_update(update(2, 2, (dec!(100.5), dec!(1))))
+        .unwrap();
+    assert_eq!(
+        book.bids.keys().collect::<Vec<_>>(),
+        [&dec!(100), &dec!(100.5)]
+    );
+
+    // Depth based metrics can ask for more
+    book.reserve_metric_depth(5);
+    assert_eq!(book.max_levels_per_side(), Some(5));
+    book.reserve_metric_depth(3);
+    assert_eq!(book.max_levels_per_side(), Some(5));
+}
+
+fn book_ticker(
+    update_id: u64,
+    bid: (Decimal, Decimal),
+    ask: (Decimal, Decimal),
+) -> BookTickerEvent {
+    BookTickerEvent {
+        update_id,
+        symbol: "BTCUSDT".to_string(),
+        best_bid_price: bid.0,
+        best_bid_qty: bid.1,
+        best_ask_price: ask.0,
+        best_ask_qty: ask.1,
+    }
+}
+
+#[test]
+fn book_ticker_leads_until_the_depth_stream_catches_up() {
+    let mut book = book(&[(dec!(100), dec!(1))], &[(dec!(101), dec!(1))]);
+
+    // Stale tickers are already reflected in the levels
+    assert!(!book.apply_book_ticker(&book_ticker(1, (dec!(99), dec!(1)), (dec!(101), dec!(1)))));
+    assert_eq!(book.best_bid, Some((dec!(100), dec!(1))));
+
+    assert!(book.apply_book_ticker(&book_ticker(
+        5,
+        (dec!(100.5), dec!(3)),
+        (dec!(101), dec!(1))
+    )));
+    assert_eq!(book.best_bid, Some((dec!(100.5), dec!(3))));
+    assert_eq!(book.mid_price, Some(dec!(100.75)));
+
+    // A depth update still behind the ticker keeps its top of book
+    book.process_update(update(2, 3, (dec!(99), dec!(2))))
+        .unwrap();
+    assert_eq!(book.best_bid, Some((dec!(100.5), dec!(3))));
+
+    // Once the depth stream passes the ticker the levels take over again
+    book.process_update(update(4, 5, (dec!(100.5), dec!(2))))
+        .unwrap();
+    assert_eq!(book.best_bid, Some((dec!(100.5), dec!(2))));
+    assert!(!book.apply_book_ticker(&book_ticker(
+        5,
+        (dec!(100.5), dec!(3)),
+        (dec!(101), dec!(1))
+    )));
+}
+
+#[test]
+fn book_ticker_imbalance_leaves_out_our_own_bids() {
+    let mut book = book(
+        &[(dec!(100), dec!(1)), (dec!(99), dec!(1))],
+        &[(dec!(101), dec!(1)), (dec!(102), dec!(1))],
+    );
+    let weighted_imbalance = book.weighted_imbalance;
+
+    assert!(book.apply_book_ticker(&book_ticker(5, (dec!(100), dec!(3)), (dec!(101), dec!(1)))));
+    assert_eq!(book.imbalance, Some(dec!(0.5)));
+    // The depth weighted imbalance is still read off the levels
+    assert_eq!(book.weighted_imbalance, weighted_imbalance);
+
+    // The levels only have our bid at 100, the ticker shows more resting with it
+    book.set_own_bids([(dec!(100), dec!(1))]);
+    assert_eq!(book.imbalance, Some(dec!(1) / dec!(3)));
+}
+
+#[test]
+fn effective_spread_widens_with_size() {
+    let book = book(
+        &[(dec!(100), dec!(1)), (dec!(99), dec!(3))],
+        &[(dec!(101), dec!(1)), (dec!(103), dec!(1))],
+    );
+
+    assert_eq!(book.vwap_for_size(OrderSide::Buy, dec!(1)), Some(dec!(101)));
+    assert_eq!(book.vwap_for_size(OrderSide::Buy, dec!(2)), Some(dec!(102)));
+    assert_eq!(
+        book.vwap_for_size(OrderSide::Sell, dec!(2)),
+        Some(dec!(99.5))
+    );
+    assert_eq!(book.vwap_for_size(OrderSide::Buy, Decimal::ZERO), None);
+
+    // The quoted spread of 1 only holds for the top level's size
+    assert_eq!(book.effective_spread(dec!(1)), Some(dec!(1)));
+    assert_eq!(book.effective_spread(dec!(2)), Some(dec!(2.5)));
+    assert_eq!(
+        book.relative_effective_spread(dec!(2)),
+        Some(dec!(2.5) / dec!(100.5))
+    );
+
+    // Only 2 on offer
+    assert_eq!(book.effective_spread(dec!(3)), None);
+}
+
+#[test]
+fn depth_weightings_rank_the_same_book_differently() {
+    // Dense bids a cent apart, with the most size deepest; sparse asks a dollar apart
+    let book = book(
+        &[
+            (dec!(100), dec!(1)),
+            (dec!(99.99), dec!(1)),
+            (dec!(99.98), dec!(3)),
+        ],
+        &[
+            (dec!(100.01), dec!(2)),
+            (dec!(101), dec!(1)),
+            (dec!(102), dec!(1)),
+        ],
+    );
+    let imbalance = |weighting| book.weighted_relative_imbalance(3usize, weighting).unwrap();
+
+    let harmonic = imbalance(DepthWeighting::Harmonic);
+    let linear = imbalance(DepthWeighting::Linear);
+    let exponential = imbalance(DepthWeighting::Exponential { decay: dec!(0.5) });
+    let distance = imbalance(DepthWeighting::Distance {
+        scale_bps: dec!(10),
+    });
+
+    // By level the deep bid size counts for less the faster the weights decay
+    assert_eq!(exponential, dec!(-0.1));
+    assert!(exponential < harmonic && harmonic < linear && linear < Decimal::ZERO);
+    // By distance all the bids are within 3bps of the mid while the deep asks are 1% away
+    assert!(distance > dec!(0.3));
+}
+
+#[test]
+fn vwap_metrics_overflowing_decimal_are_none() {
+    // Each level's notional fits in a Decimal, the sum over the bids doesn't
+    let book = book(
+        &[
+            (dec!(100_000_000_000_000), dec!(1)),
+            (dec!(90_000_000_000_000), dec!(500_000_000_000_000)),
+            (dec!(80_000_000_000_000), dec!(500_000_000_000_000)),
+        ],
+        &[
+            (dec!(110_000_000_000_000), dec!(1)),
+            (dec!(120_000_000_000_000), dec!(1)),
+            (dec!(130_000_000_000_000), dec!(1)),
+        ],
+    );
+
+    assert_eq!(book.weighted_mid(1), Some(dec!(105_000_000_000_000)));
+    assert_eq!(book.weighted_mid(3), None);
+    assert_eq!(book.relative_mid_price_imbalance(3usize), None);
+    assert_eq!(book.relative_book_imbalance(3usize), None);
+}
+
+#[test]
+fn size_aggregates_overflowing_decimal_are_none() {
+    // Each level fits in a Decimal, the total of two doesn't
+    let huge = dec!(50_000_000_000_000_000_000_000_000_000);
+    let book = OrderBookState::from_levels(
+        vec![(dec!(100), huge), (dec!(99), huge)],
+        vec![(dec!(101), dec!(1)), (dec!(102), huge)],
+    );
+
+    assert!(book.imbalance_depth(1usize).is_some());
+    assert_eq!(book.imbalance_depth(2usize), None);
+    assert_eq!(book.imbalance_within_bps(dec!(500)), None);
+    assert_eq!(
+        book.cumulative_size_to_price(OrderSide::Buy, dec!(100)),
+        Some(huge)
+    );
+    assert_eq!(
+        book.cumulative_size_to_price(OrderSide::Buy, dec!(99)),
+        None
+    );
+    // The notional of the size overflows, though the size itself doesn't
+    assert_eq!(book.vwap_for_size(OrderSide::Buy, dec!(1)), Some(dec!(101)));
+    assert_eq!(book.vwap_for_size(OrderSide::Buy, huge), None);
+    assert_eq!(book.effective_spread(huge), None);
+}
+
+fn assert_no_imbalance(book: &OrderBookState, depth: usize) {
+    assert_eq!(book.imbalance(), None);
+    assert_eq!(book.imbalance_depth(depth), None);
+    assert_eq!(
+        book.weighted_relative_imbalance(depth, DepthWeighting::Harmonic),
+        None
+    );
+    assert_eq!(book.relative_book_imbalance(depth), None);
+    assert_eq!(book.relative_mid_price_imbalance(depth), None);
+    assert_eq!(book.imbalance_within_bps(dec!(10)), None);
+}
+
+#[test]
+fn imbalance_of_empty_and_one_sided_books_is_none() {
+    for depth in [0, 1, 3] {
+        assert_no_imbalance(&OrderBookState::default(), depth);
+        assert_no_imbalance(&book(&[(dec!(100), dec!(1))], &[]), depth);
+        assert_no_imbalance(&book(&[], &[(dec!(101), dec!(1))]), depth);
+    }
+}
+
+#[test]
+fn imbalance_at_zero_depth_or_a_single_level_range_is_none() {
+    let book = book(&[(dec!(100), dec!(1))], &[(dec!(101), dec!(3))]);
+
+    assert_eq!(book.imbalance(), Some(dec!(-0.5)));
+    assert_eq!(book.imbalance_depth(0usize), None);
+    assert_eq!(
+        book.weighted_relative_imbalance(0usize, DepthWeighting::Harmonic),
+        None
+    );
+    assert_eq!(book.relative_book_imbalance(0usize), None);
+    assert_eq!(book.relative_mid_price_imbalance(0usize), None);
+    // One level per side spans no price range
+    assert_eq!(book.relative_book_imbalance(1usize), None);
+}
+
+#[test]
+fn replayed_book_is_checked_against_a_later_snapshot() {
+    let mut replayed = OrderBookState::default();
+    replayed
+        .bootstrap(
+            snapshot(1),
+            &[
+                update(2, 2, (dec!(100), dec!(2))),
+                update(3, 3, (dec!(99.5), dec!(1))),
+            ],
+        )
+        .unwrap();
+
+    // A later REST snapshot, listed best first and cut off at two levels
+    let reference = DepthSnapshot {
+        last_update_id: 3,
+        bids: levels(&[(dec!(100), dec!(2.0000001)), (dec!(99.5), dec!(1))]),
+        asks: levels(&[(dec!(101), dec!(1))]),
+    };
+    assert_eq!(replayed.divergence_from(&reference, dec!(0.000001)), None);
+    replayed.assert_matches(&reference, dec!(0.000001));
+
+    let missed_update = DepthSnapshot {
+        bids: levels(&[(dec!(100), dec!(2)), (dec!(99.8), dec!(1))]),
+        ..reference
+    };
+    assert_eq!(
+        replayed.divergence_from(&missed_update, Decimal::ZERO),
+        Some(LevelDivergence {
+            side: OrderSide::Buy,
+            level: 1,
+            expected: (dec!(99.8), dec!(1)),
+            actual: Some((dec!(99.5), dec!(1))),
+        })
+    );
+}
+
+#[test]
+fn partial_snapshot_replaces_only_the_levels_it_covers() {
+    let mut book = book(
+        &[
+            (dec!(100), dec!(1)),
+            (dec!(99), dec!(1)),
+            (dec!(98), dec!(1)),
+        ],
+        &[
+            (dec!(101), dec!(1)),
+            (dec!(102), dec!(1)),
+            (dec!(103), dec!(1)),
+        ],
+    );
+
+    let partial = |last_update_id| DepthSnapshot {
+        last_update_id,
+        bids: levels(&[(dec!(100.5), dec!(2)), (dec!(99), dec!(4))]),
+        asks: levels(&[(dec!(101), dec!(5))]),
+    };
+    assert!(book.apply_partial_snapshot(partial(10)));
+
+    // 100 vanished within the snapshot's range, 98 is beyond it and kept
+    assert_eq!(
+        book.bids
+            .iter()
+            .rev()
+            .map(|(&p, &s)| (p, s))
+            .collect::<Vec<_>>(),
+        [
+            (dec!(100.5), dec!(2)),
+            (dec!(99), dec!(4)),
+            (dec!(98), dec!(1))
+        ]
+    );
+    assert_eq!(book.asks.len(), 3);
+    assert_eq!(book.asks.get(&dec!(101)), Some(&dec!(5)));
+    assert_eq!(book.best_bid, Some((dec!(100.5), dec!(2))));
+
+    // Diff updates carry on from the book so 98 catches up, older snapshots are ignored
+    assert!(!book.apply_partial_snapshot(partial(10)));
+    book.process_update(update(2, 2, (dec!(98), dec!(0))))
+        .unwrap();
+    assert_eq!(book.bids.len(), 2);
+}
+
+#[test]
+fn partial_snapshot_seeds_an_empty_book() {
+    let mut book = OrderBookState::default();
+
+    assert!(book.apply_partial_snapshot(DepthSnapshot {
+        last_update_id: 10,
+        bids: levels(&[(dec!(100), dec!(1))]),
+        asks: levels(&[(dec!(101), dec!(1))]),
+    }));
+    // Updates the snapshot covers are old news
+    assert!(
+        book.process_update(update(5, 10, (dec!(99), dec!(1))))
+            .unwrap()
+            .is_empty()
+    );
+    book.process_update(update(11, 11, (dec!(99), dec!(1))))
+        .unwrap();
+    assert_eq!(book.bids.len(), 2);
+}
+
+fn trades(prices: &[Decimal]) -> RecentTrades {
+    let start = Utc::now();
+    let mut recent = RecentTrades::new(10);
+    for (i, &price) in prices.iter().enumerate() {
+        let time = start + TimeDelta::seconds(i as i64);
+        recent.update(TradeEventData {
+            event_time: time,
+            symbol: "BTCUSDT".to_string(),
+            trade_id: i as u64,
+            price,
+            quantity: dec!(0.1),
+            trade_time: time,
+            buyer_market_maker: false,
+        });
+    }
+    recent
+}
+
+#[test]
+fn quality_score_combines_spread_depth_and_stability() {
+    let flat = trades(&[dec!(100), dec!(100)]);
+
+    // 1bp spread, 10 lots within 10bps of the mid and no price move
+    let tight = book(&[(dec!(100), dec!(5))], &[(dec!(100.01), dec!(5))]);
+    assert_eq!(tight.quality_score(&RecentTrades::new(10)), None);
+    assert_eq!(tight.quality_score(&flat), Some(Decimal::ONE));
+
+    // Half the target depth costs half the 0.3 depth weight
+    let thin = book(&[(dec!(100), dec!(2.5))], &[(dec!(100.01), dec!(2.5))]);
+    assert_eq!(thin.quality_score(&flat), Some(dec!(0.85)));
+
+    // 200bps wide, nothing near the mid and a 50bps move
+    let wide = book(&[(dec!(99), dec!(5))], &[(dec!(101), dec!(5))]);
+    let moving = trades(&[dec!(100), dec!(100.5)]);
+    assert_eq!(wide.quality_score(&moving), Some(Decimal::ZERO));
+}
+
+#[test]
+fn ask_heavy_book_has_positive_sell_pressure() {
+    let ask_heavy = book(&[(dec!(100), dec!(1))], &[(dec!(101), dec!(3))]);
+    assert_eq!(ask_heavy.imbalance(), Some(dec!(-0.5)));
+    assert_eq!(ask_heavy.sell_pressure(), Some(dec!(0.5)));
+
+    let bid_heavy = book(&[(dec!(100), dec!(3))], &[(dec!(101), dec!(1))]);
+    assert_eq!(bid_heavy.sell_pressure(), Some(dec!(-0.5)));
+}
+
+#[test]
+fn cached_metrics_track_the_top_of_book_across_updates() {
+    let mut book = book(
+        &[(dec!(100), dec!(1)), (dec!(99), dec!(2))],
+        &[(dec!(101), dec!(3)), (dec!(102), dec!(4))],
+    );
+    let assert_fresh = |book: &OrderBookState| {
+        assert_eq!(book.mid_price, book.mid_price());
+        assert_eq!(book.spread_bps, book.relative_spread_bps());
+        assert_eq!(book.microprice, book.microprice());
+        assert_eq!(book.imbalance, book.imbalance());
+        assert_eq!(
+            book.best_bid,
+            book.bids.last_key_value().map(|(&p, &s)| (p, s))
+        );
+    };
+
+    // Behind the touch, then resizing, removing and improving the best bid
+    let delta = book
+        .process_update(update(2, 2, (dec!(98), dec!(5))))
+        .unwrap();
+    assert!(!delta.touches_top(Some((dec!(100), dec!(1))), Some((dec!(101), dec!(3)))));
+    assert_fresh(&book);
+    for (id, bid) in [
+        (3, (dec!(100), dec!(3))),
+        (4, (dec!(100), dec!(0))),
+        (5, (dec!(100.5), dec!(1))),
+    ] {
+        book.process_update(update(id, id, bid)).unwrap();
+        assert_fresh(&book);
+    }
+    assert_eq!(book.best_bid, Some((dec!(100.5), dec!(1))));
+}
+
+#[test]
+fn weighted_imbalance_tracks_levels_behind_the_touch() {
+    let mut book = book(
+        &[(dec!(100), dec!(1)), (dec!(99), dec!(1))],
+        &[(dec!(101), dec!(1)), (dec!(102), dec!(1))],
+    );
+    let fresh = |book: &OrderBookState| {
+        book.weighted_relative_imbalance(WEIGHTED_IMBALANCE_DEPTH, DepthWeighting::default())
+    };
+    assert_eq!(book.weighted_imbalance, Some(Decimal::ZERO));
+
+    // Resizing, then removing, the level behind the best bid
+    book.process_update(update(2, 2, (dec!(99), dec!(50))))
+        .unwrap();
+    assert!(book.weighted_imbalance.unwrap() > dec!(0.8));
+    assert_eq!(book.weighted_imbalance, fresh(&book));
+    book.process_update(update(3, 3, (dec!(99), dec!(0))))
+        .unwrap();
+    assert_eq!(book.weighted_imbalance, fresh(&book));
+}
+
+#[test]
+fn book_from_levels_has_metrics_and_takes_updates() {
+    let mut book = OrderBookState::from_levels(
+        vec![
+            (dec!(100), dec!(3)),
+            (dec!(99), dec!(1)),
+            (dec!(98), dec!(0)),
+        ],
+        vec![(dec!(101), dec!(1))],
+    );
+    assert_eq!(book.bids.len(), 2);
+    assert_eq!(book.mid_price, Some(dec!(100.5)));
+    assert_eq!(book.imbalance, Some(dec!(0.5)));
+
+    let first_update_id = OrderBookState::SYNTHETIC_UPDATE_ID + 1;
+    book.process_update(update(
+        first_update_id,
+        first_update_id,
+        (dec!(100.5), dec!(1)),
+    ))
+    .unwrap();
+    assert_eq!(book.best_bid, Some((dec!(100.5), dec!(1))));
+}
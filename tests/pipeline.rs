@@ -0,0 +1,121 @@
+//! The whole pipeline on a recorded session: raw combined stream messages are parsed, the
+//! book is bootstrapped from a REST snapshot and the diffs buffered while it was fetched, and
+//! the live diffs and trades then drive a maker.
+
+use chrono::DateTime;
+use marketmakerlib::{
+    binance::{
+        BinanceMessage,
+        data::{BinanceEvent, DepthSnapshot},
+    },
+    clock::MockClock,
+    market_maker::{FillSource, MarketMaker, MarketMakerConfig, OrderSide},
+    order_book_state::OrderBookState,
+    recent_trades::RecentTrades,
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::sync::Arc;
+
+/// REST depth snapshot taken while the stream below was buffered
+const SNAPSHOT: &str = include_str!("fixtures/btcusdt_snapshot.json");
+/// Combined stream messages, one per line: a diff predating the snapshot, one straddling it,
+/// a few quiet trades, a seller sweeping through the stink bid and the book settling lower
+const STREAM: &str = include_str!("fixtures/btcusdt_stream.jsonl");
+
+/// Feeds the recorded session to a maker on `config`, checking the invariants after every
+/// message once the book is live. Trades before then are dropped, as there's no maker yet.
+fn replay(config: MarketMakerConfig) -> MarketMaker {
+    let snapshot: DepthSnapshot = serde_json::from_str(SNAPSHOT).unwrap();
+    let snapshot_update_id = snapshot.last_update_id;
+    let mut snapshot = Some(snapshot);
+    let clock = MockClock::new(DateTime::from_timestamp_millis(1_700_000_000_000).unwrap());
+    let mut buffer = Vec::new();
+    let mut maker: Option<MarketMaker> = None;
+
+    for line in STREAM.lines().filter(|line| !line.trim().is_empty()) {
+        let event = BinanceMessage::from_str_into_market_data(line)
+            .unwrap_or_else(|e| panic!("failed to parse {line}: {e:?}"));
+        match (event, maker.as_mut()) {
+            (BinanceEvent::DepthUpdate(update), None) => {
+                clock.set(update.event_time);
+                let caught_up = update.final_update_id > snapshot_update_id;
+                buffer.push(update);
+                if caught_up {
+                    let mut book = OrderBookState::default();
+                    book.bootstrap(snapshot.take().unwrap(), &buffer).unwrap();
+                    maker = Some(MarketMaker::with_clock(
+                        config.clone(),
+                        book,
+                        RecentTrades::default(),
+                        Arc::new(clock.clone()),
+                    ));
+                }
+            }
+            (_, None) => {}
+            (BinanceEvent::DepthUpdate(update), Some(maker)) => {
+                clock.set(update.event_time);
+                maker.handle_depth_update(update).unwrap();
+            }
+            (BinanceEvent::AggTrade(trade), Some(maker)) => {
+                clock.set(trade.event_time);
+                maker.handle_trade(trade).unwrap();
+            }
+            (other, Some(_)) => panic!("unexpected event in the recording: {other:?}"),
+        }
+        if let Some(maker) = &maker {
+            assert_invariants(maker);
+        }
+    }
+    maker.expect("the stream never caught up with the snapshot")
+}
+
+fn assert_invariants(maker: &MarketMaker) {
+    let book = &maker.order_book;
+    if let (Some((best_bid, _)), Some((best_ask, _))) = (book.best_bid, book.best_ask) {
+        assert!(best_bid < best_ask, "crossed book {best_bid} / {best_ask}");
+        // Resting orders never take liquidity
+        for order in &maker.active_orders {
+            match order.side {
+                OrderSide::Buy => assert!(order.price < best_ask, "marketable {order:?}"),
+                OrderSide::Sell => assert!(order.price > best_bid, "marketable {order:?}"),
+            }
+        }
+    }
+    assert!(maker.active_orders.len() <= maker.config.max_active_orders);
+    assert!(maker.fees_paid() >= Decimal::ZERO);
+    assert_eq!(maker.net_pnl(), maker.gross_pnl() - maker.fees_paid());
+    assert_eq!(maker.equity(), maker.net_pnl() + maker.unrealized_pnl());
+}
+
+#[test]
+fn recorded_session_bootstraps_quotes_and_fills() {
+    let maker = replay(
+        MarketMakerConfig::builder()
+            .base_k(dec!(0.01))
+            .build()
+            .unwrap(),
+    );
+
+    // The diff predating the snapshot was dropped, the straddling one applied
+    assert_eq!(maker.order_book.bids.get(&dec!(99.8)), None);
+    assert_eq!(maker.order_book.asks.get(&dec!(100.3)), Some(&dec!(1)));
+    assert_eq!(maker.order_book.best_bid, Some((dec!(99.95), dec!(0.4))));
+    assert_eq!(maker.order_book.best_ask, Some((dec!(100.1), dec!(4))));
+
+    // The stink bid 0.05% under the 100 best bid was swept at its own price
+    assert_eq!(maker.fill_count_by_source(FillSource::TradeInference), 1);
+    let fill = &maker.filled_orders[0];
+    assert_eq!(fill.price, dec!(99.95));
+    assert_eq!(fill.reference_mid, dec!(100.05));
+    assert_eq!(maker.net_inventory(), fill.size);
+    assert_eq!(maker.gross_pnl(), dec!(0.1) * fill.size);
+    assert_eq!(
+        maker.fees_paid(),
+        maker.config.fee_model.maker_fee(dec!(99.95), fill.size)
+    );
+    assert!(maker.net_pnl() > Decimal::ZERO);
+
+    let statistics = maker.get_statistics();
+    assert!(statistics.contains("Stink Bid Statistics"), "{statistics}");
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use marketmakerlib::{
+    binance::data::TradeEventData,
+    realized_volatility::{
+        RealizedVolatility, annualize, annualize_per_day, annualize_per_minute, annualize_per_trade,
+    },
+    recent_trades::Trade,
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Trades with increasing ids, repeated ids are ignored as duplicates
+fn trade(price: Decimal, trade_time: DateTime<Utc>) -> Trade {
+    static NEXT_TRADE_ID: AtomicU64 = AtomicU64::new(1);
+    TradeEventData {
+        event_time: trade_time,
+        symbol: "BTCUSDT".to_string(),
+        trade_id: NEXT_TRADE_ID.fetch_add(1, Ordering::Relaxed),
+        price,
+        quantity: dec!(0.1),
+        trade_time,
+        buyer_market_maker: false,
+    }
+    .into()
+}
+
+fn assert_close(actual: Option<Decimal>, expected: Decimal) {
+    let actual = actual.expect("a completed bucket");
+    assert!(
+        (actual - expected).abs() < dec!(0.0000001),
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn reports_completed_minute_buckets() {
+    // Aligned to a minute boundary
+    let start = DateTime::from_timestamp_millis(1_699_999_980_000).unwrap();
+    let mut rv = RealizedVolatility::new(TimeDelta::minutes(1), 10);
+
+    rv.update(&trade(dec!(100), start));
+    rv.update(&trade(dec!(101), start + TimeDelta::seconds(30)));
+    assert_eq!(rv.latest(), None);
+
+    // The 1% move is the only return in the first minute
+    rv.update(&trade(dec!(101), start + TimeDelta::seconds(60)));
+    assert_close(rv.latest(), dec!(0.01));
+
+    rv.update(&trade(dec!(103.02), start + TimeDelta::seconds(90)));
+    rv.update(&trade(dec!(103.02), start + TimeDelta::seconds(120)));
+    assert_close(rv.latest(), dec!(0.02));
+    assert_close(rv.rolling_average(), dec!(0.015));
+    assert!(rv.annualized().unwrap() > dec!(0.02) * dec!(700));
+}
+
+#[test]
+fn quiet_minutes_count_as_zero_and_stale_trades_are_ignored() {
+    let start = DateTime::from_timestamp_millis(1_699_999_980_000).unwrap();
+    let mut rv = RealizedVolatility::new(TimeDelta::minutes(1), 3);
+
+    rv.update(&trade(dec!(100), start));
+    rv.update(&trade(dec!(101), start + TimeDelta::seconds(10)));
+    rv.update(&trade(dec!(101), start + TimeDelta::minutes(3)));
+    assert_eq!(rv.latest(), Some(Decimal::ZERO));
+    assert_close(rv.rolling_average(), dec!(0.01) / dec!(3));
+
+    // A late print from the first minute neither reopens it nor moves the last price
+    rv.update(&trade(dec!(50), start + TimeDelta::seconds(20)));
+    rv.update(&trade(dec!(101), start + TimeDelta::minutes(4)));
+    assert_eq!(rv.latest(), Some(Decimal::ZERO));
+    assert_eq!(rv.rolling_average(), Some(Decimal::ZERO));
+}
+
+#[test]
+fn annualizing_scales_by_the_root_of_periods_per_year() {
+    assert_close(Some(annualize(dec!(0.01), dec!(10000))), dec!(1));
+    assert_eq!(annualize(dec!(0.01), dec!(-1)), Decimal::ZERO);
+
+    // 1% a day is about 19.1% a year over 365 days
+    assert_close(Some(annualize_per_day(dec!(0.01))), dec!(0.1910497));
+    // √525600 minutes a year
+    assert_close(Some(annualize_per_minute(dec!(0.001))), dec!(0.7249828));
+    // One trade a second is 31.536m trades a year
+    assert_close(
+        Some(annualize_per_trade(dec!(0.0001), dec!(1))),
+        dec!(0.5615692),
+    );
+    assert_eq!(
+        annualize_per_trade(dec!(0.0001), dec!(2)),
+        annualize(dec!(0.0001), dec!(63072000))
+    );
+}
@@ -0,0 +1,205 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use marketmakerlib::{
+    binance::data::{AggregateTrade, TradeEventData},
+    market_maker::OrderSide,
+    recent_trades::{AdaptiveWindow, PriceMovement, RecentTrades, VolatilityWeighting},
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn trade(trade_id: u64, price: Decimal, trade_time: DateTime<Utc>) -> TradeEventData {
+    TradeEventData {
+        event_time: trade_time,
+        symbol: "BTCUSDT".to_string(),
+        trade_id,
+        price,
+        quantity: dec!(0.1),
+        trade_time,
+        buyer_market_maker: false,
+    }
+}
+
+#[test]
+fn out_of_order_trade_does_not_corrupt_volatility() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut recent_trades = RecentTrades::new(10);
+    for (i, price) in [dec!(100), dec!(101), dec!(100.5), dec!(101.5)]
+        .into_iter()
+        .enumerate()
+    {
+        recent_trades.update(trade(i as u64, price, start + TimeDelta::seconds(i as i64)));
+    }
+    let volatility = recent_trades.volatility;
+
+    // A stale print with a wild price arriving after a reconnect
+    recent_trades.update(trade(99, dec!(50), start - TimeDelta::seconds(10)));
+
+    assert_eq!(recent_trades.len(), 4);
+    assert_eq!(recent_trades.volatility, volatility);
+}
+
+#[test]
+fn overflowing_return_variance_leaves_volatility_unknown() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut recent_trades = RecentTrades::new(10);
+    recent_trades.update(trade(1, dec!(0.0000000001), start));
+    // A return of about 10^15, whose square is past the largest Decimal
+    recent_trades.update(trade(2, dec!(100_000), start + TimeDelta::seconds(1)));
+
+    assert_eq!(recent_trades.len(), 2);
+    assert_eq!(recent_trades.volatility, None);
+}
+
+#[test]
+fn reset_clears_the_window_but_remembers_trade_ids() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut recent_trades = RecentTrades::new(10);
+    recent_trades.update(trade(1, dec!(100), start));
+    recent_trades.update(trade(2, dec!(101), start + TimeDelta::seconds(1)));
+
+    recent_trades.reset();
+    assert!(recent_trades.is_empty());
+    assert_eq!(recent_trades.volatility, None);
+
+    // A replay of an earlier trade stays ignored
+    recent_trades.update(trade(2, dec!(101), start + TimeDelta::seconds(1)));
+    assert!(recent_trades.is_empty());
+    recent_trades.update(trade(3, dec!(102), start + TimeDelta::seconds(2)));
+    assert_eq!(recent_trades.last_price(), Some(dec!(102)));
+}
+
+#[test]
+fn shrinking_the_window_keeps_the_latest_trades() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut recent_trades = RecentTrades::new(10);
+    for (i, price) in [dec!(100), dec!(101), dec!(100.5), dec!(101.5)]
+        .into_iter()
+        .enumerate()
+    {
+        recent_trades.update(trade(i as u64, price, start + TimeDelta::seconds(i as i64)));
+    }
+
+    recent_trades.resize_window(2);
+    assert_eq!(recent_trades.window_size(), 2);
+    assert_eq!(recent_trades.len(), 2);
+    assert_eq!(
+        recent_trades.price_movement(2usize).unwrap().absolute,
+        dec!(1)
+    );
+
+    recent_trades.update(trade(4, dec!(102), start + TimeDelta::seconds(4)));
+    assert_eq!(recent_trades.len(), 2);
+}
+
+#[test]
+fn price_movement_reports_size_percentage_and_direction() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut recent_trades = RecentTrades::new(10);
+    assert_eq!(recent_trades.price_movement(2usize), None);
+    for (i, price) in [dec!(100), dec!(102), dec!(99)].into_iter().enumerate() {
+        recent_trades.update(trade(i as u64, price, start + TimeDelta::seconds(i as i64)));
+    }
+
+    let fall = PriceMovement {
+        absolute: dec!(-1),
+        pct: dec!(-1),
+        direction: OrderSide::Sell,
+    };
+    assert_eq!(recent_trades.price_movement(3usize), Some(fall.clone()));
+    // Asking for more trades than held measures from the oldest
+    assert_eq!(recent_trades.price_movement(10usize), Some(fall));
+    assert_eq!(recent_trades.price_movement(0usize), None);
+
+    // Only the prints within a second of the latest: 102 down to 99
+    let recent = recent_trades
+        .price_movement_over(TimeDelta::seconds(1))
+        .unwrap();
+    assert_eq!(recent.absolute, dec!(-3));
+    assert_eq!(recent.direction, OrderSide::Sell);
+
+    recent_trades.update(trade(3, dec!(99), start + TimeDelta::seconds(3)));
+    let flat = recent_trades.price_movement(2usize).unwrap();
+    assert_eq!(flat.absolute, Decimal::ZERO);
+    assert_eq!(flat.direction, OrderSide::Buy);
+}
+
+#[test]
+fn adaptive_window_tracks_the_trade_rate() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut recent_trades = RecentTrades::new(100).with_adaptive_window(AdaptiveWindow {
+        horizon: TimeDelta::seconds(10),
+        min_trades: 5,
+        max_trades: 50,
+    });
+    assert_eq!(recent_trades.window_size(), 50);
+
+    // One trade a second, so 10 seconds holds about 10 trades
+    for i in 0..20 {
+        recent_trades.update(trade(i, dec!(100), start + TimeDelta::seconds(i as i64)));
+    }
+    assert_eq!(recent_trades.trades_per_second(), Some(dec!(1)));
+    assert_eq!(recent_trades.window_size(), 10);
+
+    // Twice the rate takes twice the trades to cover the same 10 seconds
+    let busy_start = start + TimeDelta::seconds(20);
+    for i in 0..40 {
+        recent_trades.update(trade(
+            100 + i,
+            dec!(100),
+            busy_start + TimeDelta::milliseconds(500 * i as i64),
+        ));
+    }
+    assert_eq!(recent_trades.trades_per_second(), Some(dec!(2)));
+    assert_eq!(recent_trades.window_size(), 20);
+}
+
+fn agg_trade(id: u64, price: &str, num_trades: u64, trade_time: DateTime<Utc>) -> AggregateTrade {
+    let time = trade_time.timestamp_millis();
+    serde_json::from_str(&format!(
+        r#"{{"e":"aggTrade","E":{time},"s":"BTCUSDT","a":{id},"p":"{price}","q":"0.1","f":{first},"l":{last},"T":{time},"m":false,"M":true}}"#,
+        first = id * 1_000,
+        last = id * 1_000 + num_trades - 1,
+    ))
+    .unwrap()
+}
+
+#[test]
+fn weighting_by_trade_count_discounts_thin_prints() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    // Busy prints barely move the price, a single trade print jumps it
+    let prints = || {
+        [
+            agg_trade(1, "100", 500, start),
+            agg_trade(2, "100.01", 500, start + TimeDelta::seconds(1)),
+            agg_trade(3, "100", 500, start + TimeDelta::seconds(2)),
+            agg_trade(4, "102", 1, start + TimeDelta::seconds(3)),
+        ]
+    };
+
+    let mut unweighted = RecentTrades::new(10);
+    unweighted.update_many(prints().into_iter());
+    let mut weighted = RecentTrades::new(10).with_weighting(VolatilityWeighting::NumTrades);
+    weighted.update_many(prints().into_iter());
+
+    let unweighted = unweighted.volatility.unwrap();
+    let weighted = weighted.volatility.unwrap();
+    assert!(
+        weighted < unweighted / dec!(5),
+        "{weighted} vs {unweighted}"
+    );
+}
+
+#[test]
+fn duplicate_aggregate_trades_are_ignored() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut recent_trades = RecentTrades::new(10);
+    assert!(recent_trades.update(agg_trade(1, "100", 1, start)));
+    assert!(recent_trades.update(agg_trade(2, "101", 1, start + TimeDelta::seconds(1))));
+    let volatility = recent_trades.volatility;
+
+    // Redelivered after a reconnect, same id and time
+    assert!(!recent_trades.update(agg_trade(2, "101", 1, start + TimeDelta::seconds(1))));
+
+    assert_eq!(recent_trades.len(), 2);
+    assert_eq!(recent_trades.volatility, volatility);
+}
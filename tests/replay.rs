@@ -0,0 +1,49 @@
+use chrono::{DateTime, TimeDelta};
+use marketmakerlib::{
+    clock::{Clock, MockClock},
+    replay::{ReplayPacer, ReplaySpeed},
+};
+use std::time::Duration;
+
+#[test]
+fn delay_scales_the_recorded_gap_by_the_replay_speed() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let next = start + TimeDelta::milliseconds(400);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+
+    for (speed, expected) in [
+        (ReplaySpeed::MaxSpeed, Duration::ZERO),
+        (ReplaySpeed::Realtime, Duration::from_millis(400)),
+        (ReplaySpeed::Multiplier(4.0), Duration::from_millis(100)),
+        (ReplaySpeed::Multiplier(0.0), Duration::ZERO),
+    ] {
+        let mut pacer = ReplayPacer::new(speed, MockClock::new(start));
+        // Nothing to wait for before the first event
+        assert_eq!(pacer.delay(start), Duration::ZERO);
+        runtime.block_on(pacer.pace(start));
+        assert_eq!(pacer.delay(next), expected);
+        // Out of order events are never delayed
+        assert_eq!(pacer.delay(start - TimeDelta::seconds(1)), Duration::ZERO);
+    }
+}
+
+#[test]
+fn clock_follows_event_time_and_never_moves_back() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let clock = MockClock::new(start);
+    let mut pacer = ReplayPacer::new(ReplaySpeed::MaxSpeed, clock.clone());
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+
+    let later = start + TimeDelta::seconds(30);
+    runtime.block_on(pacer.pace(later));
+    assert_eq!(clock.now(), later);
+
+    runtime.block_on(pacer.pace(start + TimeDelta::seconds(10)));
+    assert_eq!(clock.now(), later);
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, TimeDelta};
+use marketmakerlib::market_maker::{ForwardHorizon, ForwardReturnJoiner};
+use rust_decimal_macros::dec;
+
+#[test]
+fn signals_resolve_once_enough_trades_have_printed() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut joiner = ForwardReturnJoiner::new(ForwardHorizon::Trades(2));
+    joiner.record(start, dec!(0.5), dec!(100));
+    joiner.on_trade();
+    joiner.record(start, dec!(-0.2), dec!(101));
+
+    assert!(joiner.on_mid(start, dec!(102)).is_empty());
+    joiner.on_trade();
+    let outcomes = joiner.on_mid(start, dec!(102));
+
+    // Only the first signal has seen two trades since
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].imbalance, dec!(0.5));
+    assert_eq!(outcomes[0].forward_mid_price, dec!(102));
+    assert_eq!(outcomes[0].forward_return, dec!(0.02));
+    assert_eq!(joiner.pending(), 1);
+}
+
+#[test]
+fn signals_resolve_in_order_once_the_horizon_has_passed() {
+    let start = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let mut joiner = ForwardReturnJoiner::new(ForwardHorizon::Time(TimeDelta::seconds(5)));
+    joiner.record(start, dec!(0.1), dec!(100));
+    joiner.record(start + TimeDelta::seconds(1), dec!(0.2), dec!(100));
+    joiner.record(start + TimeDelta::seconds(9), dec!(0.3), dec!(100));
+
+    assert!(
+        joiner
+            .on_mid(start + TimeDelta::seconds(4), dec!(99))
+            .is_empty()
+    );
+    let outcomes = joiner.on_mid(start + TimeDelta::seconds(6), dec!(99));
+
+    assert_eq!(
+        outcomes
+            .iter()
+            .map(|outcome| (outcome.imbalance, outcome.forward_return))
+            .collect::<Vec<_>>(),
+        [(dec!(0.1), dec!(-0.01)), (dec!(0.2), dec!(-0.01))]
+    );
+    assert_eq!(joiner.pending(), 1);
+}
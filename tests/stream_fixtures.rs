@@ -0,0 +1,51 @@
+use marketmakerlib::{
+    binance::{BinanceMessage, data::BinanceEvent},
+    order_book_state::OrderBookState,
+    testing::{FIXTURE_TIME_MS, wrapped_agg_trade, wrapped_depth, wrapped_trade},
+};
+use rust_decimal_macros::dec;
+
+#[test]
+fn depth_fixture_parses_and_applies_to_the_book() {
+    let data = wrapped_depth(
+        "btcusdt",
+        &[(dec!(100.5), dec!(1.25))],
+        &[(dec!(101), dec!(2)), (dec!(101.5), dec!(0))],
+        5,
+        7,
+    );
+    let Ok(BinanceEvent::DepthUpdate(update)) = BinanceMessage::from_str_into_market_data(&data)
+    else {
+        panic!("expected a depth update from {data}");
+    };
+    assert_eq!(update.symbol, "BTCUSDT");
+    assert_eq!((update.first_update_id, update.final_update_id), (5, 7));
+    assert_eq!(update.event_time.timestamp_millis(), FIXTURE_TIME_MS);
+
+    let mut book = OrderBookState::default();
+    book.apply_update_changes(&update).unwrap();
+    assert_eq!(book.best_bid, Some((dec!(100.5), dec!(1.25))));
+    assert_eq!(book.best_ask, Some((dec!(101), dec!(2))));
+}
+
+#[test]
+fn trade_fixtures_parse_to_their_event_types() {
+    let agg = wrapped_agg_trade("BTCUSDT", 42, dec!(100.1), dec!(0.5), true);
+    match BinanceMessage::from_str_into_market_data(&agg) {
+        Ok(BinanceEvent::AggTrade(trade)) => {
+            assert_eq!((trade.first_trade_id, trade.last_trade_id), (42, 42));
+            assert_eq!(trade.price, dec!(100.1));
+            assert!(trade.buyer_market_maker);
+        }
+        other => panic!("expected an aggregate trade, got {other:?}"),
+    }
+
+    let raw = wrapped_trade("BTCUSDT", 43, dec!(100.2), dec!(0.1), false);
+    match BinanceMessage::from_str_into_market_data(&raw) {
+        Ok(BinanceEvent::Trade(trade)) => {
+            assert_eq!(trade.trade_id, 43);
+            assert_eq!(trade.quantity, dec!(0.1));
+        }
+        other => panic!("expected a trade, got {other:?}"),
+    }
+}
@@ -0,0 +1,130 @@
+use marketmakerlib::{
+    binance::{
+        StreamError,
+        data::BinanceEvent,
+        subscription::{SubscriptionError, SubscriptionManager},
+    },
+    config::{AppConfig, StreamConfig},
+};
+use rust_decimal_macros::dec;
+use std::time::Duration;
+
+fn rejection(id: Option<u64>) -> BinanceEvent {
+    BinanceEvent::StreamError(StreamError {
+        code: 2,
+        msg: "Invalid request: unknown symbol".to_string(),
+        id,
+    })
+}
+
+#[test]
+fn acks_confirm_the_streams_of_their_request() {
+    let mut subscriptions = SubscriptionManager::default();
+    subscriptions.track(1, ["btcusdt@depth", "btcusdt@aggTrade"]);
+    subscriptions.track(2, ["btcusdt@kline_1m"]);
+
+    // Replies to requests we aren't waiting on are left alone
+    assert_eq!(subscriptions.on_event(&BinanceEvent::RequestAck(7)), None);
+
+    assert_eq!(
+        subscriptions.on_event(&BinanceEvent::RequestAck(1)),
+        Some(Ok(vec![
+            "btcusdt@depth".to_string(),
+            "btcusdt@aggTrade".to_string()
+        ]))
+    );
+    assert!(!subscriptions.is_complete());
+    assert_eq!(
+        subscriptions.timed_out(),
+        Some(SubscriptionError::TimedOut {
+            streams: vec!["btcusdt@kline_1m".to_string()],
+        })
+    );
+
+    subscriptions.on_event(&BinanceEvent::RequestAck(2));
+    assert!(subscriptions.is_complete());
+    assert_eq!(subscriptions.confirmed().len(), 3);
+    assert_eq!(subscriptions.timed_out(), None);
+}
+
+#[test]
+fn errors_reject_the_request_with_their_id() {
+    let mut subscriptions = SubscriptionManager::default();
+    subscriptions.track(1, ["foousdt@depth"]);
+
+    // Without an id the error can't be pinned on a request
+    assert_eq!(subscriptions.on_event(&rejection(None)), None);
+
+    let Some(Err(SubscriptionError::Rejected { streams, error })) =
+        subscriptions.on_event(&rejection(Some(1)))
+    else {
+        panic!("expected the request to be rejected");
+    };
+    assert_eq!(streams, ["foousdt@depth"]);
+    assert_eq!(error.code, 2);
+    assert!(subscriptions.is_complete());
+    assert!(subscriptions.confirmed().is_empty());
+}
+
+/// Writes `contents` to a config file unique to this test process
+fn config_file(name: &str, contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("{name}_{}.json", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn example_config_matches_the_defaults() {
+    let example =
+        AppConfig::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/config.example.json")).unwrap();
+    let defaults = AppConfig::default();
+    assert_eq!(example.symbol, defaults.symbol);
+    assert_eq!(example.streams, defaults.streams);
+    assert_eq!(example.run_duration, defaults.run_duration);
+    assert_eq!(example.max_book_levels, defaults.max_book_levels);
+}
+
+#[test]
+fn config_file_only_needs_the_overrides() {
+    let path = config_file(
+        "partial_config",
+        r#"{
+            "symbol": "ETHUSDT",
+            "streams": [{"type": "partial_depth", "levels": 5}, {"type": "kline", "interval": "1h"}],
+            "run_duration": 60,
+            "market_maker": {"order_size": "0.5"}
+        }"#,
+    );
+    let config = AppConfig::from_file(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(config.symbol.as_str(), "ETHUSDT");
+    assert_eq!(
+        config.streams,
+        [
+            StreamConfig::PartialDepth { levels: 5 },
+            StreamConfig::Kline {
+                interval: "1h".to_string()
+            }
+        ]
+    );
+    assert_eq!(config.run_duration, Duration::from_secs(60));
+    assert_eq!(config.market_maker.order_size, dec!(0.5));
+    assert_eq!(config.max_book_levels, AppConfig::default().max_book_levels);
+}
+
+#[test]
+fn invalid_config_files_are_rejected() {
+    for (name, contents) in [
+        ("no_book_levels", r#"{"max_book_levels": 0}"#),
+        ("bad_maker", r#"{"market_maker": {"max_active_orders": 0}}"#),
+        ("unknown_stream", r#"{"streams": [{"type": "order_book"}]}"#),
+        ("not_json", "symbol = BTCUSDT"),
+    ] {
+        let path = config_file(name, contents);
+        let loaded = AppConfig::from_file(&path);
+        std::fs::remove_file(path).unwrap();
+        assert!(loaded.is_err(), "{name} was accepted");
+    }
+    assert!(AppConfig::from_file("does/not/exist.json").is_err());
+}
@@ -0,0 +1,48 @@
+use chrono::Utc;
+use marketmakerlib::{
+    binance::data::{DepthUpdate, OfferData},
+    order_book_state::OrderBookState,
+    symbol::{Symbol, SymbolError},
+};
+use rust_decimal_macros::dec;
+
+#[test]
+fn symbols_are_normalised_and_validated() {
+    let symbol: Symbol = " btcusdt ".parse().unwrap();
+    assert_eq!(symbol.to_string(), "BTCUSDT");
+    assert_eq!(Symbol::new(""), Err(SymbolError::Empty));
+    assert_eq!(
+        Symbol::new("BTC-USDT"),
+        Err(SymbolError::InvalidChar {
+            symbol: "BTC-USDT".to_string(),
+            invalid: '-',
+        })
+    );
+
+    assert_eq!(
+        serde_json::from_str::<Symbol>(r#""ethusdt""#).unwrap(),
+        Symbol::new("ETHUSDT").unwrap()
+    );
+    assert!(serde_json::from_str::<Symbol>(r#""BTC/USDT""#).is_err());
+}
+
+#[test]
+fn book_rejects_updates_for_another_symbol() {
+    let mut book = OrderBookState::default().with_symbol(Symbol::new("BTCUSDT").unwrap());
+    let update = |symbol: &str| DepthUpdate {
+        event_time: Utc::now(),
+        symbol: symbol.to_string(),
+        first_update_id: 1,
+        final_update_id: 1,
+        bids: vec![OfferData {
+            price: dec!(100),
+            size: dec!(1),
+        }],
+        asks: Vec::new(),
+    };
+
+    assert!(book.apply_update_changes(&update("BTCUDST")).is_err());
+    assert!(book.bids.is_empty());
+    assert!(book.apply_update_changes(&update("BTCUSDT")).is_ok());
+    assert_eq!(book.bids.len(), 1);
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use marketmakerlib::{
+    binance::data::{AggregateTrade, TradeEventData},
+    trade_feed::{TradeFeed, TradeSource},
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+const TRADE_TIME: i64 = 1_700_000_000_000;
+
+fn trade(trade_id: u64, price: Decimal, trade_time: DateTime<Utc>) -> TradeEventData {
+    TradeEventData {
+        event_time: trade_time,
+        symbol: "BTCUSDT".to_string(),
+        trade_id,
+        price,
+        quantity: dec!(0.1),
+        trade_time,
+        buyer_market_maker: false,
+    }
+}
+
+fn agg_trade(first_trade_id: u64, last_trade_id: u64, price: Decimal) -> AggregateTrade {
+    serde_json::from_value(serde_json::json!({
+        "E": TRADE_TIME,
+        "s": "BTCUSDT",
+        "a": first_trade_id,
+        "p": price.to_string(),
+        "q": "0.3",
+        "f": first_trade_id,
+        "l": last_trade_id,
+        "T": TRADE_TIME,
+        "m": false,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn raw_trades_are_aggregated_by_price_side_and_time() {
+    let time = DateTime::from_timestamp_millis(TRADE_TIME).unwrap();
+    let mut feed = TradeFeed::new(TradeSource::Trade);
+
+    assert!(feed.on_trade(trade(1, dec!(100), time)).is_none());
+    assert!(feed.on_trade(trade(2, dec!(100), time)).is_none());
+    let aggregated = feed.on_trade(trade(3, dec!(101), time)).unwrap();
+    assert_eq!(aggregated.quantity, dec!(0.2));
+    assert_eq!(aggregated.num_trades(), 2);
+
+    let last = feed.flush().unwrap();
+    assert_eq!(last.price, dec!(101));
+    assert!(feed.flush().is_none());
+}
+
+#[test]
+fn trades_covered_by_both_streams_are_only_counted_once() {
+    let time = DateTime::from_timestamp_millis(TRADE_TIME).unwrap();
+    let mut feed = TradeFeed::new(TradeSource::Both);
+
+    let trades = feed.on_agg_trade(agg_trade(1, 3, dec!(100)));
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].num_trades(), 3);
+
+    for trade_id in 1..=3 {
+        assert!(feed.on_trade(trade(trade_id, dec!(100), time)).is_none());
+    }
+    assert!(feed.flush().is_none());
+
+    // A raw trade the aggregate stream hasn't reported yet is let through...
+    assert!(feed.on_trade(trade(4, dec!(100), time)).is_none());
+    // ...and its aggregate copy is dropped, apart from the newer trade ids
+    let trades = feed.on_agg_trade(agg_trade(4, 4, dec!(100)));
+    assert!(trades.is_empty());
+    let trades = feed.on_agg_trade(agg_trade(5, 5, dec!(101)));
+    assert_eq!(trades.len(), 2);
+    assert_eq!(trades[0].price, dec!(100));
+    assert_eq!(trades[1].price, dec!(101));
+}
+
+#[test]
+fn unselected_stream_is_ignored() {
+    let time = DateTime::from_timestamp_millis(TRADE_TIME).unwrap();
+    let mut feed = TradeFeed::new(TradeSource::AggTrade);
+    assert!(feed.on_trade(trade(1, dec!(100), time)).is_none());
+    assert!(feed.flush().is_none());
+    assert_eq!(feed.on_agg_trade(agg_trade(1, 1, dec!(100))).len(), 1);
+}
+
+#[test]
+fn aggregate_overlapping_raw_trades_is_left_to_them() {
+    let time = DateTime::from_timestamp_millis(TRADE_TIME).unwrap();
+    let mut feed = TradeFeed::new(TradeSource::Both);
+
+    assert!(feed.on_trade(trade(4, dec!(100), time)).is_none());
+    // Trade 4 would be counted twice if the aggregate were let through
+    assert!(feed.on_agg_trade(agg_trade(4, 6, dec!(100))).is_empty());
+
+    // The raw stream delivers the rest of the range
+    assert!(feed.on_trade(trade(5, dec!(100), time)).is_none());
+    assert!(feed.on_trade(trade(6, dec!(100), time)).is_none());
+    let aggregated = feed.flush().unwrap();
+    assert_eq!(aggregated.num_trades(), 3);
+    assert_eq!(aggregated.quantity, dec!(0.3));
+}
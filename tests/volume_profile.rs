@@ -0,0 +1,103 @@
+use chrono::Utc;
+use marketmakerlib::binance::{VolumeProfile, data::TradeEventData};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Trades with increasing ids, repeated ids are ignored as duplicates
+fn trade(price: Decimal, quantity: Decimal, buyer_market_maker: bool) -> TradeEventData {
+    static NEXT_TRADE_ID: AtomicU64 = AtomicU64::new(1);
+    TradeEventData {
+        event_time: Utc::now(),
+        symbol: "BTCUSDT".to_string(),
+        trade_id: NEXT_TRADE_ID.fetch_add(1, Ordering::Relaxed),
+        price,
+        quantity,
+        trade_time: Utc::now(),
+        buyer_market_maker,
+    }
+}
+
+#[test]
+fn value_area_grows_from_the_point_of_control() {
+    let mut profile = VolumeProfile::new(dec!(1));
+    for (price, quantity) in [
+        (dec!(98.5), dec!(1)),
+        (dec!(99.2), dec!(3)),
+        (dec!(100.7), dec!(5)),
+        (dec!(101.1), dec!(2)),
+        (dec!(102.9), dec!(1)),
+    ] {
+        profile.update_from_trade(&trade(price, quantity, false));
+    }
+    profile.update_from_trade(&trade(dec!(100.1), dec!(1), true));
+
+    assert_eq!(profile.point_of_control(), Some(dec!(100)));
+    assert_eq!(profile.total_buy_volume(), dec!(12));
+    assert_eq!(profile.total_sell_volume(), dec!(1));
+    // 6 at the POC, then 99 (3) over 101 (2) reaches 9.1 of 13 traded
+    assert_eq!(profile.value_area(dec!(0.7)), Some((dec!(99), dec!(101))));
+    assert_eq!(
+        profile.value_area(Decimal::ZERO),
+        Some((dec!(100), dec!(100)))
+    );
+    assert_eq!(
+        profile.value_area(Decimal::ONE),
+        Some((dec!(98), dec!(102)))
+    );
+
+    let levels: Vec<_> = profile
+        .levels()
+        .map(|(price, data)| (price, data.total_volume()))
+        .collect();
+    assert_eq!(levels.first(), Some(&(dec!(98), dec!(1))));
+    assert_eq!(levels.len(), 5);
+}
+
+#[test]
+fn empty_profile_has_no_point_of_control() {
+    let profile = VolumeProfile::new(dec!(1));
+    assert_eq!(profile.point_of_control(), None);
+    assert_eq!(profile.value_area(dec!(0.7)), None);
+}
+
+#[test]
+fn price_band_ignores_and_evicts_far_prices() {
+    let mut profile = VolumeProfile::new(dec!(1)).with_price_band_bps(dec!(100));
+    for price in [
+        dec!(95.5),
+        dec!(99.5),
+        dec!(100.5),
+        dec!(101.5),
+        dec!(104.5),
+    ] {
+        profile.update_from_trade(&trade(price, dec!(1), false));
+    }
+    assert_eq!(profile.levels().count(), 5);
+
+    // 1% either side of 100
+    profile.recenter(dec!(100));
+    assert_eq!(profile.band(), Some((dec!(99), dec!(101))));
+    let buckets =
+        |profile: &VolumeProfile| profile.levels().map(|(price, _)| price).collect::<Vec<_>>();
+    assert_eq!(buckets(&profile), [dec!(99), dec!(100), dec!(101)]);
+
+    profile.update_from_trade(&trade(dec!(98.9), dec!(1), false));
+    profile.update_from_trade(&trade(dec!(100.2), dec!(1), true));
+    assert_eq!(buckets(&profile), [dec!(99), dec!(100), dec!(101)]);
+    assert_eq!(profile.total_sell_volume(), dec!(1));
+
+    // Price moves up, the bottom bucket drops out
+    profile.recenter(dec!(102));
+    assert_eq!(buckets(&profile), [dec!(100), dec!(101)]);
+}
+
+#[test]
+fn redelivered_trades_are_counted_once() {
+    let mut profile = VolumeProfile::new(dec!(1));
+    let print = trade(dec!(100), dec!(2), false);
+    profile.update_from_trade(&print);
+    profile.update_from_trade(&print);
+
+    assert_eq!(profile.total_buy_volume(), dec!(2));
+}